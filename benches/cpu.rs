@@ -0,0 +1,76 @@
+use std::time::Instant;
+
+use nesrs::emulator::bus::mock_bus::MockBus;
+use nesrs::emulator::cpu::{asm, CPU};
+
+// Instruction throughput of the CPU core on a flat memory bus
+// cargo bench --bench cpu
+
+const INSTRUCTIONS: usize = 20_000_000;
+const RUNS: usize = 5;
+
+// A mix of loads, stores, arithmetic, read-modify-write, indexed and indirect addressing,
+// branches and subroutine calls, looping forever
+const PROGRAM: &str = "
+        .org $8000
+reset:  ldx #$00
+        ldy #$10
+        lda #$00
+        sta $10
+        lda #$03
+        sta $11
+loop:   lda $0200,x
+        clc
+        adc #$07
+        sta $0200,x
+        eor ($10),y
+        ora $20
+        and #$7F
+        asl a
+        rol $21
+        inc $0300,x
+        dec $22
+        cmp #$40
+        bcc skip
+        sbc #$20
+skip:   jsr work
+        inx
+        bne loop
+        dey
+        bne loop
+        jmp reset
+
+work:   pha
+        txa
+        tay
+        lda ($10),y
+        lsr a
+        sta $23
+        bit $23
+        pla
+        rts
+";
+
+fn main() {
+    let program = asm::assemble(PROGRAM).expect("benchmark program");
+
+    let mut best = f64::MAX;
+    for _ in 0..RUNS {
+        let mut bus = MockBus::new();
+        bus.load_program(&program.bytes, program.origin);
+
+        let mut cpu = CPU::new(bus);
+        cpu.program_counter = program.origin;
+
+        let start = Instant::now();
+        for _ in 0..INSTRUCTIONS {
+            cpu.step();
+        }
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+
+    println!(
+        "{} instructions in {:.3}s: {:.1}M instructions/s (best of {})",
+        INSTRUCTIONS, best, INSTRUCTIONS as f64 / best / 1_000_000.0, RUNS
+    );
+}
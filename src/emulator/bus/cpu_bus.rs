@@ -1,11 +1,27 @@
 use crate::emulator::bus::Bus;
 
+// https://www.nesdev.org/wiki/IRQ
+// The /IRQ line is shared (wired-OR), it stays low while any of the sources holds it
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct IrqSource: u8 {
+        const APU_FRAME_COUNTER = 0b0000_0001;
+        const APU_DMC = 0b0000_0010;
+        const MAPPER = 0b0000_0100;
+        const EXTERNAL = 0b0000_1000;
+    }
+}
+
 pub trait CpuBus {
-    fn read(&self, addr: u16) -> u8;
+    // A CPU read, devices may react to it (PPUSTATUS clears vblank, PPUDATA moves the VRAM address)
+    fn read(&mut self, addr: u16) -> u8;
+
+    // The value a read would return, without side effects. For debuggers, tracers and disassemblers.
+    fn peek(&self, addr: u16) -> u8;
 
-    fn read_u16(&self, addr: u16) -> u16 {
+    fn read_u16(&mut self, addr: u16) -> u16 {
         let lo = self.read(addr) as u16;
-        let hi = self.read(addr + 1) as u16;
+        let hi = self.read(addr.wrapping_add(1)) as u16;
         (hi << 8) | lo
     }
 
@@ -15,53 +31,198 @@ pub trait CpuBus {
         let lo = (data & 0xFF) as u8;
         let hi = (data >> 8) as u8;
         self.write(addr, lo);
-        self.write(addr + 1, hi);
+        self.write(addr.wrapping_add(1), hi);
     }
 
     fn tick(&mut self, cycles: u8);
 
     fn fetch_nmi(&mut self) -> Option<u8>;
+
+    // Level-triggered, each source keeps the line asserted until it is acknowledged
+    fn assert_irq(&mut self, source: IrqSource);
+
+    fn acknowledge_irq(&mut self, source: IrqSource);
+
+    fn is_irq_asserted(&self) -> bool;
+
+    // true once per completed video frame
+    fn is_frame_complete(&mut self) -> bool;
+
+    // Reset line shared with the rest of the machine (PPU, APU, cartridge)
+    fn reset(&mut self) {}
+
+    // PPU (scanline, dot) for trace logs, buses without a PPU report (0, 0)
+    fn ppu_position(&self) -> (u16, u16) {
+        (0, 0)
+    }
+
+    // Page written to $4014 since the last call, the CPU copies it to OAM before its next instruction
+    fn take_oam_dma(&mut self) -> Option<u8> {
+        None
+    }
+}
+
+// Lets a CPU borrow a bus and hand it back afterwards
+impl<T: CpuBus + ?Sized> CpuBus for &mut T {
+    fn read(&mut self, addr: u16) -> u8 {
+        (**self).read(addr)
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        (**self).peek(addr)
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        (**self).write(addr, data)
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        (**self).tick(cycles)
+    }
+
+    fn fetch_nmi(&mut self) -> Option<u8> {
+        (**self).fetch_nmi()
+    }
+
+    fn assert_irq(&mut self, source: IrqSource) {
+        (**self).assert_irq(source)
+    }
+
+    fn acknowledge_irq(&mut self, source: IrqSource) {
+        (**self).acknowledge_irq(source)
+    }
+
+    fn is_irq_asserted(&self) -> bool {
+        (**self).is_irq_asserted()
+    }
+
+    fn is_frame_complete(&mut self) -> bool {
+        (**self).is_frame_complete()
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+
+    fn ppu_position(&self) -> (u16, u16) {
+        (**self).ppu_position()
+    }
+
+    fn take_oam_dma(&mut self) -> Option<u8> {
+        (**self).take_oam_dma()
+    }
 }
 
+// Lets a CPU run on a bus chosen at runtime, Box<dyn CpuBus>
+impl<T: CpuBus + ?Sized> CpuBus for Box<T> {
+    fn read(&mut self, addr: u16) -> u8 {
+        (**self).read(addr)
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        (**self).peek(addr)
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        (**self).write(addr, data)
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        (**self).tick(cycles)
+    }
+
+    fn fetch_nmi(&mut self) -> Option<u8> {
+        (**self).fetch_nmi()
+    }
+
+    fn assert_irq(&mut self, source: IrqSource) {
+        (**self).assert_irq(source)
+    }
+
+    fn acknowledge_irq(&mut self, source: IrqSource) {
+        (**self).acknowledge_irq(source)
+    }
+
+    fn is_irq_asserted(&self) -> bool {
+        (**self).is_irq_asserted()
+    }
+
+    fn is_frame_complete(&mut self) -> bool {
+        (**self).is_frame_complete()
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+
+    fn ppu_position(&self) -> (u16, u16) {
+        (**self).ppu_position()
+    }
+
+    fn take_oam_dma(&mut self) -> Option<u8> {
+        (**self).take_oam_dma()
+    }
+}
 
 impl CpuBus for Bus {
-    fn read(&self, addr: u16) -> u8 {
-        match addr {
+    fn read(&mut self, addr: u16) -> u8 {
+        let data = match addr {
             // Internal RAM + mirroring
             0x0000..=0x1FFF => {
                 self.ram.read(addr & 0x07FF)
             }
             // PPU registers + mirroring
             0x2000..=0x3FFF => {
-                self.ppu.read(0x2000 + (addr & 0x7))
+                let (ppu, mut ppu_bus) = self.ppu_bus();
+                ppu.read(&mut ppu_bus, 0x2000 + (addr & 0x7))
             }
-            // APU & I/O registers
-            0x4000..=0x4015 => {
-                // TODO: Implement APU register reading
-                panic!("not implemented")
+            // APU status is inside the CPU, the read does not reach the data bus
+            0x4015 => {
+                return self.peek(addr);
             }
-            // Controller registers
+            // Controllers drive bits 0-4, the rest is open bus
             0x4016..=0x4017 => {
-                // TODO: Implement controller reading
-                panic!("not implemented")
+                self.controllers[(addr & 0x01) as usize].read() | (self.open_bus & 0xE0)
+            }
+            // Write-only APU registers and the disabled CPU test registers
+            0x4000..=0x401F => {
+                self.peek(addr)
             }
             // Expansion ROM
             0x4020..=0x5FFF => {
-                self.rom.read_expansion(addr)
+                self.rom.read_expansion(addr).unwrap_or(self.open_bus)
             }
             // SRAM
             0x6000..=0x7FFF => {
-                self.rom.read_sram(addr)
+                self.rom.read_sram(addr).unwrap_or(self.open_bus)
             }
             // PRG ROM
             0x8000..=0xFFFF => {
                 self.rom.read_prg(addr)
             }
-            _ => 0
+        };
+
+        self.open_bus = data;
+        data
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x1FFF => self.ram.peek(addr & 0x07FF),
+            0x2000..=0x3FFF => self.ppu.peek(0x2000 + (addr & 0x7)),
+            // todo APU status, bit 5 is open bus
+            0x4015 => self.open_bus & 0x20,
+            0x4016..=0x4017 => self.controllers[(addr & 0x01) as usize].peek() | (self.open_bus & 0xE0),
+            0x4000..=0x401F => self.open_bus,
+            0x4020..=0x5FFF => self.rom.read_expansion(addr).unwrap_or(self.open_bus),
+            0x6000..=0x7FFF => self.rom.read_sram(addr).unwrap_or(self.open_bus),
+            0x8000..=0xFFFF => self.rom.peek_prg(addr),
         }
     }
 
     fn write(&mut self, addr: u16, data: u8) {
+        self.open_bus = data;
+
         match addr {
             // Internal RAM + mirroring
             0x0000..=0x1FFF => {
@@ -69,17 +230,22 @@ impl CpuBus for Bus {
             }
             // PPU registers + mirroring
             0x2000..=0x3FFF => {
-                self.ppu.write(0x2000 + (addr & 0x7), data);
+                let (ppu, mut ppu_bus) = self.ppu_bus();
+                ppu.write(&mut ppu_bus, 0x2000 + (addr & 0x7), data);
             }
-            // APU & I/O registers
-            0x4000..=0x4015 => {
-                // TODO: Implement APU register writing
-                panic!("not implemented")
+            // OAM DMA, the CPU runs the copy once the write is done
+            0x4014 => {
+                self.oam_dma = Some(data);
             }
-            // Controller registers
-            0x4016..=0x4017 => {
-                // TODO: Implement controller writing
-                panic!("not implemented")
+            // Controller strobe, both ports share it
+            0x4016 => {
+                for controller in &mut self.controllers {
+                    controller.write(data);
+                }
+            }
+            // APU & I/O registers, $4017 is the frame counter
+            0x4000..=0x4015 | 0x4017 => {
+                // todo APU, writes are ignored until it exists
             }
             // Expansion ROM
             0x4020..=0x5FFF => {
@@ -101,7 +267,164 @@ impl CpuBus for Bus {
         self.tick(cycles as u16)
     }
 
+    fn reset(&mut self) {
+        self.reset()
+    }
+
+    fn is_frame_complete(&mut self) -> bool {
+        self.ppu.is_frame_complete()
+    }
+
+    fn ppu_position(&self) -> (u16, u16) {
+        self.ppu.position()
+    }
+
+    fn take_oam_dma(&mut self) -> Option<u8> {
+        self.oam_dma.take()
+    }
+
     fn fetch_nmi(&mut self) -> Option<u8> {
+        // the PPU holds the NMI until the CPU polls for it, so a racing $2002 read can still cancel it
+        if self.ppu.fetch_nmi() {
+            self.nmi_interrupt = Some(0xFF);
+        }
         self.nmi_interrupt.take()
     }
-}
\ No newline at end of file
+
+    fn assert_irq(&mut self, source: IrqSource) {
+        self.irq.insert(source);
+    }
+
+    fn acknowledge_irq(&mut self, source: IrqSource) {
+        self.irq.remove(source);
+    }
+
+    fn is_irq_asserted(&self) -> bool {
+        !self.irq.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::rom::mirroring::Mirroring;
+    use crate::emulator::rom::ROM;
+    use crate::emulator::cpu::{StepResult, CPU};
+    use crate::emulator::controller::{Buttons, Port};
+
+    fn prepare_bus() -> Bus {
+        let rom = ROM::new(vec![0xEA; 16384], Vec::new(), 0, Mirroring::Horizontal, false);
+        Bus::new(rom)
+    }
+
+    #[test]
+    fn test_reset_keeps_battery_ram() {
+        let rom = ROM::new(vec![0xEA; 16384], Vec::new(), 0, Mirroring::Horizontal, true);
+        let mut bus = Bus::new(rom);
+
+        bus.write(0x6000, 0x42);
+        bus.write(0x0010, 0x24);
+        CpuBus::reset(&mut bus);
+
+        assert_eq!(bus.read(0x6000), 0x42);
+        assert_eq!(bus.read(0x0010), 0x24);
+    }
+
+    #[test]
+    fn test_unmapped_reads_return_open_bus() {
+        let mut bus = prepare_bus();
+
+        bus.write(0x0010, 0x5A);
+        assert_eq!(bus.read(0x4000), 0x5A);
+        assert_eq!(bus.read(0x4018), 0x5A);
+        // no expansion ROM or SRAM on this cartridge
+        assert_eq!(bus.read(0x5000), 0x5A);
+        assert_eq!(bus.read(0x6000), 0x5A);
+
+        // the last read is what is left on the bus
+        bus.read(0x8000);
+        assert_eq!(bus.read(0x4000), 0xEA);
+        assert_eq!(bus.peek(0x4000), 0xEA);
+    }
+
+    #[test]
+    fn test_write_only_ppu_registers_return_the_ppu_latch() {
+        let mut bus = prepare_bus();
+
+        bus.write(0x2003, 0x42);
+        bus.write(0x0010, 0x00);
+
+        assert_eq!(bus.read(0x2000), 0x42);
+        assert_eq!(bus.peek(0x2005), 0x42);
+        // PPUSTATUS bits 0-4 come from the PPU latch, not the CPU bus
+        assert_eq!(bus.read(0x2002) & 0x1F, 0x02);
+    }
+
+    #[test]
+    fn test_partially_driven_registers() {
+        let mut bus = prepare_bus();
+
+        bus.write(0x0010, 0xFF);
+        assert_eq!(bus.read(0x4016), 0xE0);
+        assert_eq!(bus.read(0x4017), 0xE0);
+
+        // the APU status read does not change the bus
+        bus.write(0x0010, 0x7F);
+        assert_eq!(bus.read(0x4015), 0x20);
+        assert_eq!(bus.read(0x4000), 0x7F);
+    }
+
+    #[test]
+    fn test_oam_dma() {
+        let mut bus = prepare_bus();
+        for offset in 0..=0xFF_u16 {
+            bus.write(0x0300 + offset, offset as u8);
+        }
+        bus.write(0x2003, 0x10);
+
+        // LDA #$03, STA $4014, BIT $00, STA $4014
+        for (offset, &data) in [0xA9, 0x03, 0x8D, 0x14, 0x40, 0x24, 0x00, 0x8D, 0x14, 0x40].iter().enumerate() {
+            bus.write(0x0200 + offset as u16, data);
+        }
+        let mut cpu = CPU::new(bus);
+        cpu.program_counter = 0x0200;
+
+        cpu.step();
+        // the copy starts on cycle 6, an even cycle, so the reads need an alignment cycle
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x8D, cycles: 4 + 514 });
+        assert_eq!(cpu.bus().cycles, 2 + 4 + 514);
+
+        // 3 more cycles put the next copy on an odd cycle
+        cpu.step();
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x8D, cycles: 4 + 513 });
+
+        // OAMADDR wrapped back to where the copy started
+        let mut bus = cpu.into_bus();
+        assert_eq!(bus.peek(0x2004), 0x00);
+        bus.write(0x2003, 0x00);
+        assert_eq!(bus.peek(0x2004), 0xF0);
+        bus.write(0x2003, 0xFF);
+        assert_eq!(bus.peek(0x2004), 0xEF);
+    }
+
+    #[test]
+    fn test_controller_ports() {
+        let mut bus = prepare_bus();
+        bus.set_buttons(Port::One, Buttons::A | Buttons::RIGHT);
+        bus.set_buttons(Port::Two, Buttons::B);
+
+        // frame counter setup in reset code, $4017 writes go to the APU and leave the controllers alone
+        bus.write(0x4017, 0x40);
+
+        // $4016 strobes both ports, the value written stays on the bus as the upper bits
+        bus.write(0x4016, 0x41);
+        bus.write(0x4016, 0x40);
+
+        let port_1: Vec<u8> = (0..9).map(|_| bus.read(0x4016)).collect();
+        assert_eq!(port_1, vec![0x41, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x41, 0x41]);
+
+        assert_eq!(bus.peek(0x4017), 0x40);
+        assert_eq!(bus.read(0x4017), 0x40);
+        assert_eq!(bus.read(0x4017), 0x41);
+    }
+}
@@ -1,9 +1,41 @@
-use crate::emulator::bus::cpu_bus::CpuBus;
+use crate::emulator::bus::cpu_bus::{CpuBus, IrqSource};
+
+// NTSC: 341 * 262 / 3 CPU cycles per frame
+pub const CYCLES_PER_FRAME: usize = 29781;
+
+const DOTS_PER_SCANLINE: usize = 341;
+const SCANLINES_PER_FRAME: usize = 262;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusAccessKind {
+    Read,
+    Write,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BusAccess {
+    // bus cycle count at the time of the access
+    pub cycle: usize,
+    pub address: u16,
+    pub data: u8,
+    pub kind: BusAccessKind,
+}
 
 pub struct MockBus {
     pub memory: [u8; 0x10000],
     pub nmi_interrupt: Option<u8>,
+    pub irq: IrqSource,
     pub cycles: usize,
+    frame_complete: bool,
+    // when set, every read and write is appended to accesses
+    pub record_accesses: bool,
+    pub accesses: Vec<BusAccess>,
+}
+
+impl Default for MockBus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MockBus {
@@ -11,7 +43,11 @@ impl MockBus {
         Self {
             memory: [0; 0x10000],
             nmi_interrupt: None,
-            cycles: 0
+            irq: IrqSource::empty(),
+            cycles: 0,
+            frame_complete: false,
+            record_accesses: false,
+            accesses: Vec::new(),
         }
     }
 
@@ -24,24 +60,65 @@ impl MockBus {
 
         self.memory[start_address as usize..end_address].copy_from_slice(program);
     }
+
+    fn record(&mut self, address: u16, data: u8, kind: BusAccessKind) {
+        if self.record_accesses {
+            self.accesses.push(BusAccess { cycle: self.cycles, address, data, kind });
+        }
+    }
 }
 
 impl CpuBus for MockBus {
-    fn read(&self, addr: u16) -> u8 {
+    fn read(&mut self, addr: u16) -> u8 {
+        let data = self.memory[addr as usize];
+        self.record(addr, data, BusAccessKind::Read);
+        data
+    }
+
+    // not recorded
+    fn peek(&self, addr: u16) -> u8 {
         self.memory[addr as usize]
     }
 
     fn write(&mut self, addr: u16, data: u8) {
+        self.record(addr, data, BusAccessKind::Write);
         self.memory[addr as usize] = data;
     }
 
     fn tick(&mut self, cycles: u8) {
+        let frame = self.cycles / CYCLES_PER_FRAME;
         self.cycles += cycles as usize;
+        if self.cycles / CYCLES_PER_FRAME != frame {
+            self.frame_complete = true;
+        }
     }
 
     fn fetch_nmi(&mut self) -> Option<u8> {
         self.nmi_interrupt.take()
     }
+
+    fn assert_irq(&mut self, source: IrqSource) {
+        self.irq.insert(source);
+    }
+
+    fn acknowledge_irq(&mut self, source: IrqSource) {
+        self.irq.remove(source);
+    }
+
+    fn is_irq_asserted(&self) -> bool {
+        !self.irq.is_empty()
+    }
+
+    fn is_frame_complete(&mut self) -> bool {
+        std::mem::take(&mut self.frame_complete)
+    }
+
+    // a PPU with rendering off, running 3 dots per CPU cycle from (0, 0)
+    fn ppu_position(&self) -> (u16, u16) {
+        let dots = self.cycles * 3;
+        let scanline = (dots / DOTS_PER_SCANLINE) % SCANLINES_PER_FRAME;
+        (scanline as u16, (dots % DOTS_PER_SCANLINE) as u16)
+    }
 }
 
 
@@ -85,6 +162,11 @@ mod tests {
         bus.write_u16(0x3000, 0x1234);
         let value = bus.read_u16(0x3000);
         assert_eq!(value, 0x1234);
+
+        // the high byte wraps to $0000
+        bus.write_u16(0xFFFF, 0x5678);
+        assert_eq!(bus.memory[0x0000], 0x56);
+        assert_eq!(bus.read_u16(0xFFFF), 0x5678);
     }
 
     #[test]
@@ -95,6 +177,48 @@ mod tests {
     }
 
 
+    #[test]
+    fn test_irq_sources_are_independent() {
+        let mut bus = MockBus::new();
+
+        bus.assert_irq(IrqSource::APU_FRAME_COUNTER);
+        bus.assert_irq(IrqSource::MAPPER);
+        assert!(bus.is_irq_asserted());
+
+        bus.acknowledge_irq(IrqSource::APU_FRAME_COUNTER);
+        assert!(bus.is_irq_asserted());
+
+        bus.acknowledge_irq(IrqSource::MAPPER);
+        assert!(!bus.is_irq_asserted());
+    }
+
+    #[test]
+    fn test_record_accesses() {
+        let mut bus = MockBus::new();
+        bus.write(0x10, 0x01);
+        assert!(bus.accesses.is_empty());
+
+        bus.record_accesses = true;
+        bus.tick(1);
+        bus.write(0x20, 0x42);
+        bus.read(0x20);
+
+        assert_eq!(bus.accesses, vec![
+            BusAccess { cycle: 1, address: 0x20, data: 0x42, kind: BusAccessKind::Write },
+            BusAccess { cycle: 1, address: 0x20, data: 0x42, kind: BusAccessKind::Read },
+        ]);
+    }
+
+    #[test]
+    fn test_peek_is_not_recorded() {
+        let mut bus = MockBus::new();
+        bus.memory[0x30] = 0x99;
+        bus.record_accesses = true;
+
+        assert_eq!(bus.peek(0x30), 0x99);
+        assert!(bus.accesses.is_empty());
+    }
+
     #[test]
     fn test_bus_cycles() {
         let mut bus = MockBus::new();
@@ -104,4 +228,17 @@ mod tests {
         bus.tick(2);
         assert_eq!(bus.cycles, 2);
     }
+
+    #[test]
+    fn test_frame_complete() {
+        let mut bus = MockBus::new();
+
+        bus.cycles = CYCLES_PER_FRAME - 2;
+        bus.tick(1);
+        assert!(!bus.is_frame_complete());
+
+        bus.tick(2);
+        assert!(bus.is_frame_complete());
+        assert!(!bus.is_frame_complete());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,29 @@
+use crate::emulator::bus::ppu_bus::PpuBus;
+
+pub struct MockPpuBus {
+    pub memory: [u8; 0x4000],
+}
+
+impl Default for MockPpuBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockPpuBus {
+    pub fn new() -> Self {
+        Self {
+            memory: [0; 0x4000],
+        }
+    }
+}
+
+impl PpuBus for MockPpuBus {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.memory[(addr & 0x3FFF) as usize]
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        self.memory[(addr & 0x3FFF) as usize] = data;
+    }
+}
@@ -1,7 +1,11 @@
 pub mod cpu_bus;
 pub mod mock_bus;
+pub mod mock_ppu_bus;
+pub mod ppu_bus;
 
-use crate::emulator::bus::cpu_bus::CpuBus;
+use crate::emulator::bus::cpu_bus::IrqSource;
+use crate::emulator::bus::ppu_bus::PpuMemoryMap;
+use crate::emulator::controller::{Buttons, Controller, Port};
 use crate::emulator::ppu::PPU;
 use crate::emulator::ram::RAM;
 use crate::emulator::rom::ROM;
@@ -9,8 +13,20 @@ use crate::emulator::rom::ROM;
 pub struct Bus {
     ram: RAM,
     ppu: PPU,
+    // nametable memory behind the PPU bus
+    vram: [u8; 0x1000],
     pub rom: ROM,
     pub nmi_interrupt: Option<u8>,
+    // sources currently holding the IRQ line
+    irq: IrqSource,
+    // indexed by Port
+    controllers: [Controller; 2],
+    // https://www.nesdev.org/wiki/Open_bus_behavior
+    // last value on the CPU data bus, returned by reads nothing responds to
+    open_bus: u8,
+    // https://www.nesdev.org/wiki/PPU_registers#OAMDMA
+    // page written to $4014, waiting for the CPU to copy it
+    oam_dma: Option<u8>,
     cycles: usize,
     // todo
     // apu: APU,
@@ -21,8 +37,13 @@ impl Bus {
         Self {
             ram: RAM::new(),
             ppu: PPU::new(),
+            vram: [0; 0x1000],
             rom,
             nmi_interrupt: None,
+            irq: IrqSource::empty(),
+            controllers: [Controller::new(), Controller::new()],
+            open_bus: 0,
+            oam_dma: None,
             cycles: 0
         }
     }
@@ -31,16 +52,35 @@ impl Bus {
         &self.rom.prg_rom
     }
 
+    pub(crate) fn ppu_bus(&mut self) -> (&mut PPU, PpuMemoryMap<'_>) {
+        (&mut self.ppu, PpuMemoryMap::new(&mut self.rom, &mut self.vram))
+    }
+
+    // Buttons held on a port, the host sets them before each frame
+    pub fn set_buttons(&mut self, port: Port, buttons: Buttons) {
+        self.controllers[port as usize].set_buttons(buttons);
+    }
+
+    pub fn frame_buffer(&self) -> &[u8] {
+        self.ppu.frame_buffer()
+    }
+
+    // Internal RAM, nametables and battery-backed SRAM keep their contents over a reset
+    pub fn reset(&mut self) {
+        self.ppu.reset();
+        self.nmi_interrupt = None;
+        self.irq = IrqSource::empty();
+        self.oam_dma = None;
+    }
+
     pub fn tick(&mut self, cycles: u16) {
         self.cycles += cycles as usize;
 
+        let mut ppu_bus = PpuMemoryMap::new(&mut self.rom, &mut self.vram);
+
         // 3x PPU = 1x CPU
         for _ in 0..(cycles * 3) {
-            self.ppu.tick();
-
-            if self.ppu.fetch_nmi() {
-                self.nmi_interrupt = Some(0xFF);
-            }
+            self.ppu.tick(&mut ppu_bus);
         }
     }
 
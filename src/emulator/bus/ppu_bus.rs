@@ -0,0 +1,117 @@
+use crate::emulator::rom::ROM;
+
+// PPU address space $0000-$3EFF, palette RAM ($3F00-$3FFF) lives inside the PPU
+// https://www.nesdev.org/wiki/PPU_memory_map
+pub trait PpuBus {
+    fn read(&mut self, addr: u16) -> u8;
+
+    fn write(&mut self, addr: u16, data: u8);
+}
+
+pub struct PpuMemoryMap<'a> {
+    rom: &'a mut ROM,
+    // 2KB CIRAM, plus the cartridge's extra 2KB in four-screen mode
+    vram: &'a mut [u8; 0x1000],
+}
+
+impl<'a> PpuMemoryMap<'a> {
+    pub fn new(rom: &'a mut ROM, vram: &'a mut [u8; 0x1000]) -> Self {
+        Self { rom, vram }
+    }
+}
+
+impl PpuBus for PpuMemoryMap<'_> {
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr & 0x3FFF {
+            // Pattern tables (CHR ROM/RAM through the mapper)
+            0x0000..=0x1FFF => {
+                self.rom.read_chr(addr)
+            }
+            // Nametables + mirroring of $2000-$2EFF
+            _ => {
+                let index = self.rom.mirroring().vram_index(addr);
+                self.vram[index]
+            }
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr & 0x3FFF {
+            0x0000..=0x1FFF => {
+                self.rom.write_chr(addr, data)
+            }
+            _ => {
+                let index = self.rom.mirroring().vram_index(addr);
+                self.vram[index] = data;
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::rom::mirroring::Mirroring;
+
+    fn create_rom(mirroring: Mirroring) -> ROM {
+        let chr_rom = (0..8192).map(|i| (i % 256) as u8).collect();
+        ROM::new(vec![0; 16384], chr_rom, 0, mirroring, false)
+    }
+
+    #[test]
+    fn test_pattern_tables_read_chr() {
+        let mut rom = create_rom(Mirroring::Horizontal);
+        let mut vram = [0; 0x1000];
+        let mut bus = PpuMemoryMap::new(&mut rom, &mut vram);
+
+        assert_eq!(bus.read(0x0010), 0x10);
+        assert_eq!(bus.read(0x1FFF), 0xFF);
+    }
+
+    #[test]
+    fn test_horizontal_mirroring() {
+        let mut rom = create_rom(Mirroring::Horizontal);
+        let mut vram = [0; 0x1000];
+        let mut bus = PpuMemoryMap::new(&mut rom, &mut vram);
+
+        bus.write(0x2005, 0x11);
+        bus.write(0x2805, 0x22);
+
+        assert_eq!(bus.read(0x2405), 0x11);
+        assert_eq!(bus.read(0x2C05), 0x22);
+        assert_eq!(bus.read(0x3005), 0x11); // $3000-$3EFF mirrors $2000-$2EFF
+    }
+
+    #[test]
+    fn test_vertical_mirroring() {
+        let mut rom = create_rom(Mirroring::Vertical);
+        let mut vram = [0; 0x1000];
+        let mut bus = PpuMemoryMap::new(&mut rom, &mut vram);
+
+        bus.write(0x2005, 0x11);
+        bus.write(0x2405, 0x22);
+
+        assert_eq!(bus.read(0x2805), 0x11);
+        assert_eq!(bus.read(0x2C05), 0x22);
+    }
+
+    #[test]
+    fn test_four_screen_and_single_screen() {
+        let mut vram = [0; 0x1000];
+
+        let mut rom = create_rom(Mirroring::FourScreen);
+        let mut bus = PpuMemoryMap::new(&mut rom, &mut vram);
+        for (i, addr) in [0x2000, 0x2400, 0x2800, 0x2C00].into_iter().enumerate() {
+            bus.write(addr, i as u8);
+        }
+        for (i, addr) in [0x2000, 0x2400, 0x2800, 0x2C00].into_iter().enumerate() {
+            assert_eq!(bus.read(addr), i as u8);
+        }
+
+        let mut rom = create_rom(Mirroring::SingleScreenUpper);
+        let mut bus = PpuMemoryMap::new(&mut rom, &mut vram);
+        assert_eq!(bus.read(0x2000), 1);
+        assert_eq!(bus.read(0x2C00), 1);
+    }
+}
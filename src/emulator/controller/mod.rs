@@ -0,0 +1,122 @@
+// Standard controller
+// https://www.nesdev.org/wiki/Standard_controller
+// https://www.nesdev.org/wiki/Controller_reading
+
+// One bit per button, in the order they are shifted out
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct Buttons: u8 {
+        const A = 0b0000_0001;
+        const B = 0b0000_0010;
+        const SELECT = 0b0000_0100;
+        const START = 0b0000_1000;
+        const UP = 0b0001_0000;
+        const DOWN = 0b0010_0000;
+        const LEFT = 0b0100_0000;
+        const RIGHT = 0b1000_0000;
+    }
+}
+
+// $4016 and $4017
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Port {
+    One,
+    Two,
+}
+
+#[derive(Default)]
+pub struct Controller {
+    // held down right now, set by the host
+    buttons: Buttons,
+    // bit 0 of the last $4016 write, while set the shift register keeps reloading
+    strobe: bool,
+    // 4021 shift register, refilled with 1s from the serial input
+    shift: u8,
+}
+
+impl Controller {
+    pub fn new() -> Self {
+        Controller::default()
+    }
+
+    pub fn buttons(&self) -> Buttons {
+        self.buttons
+    }
+
+    pub fn set_buttons(&mut self, buttons: Buttons) {
+        self.buttons = buttons;
+    }
+
+    pub fn write(&mut self, data: u8) {
+        self.strobe = data & 0x01 != 0;
+        if self.strobe {
+            self.shift = self.buttons.bits();
+        }
+    }
+
+    // Serial data on bit 0: A, B, Select, Start, Up, Down, Left, Right, then 1s
+    pub fn read(&mut self) -> u8 {
+        let data = self.peek();
+        if !self.strobe {
+            self.shift = (self.shift >> 1) | 0x80;
+        }
+        data
+    }
+
+    pub fn peek(&self) -> u8 {
+        if self.strobe {
+            // the register is reloaded continuously, only A comes out
+            self.buttons.contains(Buttons::A) as u8
+        } else {
+            self.shift & 0x01
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_report(controller: &mut Controller) -> u8 {
+        (0..8).fold(0, |report, bit| report | (controller.read() << bit))
+    }
+
+    #[test]
+    fn test_serial_read() {
+        let mut controller = Controller::new();
+        controller.set_buttons(Buttons::A | Buttons::START | Buttons::LEFT);
+
+        controller.write(1);
+        controller.write(0);
+        assert_eq!(read_report(&mut controller), 0b0100_1001);
+
+        // after the 8th bit the register is empty and reads 1
+        assert_eq!(controller.read(), 1);
+        assert_eq!(controller.read(), 1);
+    }
+
+    #[test]
+    fn test_buttons_latched_on_strobe() {
+        let mut controller = Controller::new();
+        controller.set_buttons(Buttons::B);
+
+        controller.write(1);
+        controller.write(0);
+        controller.set_buttons(Buttons::A);
+        assert_eq!(read_report(&mut controller), Buttons::B.bits());
+    }
+
+    #[test]
+    fn test_strobe_high_reads_a() {
+        let mut controller = Controller::new();
+        controller.set_buttons(Buttons::A | Buttons::B);
+        controller.write(1);
+
+        assert_eq!(controller.read(), 1);
+        assert_eq!(controller.read(), 1);
+
+        controller.set_buttons(Buttons::B);
+        assert_eq!(controller.peek(), 0);
+        assert_eq!(controller.read(), 0);
+    }
+}
@@ -1,7 +1,8 @@
+use crate::emulator::bus::cpu_bus::CpuBus;
 use crate::emulator::cpu::CPU;
 
 // https://www.nesdev.org/obelisk-6502-guide/addressing.html#IMM
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub (super) enum AddressingMode {
     Implicit,
     Accumulator,
@@ -15,7 +16,15 @@ pub (super) enum AddressingMode {
     AbsoluteY,
     Indirect,
     IndirectX,
-    IndirectY
+    IndirectY,
+    // 65C02 only: (zp), JMP (abs,X) and the zero page test + relative branch of BBR and BBS
+    ZeroPageIndirect,
+    AbsoluteIndirectX,
+    ZeroPageRelative,
+}
+
+pub (super) fn page_crossed(base: u16, address: u16) -> bool {
+    (base & 0xFF00) != (address & 0xFF00)
 }
 
 pub trait AddressingModeOperations {
@@ -38,11 +47,15 @@ pub trait AddressingModeOperations {
     fn get_indirect_x(&mut self) -> u8;
     fn get_indirect_y_address(&mut self) -> u16;
     fn get_indirect_y(&mut self) -> u8;
+    fn get_zero_page_indirect_address(&mut self) -> u16;
+    fn get_zero_page_indirect(&mut self) -> u8;
     fn get_relative(&mut self) -> u8;
     fn get_accumulator(&mut self) -> u8;
 }
 
-impl<'a> AddressingModeOperations for CPU<'a> {
+// Every memory access below is one CPU cycle
+// https://www.nesdev.org/6502_cpu.txt
+impl<B: CpuBus> AddressingModeOperations for CPU<B> {
     fn get_immediate(&mut self) -> u8 {
         self.mem_read(self.program_counter)
     }
@@ -57,7 +70,10 @@ impl<'a> AddressingModeOperations for CPU<'a> {
     }
 
     fn get_zero_page_x_address(&mut self) -> u16 {
-        (self.mem_read(self.program_counter).wrapping_add(self.register_x)) as u16
+        let base = self.mem_read(self.program_counter);
+        // dummy read while the index is added
+        self.mem_read(base as u16);
+        base.wrapping_add(self.register_x) as u16
     }
 
     fn get_zero_page_x(&mut self) -> u8 {
@@ -66,7 +82,10 @@ impl<'a> AddressingModeOperations for CPU<'a> {
     }
 
     fn get_zero_page_y_address(&mut self) -> u16 {
-        (self.mem_read(self.program_counter).wrapping_add(self.register_y)) as u16
+        let base = self.mem_read(self.program_counter);
+        // dummy read while the index is added
+        self.mem_read(base as u16);
+        base.wrapping_add(self.register_y) as u16
     }
 
     fn get_zero_page_y(&mut self) -> u8 {
@@ -76,7 +95,7 @@ impl<'a> AddressingModeOperations for CPU<'a> {
 
     fn get_absolute_address(&mut self) -> u16 {
         let low = self.mem_read(self.program_counter) as u16;
-        let high = self.mem_read(self.program_counter + 1) as u16;
+        let high = self.mem_read(self.program_counter.wrapping_add(1)) as u16;
         (high << 8) | low
     }
 
@@ -86,20 +105,24 @@ impl<'a> AddressingModeOperations for CPU<'a> {
     }
 
     fn get_absolute_x_address(&mut self) -> u16 {
-        self.get_absolute_address().wrapping_add(self.register_x as u16)
+        let base = self.get_absolute_address();
+        self.indexed_address(base, self.register_x, true)
     }
 
     fn get_absolute_x(&mut self) -> u8 {
-        let address = self.get_absolute_x_address();
+        let base = self.get_absolute_address();
+        let address = self.indexed_address(base, self.register_x, false);
         self.mem_read(address)
     }
 
     fn get_absolute_y_address(&mut self) -> u16 {
-        self.get_absolute_address().wrapping_add(self.register_y as u16)
+        let base = self.get_absolute_address();
+        self.indexed_address(base, self.register_y, true)
     }
 
     fn get_absolute_y(&mut self) -> u8 {
-        let address = self.get_absolute_y_address();
+        let base = self.get_absolute_address();
+        let address = self.indexed_address(base, self.register_y, false);
         self.mem_read(address)
     }
 
@@ -117,9 +140,11 @@ impl<'a> AddressingModeOperations for CPU<'a> {
 
     fn get_indirect_x_address(&mut self) -> u16 {
         let base = self.mem_read(self.program_counter);
-        let ptr = base.wrapping_add(self.register_x) as u16;
-        let low = self.mem_read(ptr) as u16;
-        let high = self.mem_read(ptr.wrapping_add(1)) as u16;
+        // dummy read while the index is added
+        self.mem_read(base as u16);
+        let ptr = base.wrapping_add(self.register_x);
+        let low = self.mem_read(ptr as u16) as u16;
+        let high = self.mem_read(ptr.wrapping_add(1) as u16) as u16;
         (high << 8) | low
     }
 
@@ -129,15 +154,25 @@ impl<'a> AddressingModeOperations for CPU<'a> {
     }
 
     fn get_indirect_y_address(&mut self) -> u16 {
-        let base = self.mem_read(self.program_counter) as u16;
-        let low = self.mem_read(base) as u16;
-        let high = self.mem_read(base.wrapping_add(1)) as u16;
-        let deref_base = (high << 8) | low;
-        deref_base.wrapping_add(self.register_y as u16)
+        let base = self.get_indirect_y_base();
+        self.indexed_address(base, self.register_y, true)
     }
 
     fn get_indirect_y(&mut self) -> u8 {
-        let address = self.get_indirect_y_address();
+        let base = self.get_indirect_y_base();
+        let address = self.indexed_address(base, self.register_y, false);
+        self.mem_read(address)
+    }
+
+    fn get_zero_page_indirect_address(&mut self) -> u16 {
+        let ptr = self.mem_read(self.program_counter);
+        let low = self.mem_read(ptr as u16) as u16;
+        let high = self.mem_read(ptr.wrapping_add(1) as u16) as u16;
+        (high << 8) | low
+    }
+
+    fn get_zero_page_indirect(&mut self) -> u8 {
+        let address = self.get_zero_page_indirect_address();
         self.mem_read(address)
     }
 
@@ -148,4 +183,33 @@ impl<'a> AddressingModeOperations for CPU<'a> {
     fn get_accumulator(&mut self) -> u8 {
         self.register_a
     }
-}
\ No newline at end of file
+}
+
+// helper
+impl<B: CpuBus> CPU<B> {
+    // The index is added to the low byte first and the high byte is fixed up a cycle later,
+    // meanwhile the CPU reads from the unfixed address. Reads skip that cycle when no page is crossed,
+    // writes and read-modify-writes always take it.
+    pub (super) fn indexed_address(&mut self, base: u16, index: u8, always_fix: bool) -> u16 {
+        let address = base.wrapping_add(index as u16);
+
+        if always_fix || page_crossed(base, address) {
+            if self.variant.is_cmos() {
+                // the 65C02 reads the last address again instead
+                self.repeat_read();
+            } else {
+                self.mem_read((base & 0xFF00) | (address & 0x00FF));
+            }
+        }
+
+        address
+    }
+
+    // Pointer from the zero page, the high byte wraps around within it
+    pub (super) fn get_indirect_y_base(&mut self) -> u16 {
+        let ptr = self.mem_read(self.program_counter);
+        let low = self.mem_read(ptr as u16) as u16;
+        let high = self.mem_read(ptr.wrapping_add(1) as u16) as u16;
+        (high << 8) | low
+    }
+}
@@ -0,0 +1,855 @@
+use std::collections::HashMap;
+
+use crate::emulator::cpu::addressing::AddressingMode;
+use crate::emulator::cpu::{OperationCode, CPU_OPERATION_CODES_LIST};
+
+/*
+Two-pass 6502 assembler built on the operation code table, so it encodes exactly what the CPU and
+the disassembler decode
+
+        .org $8000
+RESULT = $10
+start:  ldx #3
+loop:   dex
+        bne loop
+        stx RESULT      ; zero page, the value is known in the first pass
+        jmp (vector)
+vector: .word start, >start+1
+        .byte 1, "ab", <vector
+
+Expressions: $hex, %binary, decimal, 'c', symbols, * (current address), + - * / & | ^ << >> ( ),
+unary - ~ < (low byte) > (high byte). Forward references are assembled as absolute addresses.
+*/
+
+// Assembled bytes, to be loaded at `origin`. Gaps left by .org are zero filled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Program {
+    pub origin: u16,
+    pub bytes: Vec<u8>,
+}
+
+impl Program {
+    // Copies the program into an image (ROM bank, memory dump) whose first byte is at CPU address `base`
+    pub fn patch(&self, image: &mut [u8], base: u16) -> Result<(), String> {
+        let start = (self.origin as usize).checked_sub(base as usize)
+            .ok_or_else(|| format!("program at ${:04X} starts before the image at ${:04X}", self.origin, base))?;
+        let end = start + self.bytes.len();
+
+        if end > image.len() {
+            return Err(format!("program at ${:04X} does not fit in the image", self.origin));
+        }
+
+        image[start..end].copy_from_slice(&self.bytes);
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct Assembler {
+    // accept unofficial operation codes, the first one in the table is used when there are several
+    pub unofficial: bool,
+}
+
+// Official operation codes only
+pub fn assemble(source: &str) -> Result<Program, String> {
+    Assembler::new().assemble(source)
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Assembler { unofficial: false }
+    }
+
+    pub fn assemble(&self, source: &str) -> Result<Program, String> {
+        let mut symbols = HashMap::new();
+        let items = self.first_pass(source, &mut symbols)?;
+        second_pass(&items, &symbols)
+    }
+
+    // Defines every label and picks the operation code, so the size of each line is known
+    fn first_pass(&self, source: &str, symbols: &mut HashMap<String, i64>) -> Result<Vec<Item>, String> {
+        let mut items = Vec::new();
+        let mut pc: i64 = 0;
+        let mut origin = None;
+
+        for (index, text) in source.lines().enumerate() {
+            let line = index + 1;
+            let error = |message: String| format!("line {}: {}", line, message);
+            let statement = parse_line(text).map_err(error)?;
+
+            if let Some(label) = statement.label {
+                define(symbols, label, pc).map_err(error)?;
+            }
+
+            let kind = match statement.body {
+                Body::Empty => continue,
+                Body::Constant(name, expr) => {
+                    let value = expr.eval(symbols, pc).map_err(error)?;
+                    define(symbols, name, value).map_err(error)?;
+                    continue;
+                }
+                Body::Org(expr) => {
+                    let address = expr.eval(symbols, pc).map_err(error)?;
+                    if !(0..=0xFFFF).contains(&address) {
+                        return Err(error(format!(".org {} is out of the address space", address)));
+                    }
+                    if origin.is_some() && address < pc {
+                        return Err(error(format!(".org ${:04X} is behind ${:04X}", address, pc)));
+                    }
+                    origin.get_or_insert(address);
+                    pc = address;
+                    continue;
+                }
+                Body::Bytes(values) => ItemKind::Bytes(values),
+                Body::Words(values) => ItemKind::Words(values),
+                Body::Instruction(mnemonic, operand) => {
+                    let (op, expr) = self.encode(&mnemonic, operand, symbols, pc).map_err(error)?;
+                    ItemKind::Instruction(op, expr)
+                }
+            };
+
+            origin.get_or_insert(pc);
+            let item = Item { line, address: pc, kind };
+            pc += item.size() as i64;
+
+            if pc > 0x10000 {
+                return Err(error("program runs past $FFFF".to_string()));
+            }
+            items.push(item);
+        }
+
+        Ok(items)
+    }
+
+    // Addressing mode from the operand syntax, zero page when the address is already known to fit
+    fn encode(&self, mnemonic: &str, operand: OperandSyntax, symbols: &HashMap<String, i64>, pc: i64)
+        -> Result<(&'static OperationCode, Option<Expr>), String> {
+        let mnemonic = mnemonic.to_ascii_uppercase();
+        let candidates: Vec<&'static OperationCode> = CPU_OPERATION_CODES_LIST.iter()
+            .filter(|op| op.mnemonic == mnemonic && (op.official || self.unofficial))
+            .collect();
+
+        if candidates.is_empty() {
+            return Err(format!("unknown instruction {}", mnemonic));
+        }
+
+        let has = |mode: AddressingMode| candidates.iter().any(|op| op.addressing_mode == mode);
+        let fits_zero_page = |expr: &Expr| matches!(expr.eval(symbols, pc), Ok(0..=0xFF));
+
+        let (mode, expr) = match operand {
+            OperandSyntax::None if has(AddressingMode::Implicit) => (AddressingMode::Implicit, None),
+            OperandSyntax::None | OperandSyntax::Accumulator => (AddressingMode::Accumulator, None),
+            OperandSyntax::Immediate(expr) => (AddressingMode::Immediate, Some(expr)),
+            OperandSyntax::IndirectX(expr) => (AddressingMode::IndirectX, Some(expr)),
+            OperandSyntax::IndirectY(expr) => (AddressingMode::IndirectY, Some(expr)),
+            OperandSyntax::Parenthesized(expr) if has(AddressingMode::Indirect) => (AddressingMode::Indirect, Some(expr)),
+            OperandSyntax::Parenthesized(expr) | OperandSyntax::Direct(expr, Index::None) if has(AddressingMode::Relative) => {
+                (AddressingMode::Relative, Some(expr))
+            }
+            OperandSyntax::Parenthesized(expr) => direct_mode(expr, Index::None, &has, fits_zero_page),
+            OperandSyntax::Direct(expr, index) => direct_mode(expr, index, &has, fits_zero_page),
+        };
+
+        // official first, then table order
+        let op = candidates.iter()
+            .filter(|op| op.addressing_mode == mode)
+            .min_by_key(|op| !op.official)
+            .ok_or_else(|| format!("{} does not support {:?} addressing", mnemonic, mode))?;
+
+        Ok((op, expr))
+    }
+}
+
+fn direct_mode(expr: Expr, index: Index, has: &dyn Fn(AddressingMode) -> bool, fits_zero_page: impl Fn(&Expr) -> bool)
+    -> (AddressingMode, Option<Expr>) {
+    let (zero_page, absolute) = match index {
+        Index::None => (AddressingMode::ZeroPage, AddressingMode::Absolute),
+        Index::X => (AddressingMode::ZeroPageX, AddressingMode::AbsoluteX),
+        Index::Y => (AddressingMode::ZeroPageY, AddressingMode::AbsoluteY),
+    };
+
+    let mode = if has(zero_page) && (fits_zero_page(&expr) || !has(absolute)) {
+        zero_page
+    } else {
+        absolute
+    };
+
+    (mode, Some(expr))
+}
+
+fn define(symbols: &mut HashMap<String, i64>, name: String, value: i64) -> Result<(), String> {
+    if symbols.contains_key(&name) {
+        return Err(format!("{} is already defined", name));
+    }
+    symbols.insert(name, value);
+    Ok(())
+}
+
+// Every label is known now, evaluates the operands and emits the bytes
+fn second_pass(items: &[Item], symbols: &HashMap<String, i64>) -> Result<Program, String> {
+    let origin = items.first().map_or(0, |item| item.address);
+    let mut bytes = Vec::new();
+
+    for item in items {
+        let error = |message: String| format!("line {}: {}", item.line, message);
+        let pc = item.address;
+
+        bytes.resize((item.address - origin) as usize, 0);
+
+        match &item.kind {
+            ItemKind::Bytes(values) => {
+                for value in values {
+                    match value {
+                        ByteValue::Text(text) => bytes.extend_from_slice(text.as_bytes()),
+                        ByteValue::Expr(expr) => bytes.push(to_byte(expr.eval(symbols, pc).map_err(error)?).map_err(error)?),
+                    }
+                }
+            }
+            ItemKind::Words(values) => {
+                for expr in values {
+                    let word = to_word(expr.eval(symbols, pc).map_err(error)?).map_err(error)?;
+                    bytes.extend_from_slice(&word.to_le_bytes());
+                }
+            }
+            ItemKind::Instruction(op, expr) => {
+                bytes.push(op.code);
+
+                let value = match expr {
+                    Some(expr) => expr.eval(symbols, pc).map_err(error)?,
+                    None => continue,
+                };
+
+                match op.addressing_mode {
+                    AddressingMode::Relative => {
+                        let offset = value - (pc + 2);
+                        if !(-128..=127).contains(&offset) {
+                            return Err(error(format!("branch target ${:04X} is out of range", value)));
+                        }
+                        bytes.push(offset as i8 as u8);
+                    }
+                    AddressingMode::ZeroPage | AddressingMode::ZeroPageX | AddressingMode::ZeroPageY
+                    | AddressingMode::IndirectX | AddressingMode::IndirectY => {
+                        if !(0..=0xFF).contains(&value) {
+                            return Err(error(format!("${:X} is not a zero page address", value)));
+                        }
+                        bytes.push(value as u8);
+                    }
+                    _ if op.bytes == 2 => bytes.push(to_byte(value).map_err(error)?),
+                    _ => bytes.extend_from_slice(&to_word(value).map_err(error)?.to_le_bytes()),
+                }
+            }
+        }
+    }
+
+    Ok(Program { origin: origin as u16, bytes })
+}
+
+fn to_byte(value: i64) -> Result<u8, String> {
+    if (-128..=0xFF).contains(&value) {
+        Ok(value as u8)
+    } else {
+        Err(format!("{} does not fit in a byte", value))
+    }
+}
+
+fn to_word(value: i64) -> Result<u16, String> {
+    if (-32768..=0xFFFF).contains(&value) {
+        Ok(value as u16)
+    } else {
+        Err(format!("{} does not fit in a word", value))
+    }
+}
+
+struct Item {
+    line: usize,
+    address: i64,
+    kind: ItemKind,
+}
+
+enum ItemKind {
+    Bytes(Vec<ByteValue>),
+    Words(Vec<Expr>),
+    Instruction(&'static OperationCode, Option<Expr>),
+}
+
+impl Item {
+    fn size(&self) -> usize {
+        match &self.kind {
+            ItemKind::Bytes(values) => values.iter().map(|value| match value {
+                ByteValue::Text(text) => text.len(),
+                ByteValue::Expr(_) => 1,
+            }).sum(),
+            ItemKind::Words(values) => values.len() * 2,
+            ItemKind::Instruction(op, _) => op.bytes as usize,
+        }
+    }
+}
+
+// parsing
+
+struct Statement {
+    label: Option<String>,
+    body: Body,
+}
+
+enum Body {
+    Empty,
+    Constant(String, Expr),
+    Org(Expr),
+    Bytes(Vec<ByteValue>),
+    Words(Vec<Expr>),
+    Instruction(String, OperandSyntax),
+}
+
+enum ByteValue {
+    Text(String),
+    Expr(Expr),
+}
+
+enum OperandSyntax {
+    None,
+    Accumulator,
+    Immediate(Expr),
+    Direct(Expr, Index),
+    // (expr) - JMP indirect, or just a parenthesized address
+    Parenthesized(Expr),
+    IndirectX(Expr),
+    IndirectY(Expr),
+}
+
+#[derive(Clone, Copy)]
+enum Index {
+    None,
+    X,
+    Y,
+}
+
+fn parse_line(text: &str) -> Result<Statement, String> {
+    let mut parser = Parser::new(strip_comment(text));
+    parser.skip_whitespace();
+
+    let mut label = None;
+    if let Some(name) = parser.peek_identifier() {
+        let after = parser.pos + name.chars().count();
+        let rest: String = parser.chars[after..].iter().collect();
+        let rest = rest.trim_start();
+
+        if rest.starts_with(':') {
+            label = Some(name);
+            parser.pos = parser.chars.len() - rest.len() + 1;
+        } else if rest.starts_with('=') {
+            parser.pos = parser.chars.len() - rest.len() + 1;
+            let expr = parser.expression_to_end()?;
+            return Ok(Statement { label: None, body: Body::Constant(name, expr) });
+        }
+    }
+
+    parser.skip_whitespace();
+    if parser.at_end() {
+        return Ok(Statement { label, body: Body::Empty });
+    }
+
+    let word = parser.word();
+    let body = match word.to_ascii_lowercase().as_str() {
+        ".org" => Body::Org(parser.expression_to_end()?),
+        ".byte" | ".db" => Body::Bytes(parser.byte_list()?),
+        ".word" | ".dw" => Body::Words(parser.expression_list()?),
+        directive if directive.starts_with('.') => return Err(format!("unknown directive {}", word)),
+        _ => Body::Instruction(word, parse_operand(parser.rest().trim())?),
+    };
+
+    Ok(Statement { label, body })
+}
+
+fn strip_comment(text: &str) -> &str {
+    let mut quoted = None;
+    for (index, c) in text.char_indices() {
+        match (c, quoted) {
+            ('"' | '\'', None) => quoted = Some(c),
+            (c, Some(quote)) if c == quote => quoted = None,
+            (';', None) => return &text[..index],
+            _ => {}
+        }
+    }
+    text
+}
+
+fn parse_operand(operand: &str) -> Result<OperandSyntax, String> {
+    let upper = operand.to_ascii_uppercase();
+    let compact: String = upper.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if operand.is_empty() {
+        return Ok(OperandSyntax::None);
+    }
+    if compact == "A" {
+        return Ok(OperandSyntax::Accumulator);
+    }
+    if let Some(expr) = operand.strip_prefix('#') {
+        return Ok(OperandSyntax::Immediate(Parser::new(expr).expression_to_end()?));
+    }
+
+    if compact.starts_with('(') {
+        if compact.ends_with(",X)") {
+            let inner = &operand[1..operand.rfind(',').unwrap_or(operand.len())];
+            return Ok(OperandSyntax::IndirectX(Parser::new(inner).expression_to_end()?));
+        }
+        if compact.ends_with("),Y") {
+            let inner = &operand[1..operand.rfind(')').unwrap_or(operand.len())];
+            return Ok(OperandSyntax::IndirectY(Parser::new(inner).expression_to_end()?));
+        }
+        if compact.ends_with(')') && closing_paren(operand) == Some(operand.len() - 1) {
+            return Ok(OperandSyntax::Parenthesized(Parser::new(&operand[1..operand.len() - 1]).expression_to_end()?));
+        }
+    }
+
+    let (expr, index) = if compact.ends_with(",X") {
+        (&operand[..operand.rfind(',').unwrap_or(operand.len())], Index::X)
+    } else if compact.ends_with(",Y") {
+        (&operand[..operand.rfind(',').unwrap_or(operand.len())], Index::Y)
+    } else {
+        (operand, Index::None)
+    };
+
+    Ok(OperandSyntax::Direct(Parser::new(expr).expression_to_end()?, index))
+}
+
+// Byte index of the parenthesis closing the one at the start
+fn closing_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// expressions
+
+const OVERFLOW: &str = "arithmetic overflow";
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Number(i64),
+    Symbol(String),
+    CurrentAddress,
+    Unary(char, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, symbols: &HashMap<String, i64>, pc: i64) -> Result<i64, String> {
+        Ok(match self {
+            Expr::Number(value) => *value,
+            Expr::Symbol(name) => *symbols.get(name).ok_or_else(|| format!("undefined symbol {}", name))?,
+            Expr::CurrentAddress => pc,
+            Expr::Unary(op, expr) => {
+                let value = expr.eval(symbols, pc)?;
+                match op {
+                    '-' => value.checked_neg().ok_or(OVERFLOW)?,
+                    '~' => !value,
+                    '<' => value & 0xFF,
+                    '>' => (value >> 8) & 0xFF,
+                    _ => unreachable!(),
+                }
+            }
+            Expr::Binary(op, left, right) => {
+                let left = left.eval(symbols, pc)?;
+                let right = right.eval(symbols, pc)?;
+                match *op {
+                    "+" => left.checked_add(right).ok_or(OVERFLOW)?,
+                    "-" => left.checked_sub(right).ok_or(OVERFLOW)?,
+                    "*" => left.checked_mul(right).ok_or(OVERFLOW)?,
+                    "/" if right == 0 => return Err("division by zero".to_string()),
+                    "/" => left.checked_div(right).ok_or(OVERFLOW)?,
+                    "&" => left & right,
+                    "|" => left | right,
+                    "^" => left ^ right,
+                    "<<" => u32::try_from(right).ok().and_then(|shift| left.checked_shl(shift)).ok_or(OVERFLOW)?,
+                    ">>" => u32::try_from(right).ok().and_then(|shift| left.checked_shr(shift)).ok_or(OVERFLOW)?,
+                    _ => unreachable!(),
+                }
+            }
+        })
+    }
+}
+
+// lowest precedence first
+const BINARY_OPERATORS: [&[&str]; 6] = [&["|"], &["^"], &["&"], &["<<", ">>"], &["+", "-"], &["*", "/"]];
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(text: &str) -> Self {
+        Parser { chars: text.chars().collect(), pos: 0 }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn rest(&self) -> String {
+        self.chars[self.pos..].iter().collect()
+    }
+
+    // Mnemonic or directive
+    fn word(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| !c.is_whitespace()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn peek_identifier(&self) -> Option<String> {
+        let first = self.peek()?;
+        if !(first.is_ascii_alphabetic() || first == '_') {
+            return None;
+        }
+
+        Some(self.chars[self.pos..].iter()
+            .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+            .collect())
+    }
+
+    fn expression_to_end(&mut self) -> Result<Expr, String> {
+        let expr = self.expression()?;
+        self.expect_end()?;
+        Ok(expr)
+    }
+
+    fn expression_list(&mut self) -> Result<Vec<Expr>, String> {
+        let mut values = vec![self.expression()?];
+        while self.eat(",") {
+            values.push(self.expression()?);
+        }
+        self.expect_end()?;
+        Ok(values)
+    }
+
+    fn byte_list(&mut self) -> Result<Vec<ByteValue>, String> {
+        let mut values = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some('"') {
+                self.pos += 1;
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c != '"') {
+                    self.pos += 1;
+                }
+                if self.at_end() {
+                    return Err("unterminated string".to_string());
+                }
+                values.push(ByteValue::Text(self.chars[start..self.pos].iter().collect()));
+                self.pos += 1;
+            } else {
+                values.push(ByteValue::Expr(self.expression()?));
+            }
+
+            if !self.eat(",") {
+                break;
+            }
+        }
+        self.expect_end()?;
+        Ok(values)
+    }
+
+    fn expect_end(&mut self) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.at_end() {
+            Ok(())
+        } else {
+            Err(format!("unexpected '{}'", self.rest()))
+        }
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let matches = token.chars().enumerate().all(|(offset, c)| self.chars.get(self.pos + offset) == Some(&c));
+        if matches {
+            self.pos += token.chars().count();
+        }
+        matches
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        let operand = |parser: &mut Self| if level + 1 < BINARY_OPERATORS.len() {
+            parser.binary(level + 1)
+        } else {
+            parser.unary()
+        };
+
+        let mut left = operand(self)?;
+        'outer: loop {
+            for op in BINARY_OPERATORS[level] {
+                if self.eat(op) {
+                    let right = operand(self)?;
+                    left = Expr::Binary(op, Box::new(left), Box::new(right));
+                    continue 'outer;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        for op in ['-', '~', '<', '>'] {
+            if self.eat(&op.to_string()) {
+                return Ok(Expr::Unary(op, Box::new(self.unary()?)));
+            }
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        self.skip_whitespace();
+
+        let c = self.peek().ok_or("expression expected")?;
+        match c {
+            '(' => {
+                self.pos += 1;
+                let expr = self.expression()?;
+                if !self.eat(")") {
+                    return Err("')' expected".to_string());
+                }
+                Ok(expr)
+            }
+            '*' => {
+                self.pos += 1;
+                Ok(Expr::CurrentAddress)
+            }
+            '$' => self.number(16, 1),
+            '%' => self.number(2, 1),
+            '0'..='9' => self.number(10, 0),
+            '\'' => {
+                let value = self.chars.get(self.pos + 1).copied().ok_or("character expected")?;
+                if self.chars.get(self.pos + 2) != Some(&'\'') {
+                    return Err("unterminated character".to_string());
+                }
+                self.pos += 3;
+                Ok(Expr::Number(value as i64))
+            }
+            _ => {
+                let name = self.peek_identifier().ok_or_else(|| format!("unexpected '{}'", self.rest()))?;
+                self.pos += name.chars().count();
+                Ok(Expr::Symbol(name))
+            }
+        }
+    }
+
+    fn number(&mut self, radix: u32, prefix: usize) -> Result<Expr, String> {
+        self.pos += prefix;
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_digit(radix)) {
+            self.pos += 1;
+        }
+
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        i64::from_str_radix(&digits, radix)
+            .map(Expr::Number)
+            .map_err(|_| format!("invalid number '{}'", digits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::cpu::disasm;
+
+    #[test]
+    fn test_assemble_addressing_modes() {
+        let program = assemble("
+            .org $8000
+            nop
+            asl
+            asl a
+            lda #$10
+            lda $20
+            ldx $20,y
+            lda $0200
+            sta $0200,x
+            lda $0200,y
+            lda $20,x
+            jmp ($FFFC)
+            lda ($40,x)
+            sta ($42),y
+        ").unwrap();
+
+        assert_eq!(program.origin, 0x8000);
+        assert_eq!(program.bytes, vec![
+            0xEA,
+            0x0A,
+            0x0A,
+            0xA9, 0x10,
+            0xA5, 0x20,
+            0xB6, 0x20,
+            0xAD, 0x00, 0x02,
+            0x9D, 0x00, 0x02,
+            0xB9, 0x00, 0x02,
+            0xB5, 0x20,
+            0x6C, 0xFC, 0xFF,
+            0xA1, 0x40,
+            0x91, 0x42,
+        ]);
+    }
+
+    #[test]
+    fn test_labels_and_branches() {
+        let program = assemble("
+                .org $C000
+        start:  ldx #3
+        loop:   dex
+                bne loop
+                beq done
+                jmp start
+        done:   rts
+        ").unwrap();
+
+        assert_eq!(program.bytes, vec![
+            0xA2, 0x03,
+            0xCA,
+            0xD0, 0xFD,
+            0xF0, 0x03,
+            0x4C, 0x00, 0xC0,
+            0x60,
+        ]);
+    }
+
+    #[test]
+    fn test_forward_reference_is_absolute() {
+        let program = assemble("
+            .org $0600
+            lda data
+            lda value+1
+            rts
+        data:
+            value = $10
+        ").unwrap();
+
+        assert_eq!(program.bytes, vec![0xAD, 0x07, 0x06, 0xAD, 0x11, 0x00, 0x60]);
+    }
+
+    #[test]
+    fn test_directives_and_expressions() {
+        let program = assemble("
+        PPUCTRL = $2000
+                .org $8000
+        table:  .word table, PPUCTRL + 1
+                .byte <table, >table, 'A', \"hi;\" ; comment
+                .byte 2 * (3 + 4), -1, * & $FF, %101 << 1
+                lda #<PPUCTRL
+                sta PPUCTRL|7
+        ").unwrap();
+
+        assert_eq!(program.bytes, vec![
+            0x00, 0x80, 0x01, 0x20,
+            0x00, 0x80, 0x41, b'h', b'i', b';',
+            14, 0xFF, 0x0A, 0x0A,
+            0xA9, 0x00,
+            0x8D, 0x07, 0x20,
+        ]);
+    }
+
+    #[test]
+    fn test_org_gap_is_zero_filled() {
+        let program = assemble("
+            .org $10
+            .byte 1
+            .org $13
+            .byte 2
+        ").unwrap();
+
+        assert_eq!(program, Program { origin: 0x10, bytes: vec![1, 0, 0, 2] });
+    }
+
+    #[test]
+    fn test_errors_report_line() {
+        assert_eq!(assemble("nop\nfoo #1").unwrap_err(), "line 2: unknown instruction FOO");
+        assert_eq!(assemble("ldx $10,x").unwrap_err(), "line 1: LDX does not support AbsoluteX addressing");
+        assert_eq!(assemble(".org $8000\nbne far\n.org $8100\nfar: rts").unwrap_err(), "line 2: branch target $8100 is out of range");
+        assert_eq!(assemble("a: nop\na: nop").unwrap_err(), "line 2: a is already defined");
+        assert_eq!(assemble("lda #256").unwrap_err(), "line 1: 256 does not fit in a byte");
+    }
+
+    #[test]
+    fn test_expression_overflow_is_an_error() {
+        for source in [
+            "lda #$7FFFFFFFFFFFFFFF+1",
+            "lda #-$7FFFFFFFFFFFFFFF-2",
+            "lda #$7FFFFFFFFFFFFFFF*2",
+            "lda #-(-$7FFFFFFFFFFFFFFF-1)",
+            "lda #(-$7FFFFFFFFFFFFFFF-1)/-1",
+            "lda #1<<64",
+            "lda #1>>-1",
+            "lda #$FFFFFFFFFFFFFFFFFF",
+        ] {
+            assert!(assemble(source).is_err(), "{}", source);
+        }
+        assert_eq!(assemble("lda #$7FFFFFFFFFFFFFFF+1").unwrap_err(), "line 1: arithmetic overflow");
+        assert_eq!(assemble("lda #1/0").unwrap_err(), "line 1: division by zero");
+    }
+
+    #[test]
+    fn test_unofficial_operation_codes_are_optional() {
+        assert!(assemble("lax $10").is_err());
+
+        let mut assembler = Assembler::new();
+        assembler.unofficial = true;
+
+        assert_eq!(assembler.assemble("lax $10\nsbc #1\nnop #2").unwrap().bytes, vec![0xA7, 0x10, 0xE9, 0x01, 0x80, 0x02]);
+    }
+
+    #[test]
+    fn test_patch_image() {
+        let program = assemble(".org $8002\n.byte 1, 2").unwrap();
+        let mut image = vec![0; 4];
+
+        program.patch(&mut image, 0x8000).unwrap();
+        assert_eq!(image, vec![0, 0, 1, 2]);
+
+        assert!(program.patch(&mut image, 0x7FFF).is_err());
+        assert!(program.patch(&mut image, 0x8003).is_err());
+    }
+
+    // Every operation code disassembles to text that assembles back to the same instruction
+    #[test]
+    fn test_round_trip_with_disassembler() {
+        let mut assembler = Assembler::new();
+        assembler.unofficial = true;
+
+        for op in CPU_OPERATION_CODES_LIST.iter() {
+            let bytes = [op.code, 0x34, 0x12];
+            let instruction = &disasm::disassemble(&bytes, 0x8000)[0];
+            let source = format!(".org $8000\n{} {}", instruction.mnemonic, instruction.operand(&disasm::Labels::new()));
+
+            let program = assembler.assemble(&source).unwrap_or_else(|e| panic!("{:02X} {}: {}", op.code, source, e));
+            let decoded = &disasm::disassemble(&program.bytes, 0x8000)[0];
+
+            assert_eq!(decoded.to_string().get(16..), instruction.to_string().get(16..), "{:02X}", op.code);
+        }
+    }
+}
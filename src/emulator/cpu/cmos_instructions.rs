@@ -0,0 +1,132 @@
+use crate::emulator::bus::cpu_bus::CpuBus;
+use crate::emulator::cpu::addressing::AddressingMode;
+use crate::emulator::cpu::flags::{CpuFlags, FlagOperations};
+use crate::emulator::cpu::stack::StackOperations;
+use crate::emulator::cpu::{AddressingModeOperations, CPU};
+
+// Instructions added by the 65C02
+// http://www.6502.org/tutorials/65c02opcodes.html
+// https://www.westerndesigncenter.com/wdc/documentation/w65c02s.pdf
+
+pub trait CmosInstructions {
+    fn bbr(&mut self, bit: u8);
+    fn bbs(&mut self, bit: u8);
+    fn bra(&mut self);
+    fn nop_long(&mut self);
+    fn phx(&mut self);
+    fn phy(&mut self);
+    fn plx(&mut self);
+    fn ply(&mut self);
+    fn rmb(&mut self, bit: u8);
+    fn smb(&mut self, bit: u8);
+    fn stp(&mut self);
+    fn stz(&mut self, mode: &AddressingMode);
+    fn trb(&mut self, mode: &AddressingMode);
+    fn tsb(&mut self, mode: &AddressingMode);
+    fn wai(&mut self);
+}
+
+impl<B: CpuBus> CmosInstructions for CPU<B> {
+    fn bbr(&mut self, bit: u8) {
+        self.branch_on_bit(bit, false);
+    }
+
+    fn bbs(&mut self, bit: u8) {
+        self.branch_on_bit(bit, true);
+    }
+
+    fn bra(&mut self) {
+        self.branch_helper(true);
+    }
+
+    fn nop_long(&mut self) {
+        // $5C reads its absolute operand, then spends five more cycles reading from $FFxx
+        let address = self.get_absolute_address();
+        for _ in 0..5 {
+            self.mem_read(0xFF00 | (address & 0x00FF));
+        }
+    }
+
+    fn phx(&mut self) {
+        self.push_stack(self.register_x);
+    }
+
+    fn phy(&mut self) {
+        self.push_stack(self.register_y);
+    }
+
+    fn plx(&mut self) {
+        self.dummy_stack_read();
+        self.register_x = self.pop_stack();
+        self.update_zero_and_negative_flags(self.register_x);
+    }
+
+    fn ply(&mut self) {
+        self.dummy_stack_read();
+        self.register_y = self.pop_stack();
+        self.update_zero_and_negative_flags(self.register_y);
+    }
+
+    fn rmb(&mut self, bit: u8) {
+        let address = self.get_zero_page_address();
+        let value = self.mem_read(address);
+
+        self.mem_write_modified(address, value, value & !(1 << bit));
+    }
+
+    fn smb(&mut self, bit: u8) {
+        let address = self.get_zero_page_address();
+        let value = self.mem_read(address);
+
+        self.mem_write_modified(address, value, value | (1 << bit));
+    }
+
+    fn stp(&mut self) {
+        // the clock stops until reset, program counter stays on the operation code like JAM
+        self.repeat_read();
+        self.program_counter = self.program_counter.wrapping_sub(1);
+        self.jammed = true;
+    }
+
+    fn stz(&mut self, mode: &AddressingMode) {
+        let address = self.get_address(mode);
+        self.mem_write(address, 0);
+    }
+
+    fn trb(&mut self, mode: &AddressingMode) {
+        let address = self.get_address(mode);
+        let value = self.mem_read(address);
+
+        self.set_flag(CpuFlags::ZERO, value & self.register_a == 0);
+        self.mem_write_modified(address, value, value & !self.register_a);
+    }
+
+    fn tsb(&mut self, mode: &AddressingMode) {
+        let address = self.get_address(mode);
+        let value = self.mem_read(address);
+
+        self.set_flag(CpuFlags::ZERO, value & self.register_a == 0);
+        self.mem_write_modified(address, value, value | self.register_a);
+    }
+
+    fn wai(&mut self) {
+        // step idles from now on until an interrupt line is asserted
+        self.repeat_read();
+        self.waiting = true;
+    }
+}
+
+// helper
+impl<B: CpuBus> CPU<B> {
+    // BBR and BBS: zero page address, then the branch offset as the third byte
+    fn branch_on_bit(&mut self, bit: u8, set: bool) {
+        let address = self.get_zero_page_address();
+        let value = self.mem_read(address);
+        self.repeat_read();
+
+        // branch_helper expects the program counter on the offset of a two byte instruction
+        self.program_counter = self.program_counter.wrapping_add(1);
+        self.branch_helper((value >> bit) & 0x01 == set as u8);
+        self.program_counter = self.program_counter.wrapping_sub(1);
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::emulator::bus::cpu_bus::CpuBus;
+use crate::emulator::cpu::addressing::AddressingMode;
+use crate::emulator::cpu::{CpuVariant, OperationCode};
+
+/*
+6502 disassembler built on the operation code table
+C000  4C F5 C5  JMP $C5F5
+C5F5  A2 00     LDX #$00
+8000  04 A9    *NOP $A9
+Unofficial operation codes are marked with '*', bytes that do not form a whole instruction become .BYTE
+*/
+
+// Names substituted for addresses in operands, e.g. 0x2002 => "PPUSTATUS"
+pub type Labels = HashMap<u16, String>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub mnemonic: &'static str,
+    pub official: bool,
+    operand: Operand,
+}
+
+// Operand as written in assembly, addresses are kept so labels can replace them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operand {
+    None,
+    Accumulator,
+    Immediate(u8),
+    ZeroPage(u8, &'static str),
+    Absolute(u16, &'static str),
+    Indirect(u16),
+    IndirectX(u8),
+    IndirectY(u8),
+    ZeroPageIndirect(u8),
+    AbsoluteIndirectX(u16),
+    // BBR and BBS, zero page address and branch target
+    ZeroPageRelative(u8, u16),
+    // branch target already resolved to an absolute address
+    Relative(u16),
+    // trailing bytes of an instruction cut off by the end of the range
+    Data,
+}
+
+impl Instruction {
+    // Absolute address the operand refers to, branch targets included
+    pub fn target(&self) -> Option<u16> {
+        match self.operand {
+            Operand::ZeroPage(address, _) | Operand::IndirectX(address) | Operand::IndirectY(address)
+            | Operand::ZeroPageIndirect(address) => Some(address as u16),
+            Operand::Absolute(address, _) | Operand::Indirect(address) | Operand::AbsoluteIndirectX(address)
+            | Operand::Relative(address) | Operand::ZeroPageRelative(_, address) => Some(address),
+            _ => None,
+        }
+    }
+
+    // Next instruction in memory
+    pub fn next_address(&self) -> u16 {
+        self.address.wrapping_add(self.bytes.len() as u16)
+    }
+
+    pub fn operand(&self, labels: &Labels) -> String {
+        let name = |address: u16, text: String| labels.get(&address).cloned().unwrap_or(text);
+
+        match self.operand {
+            Operand::None => String::new(),
+            Operand::Accumulator => "A".to_string(),
+            Operand::Immediate(value) => format!("#${:02X}", value),
+            Operand::ZeroPage(address, index) => {
+                format!("{}{}", name(address as u16, format!("${:02X}", address)), index)
+            }
+            Operand::Absolute(address, index) => format!("{}{}", name(address, format!("${:04X}", address)), index),
+            Operand::Indirect(address) => format!("({})", name(address, format!("${:04X}", address))),
+            Operand::IndirectX(address) => format!("({},X)", name(address as u16, format!("${:02X}", address))),
+            Operand::IndirectY(address) => format!("({}),Y", name(address as u16, format!("${:02X}", address))),
+            Operand::ZeroPageIndirect(address) => format!("({})", name(address as u16, format!("${:02X}", address))),
+            Operand::AbsoluteIndirectX(address) => format!("({},X)", name(address, format!("${:04X}", address))),
+            Operand::ZeroPageRelative(address, target) => format!(
+                "{},{}",
+                name(address as u16, format!("${:02X}", address)),
+                name(target, format!("${:04X}", target))
+            ),
+            Operand::Relative(target) => name(target, format!("${:04X}", target)),
+            Operand::Data => self.bytes.iter().map(|byte| format!("${:02X}", byte)).collect::<Vec<_>>().join(","),
+        }
+    }
+
+    // Listing line: address, raw bytes, mnemonic and operand
+    pub fn format(&self, labels: &Labels) -> String {
+        let hex = self.bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(" ");
+        let marker = if self.official { ' ' } else { '*' };
+        let operand = self.operand(labels);
+
+        let line = format!("{:04X}  {:<8} {}{} {}", self.address, hex, marker, self.mnemonic, operand);
+        line.trim_end().to_string()
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(&Labels::new()))
+    }
+}
+
+// Decodes the whole slice, `origin` is the CPU address of its first byte
+pub fn disassemble(data: &[u8], origin: u16) -> Vec<Instruction> {
+    disassemble_variant(data, origin, CpuVariant::default())
+}
+
+// Same with the operation codes of another 6502 variant
+pub fn disassemble_variant(data: &[u8], origin: u16, variant: CpuVariant) -> Vec<Instruction> {
+    let table = variant.operation_codes();
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+
+    while offset < data.len() {
+        let instruction = decode(table, &data[offset..], origin.wrapping_add(offset as u16));
+        offset += instruction.bytes.len();
+        instructions.push(instruction);
+    }
+
+    instructions
+}
+
+// Decodes CPU memory from `start` to `end` inclusive
+pub fn disassemble_bus(bus: &dyn CpuBus, start: u16, end: u16) -> Vec<Instruction> {
+    let data = peek_bytes(bus, start, (end as usize + 1).saturating_sub(start as usize));
+    disassemble(&data, start)
+}
+
+// `count` bytes of CPU memory from `address`, wrapping at $FFFF. Memory is peeked, so reading
+// I/O registers has no side effects. Shared with the tracer.
+pub(super) fn peek_bytes<B: CpuBus + ?Sized>(bus: &B, address: u16, count: usize) -> Vec<u8> {
+    (0..count).map(|offset| bus.peek(address.wrapping_add(offset as u16))).collect()
+}
+
+// One instruction per line, labels on their own line before the instruction they name
+pub fn listing(instructions: &[Instruction], labels: &Labels) -> String {
+    let mut out = String::new();
+
+    for instruction in instructions {
+        if let Some(label) = labels.get(&instruction.address) {
+            out.push_str(&format!("{}:\n", label));
+        }
+        out.push_str(&instruction.format(labels));
+        out.push('\n');
+    }
+
+    out
+}
+
+fn decode(table: &[OperationCode; 256], data: &[u8], address: u16) -> Instruction {
+    let op = &table[data[0] as usize];
+
+    let length = op.bytes as usize;
+    if data.len() < length {
+        return data_bytes(data, address);
+    }
+
+    Instruction {
+        address,
+        bytes: data[..length].to_vec(),
+        mnemonic: op.mnemonic,
+        official: op.official,
+        operand: decode_operand(op, &data[..length], address),
+    }
+}
+
+fn decode_operand(op: &OperationCode, bytes: &[u8], address: u16) -> Operand {
+    let byte = bytes.get(1).copied().unwrap_or(0);
+    let word = u16::from_le_bytes([byte, bytes.get(2).copied().unwrap_or(0)]);
+
+    match op.addressing_mode {
+        AddressingMode::Implicit => Operand::None,
+        AddressingMode::Accumulator => Operand::Accumulator,
+        AddressingMode::Immediate => Operand::Immediate(byte),
+        AddressingMode::ZeroPage => Operand::ZeroPage(byte, ""),
+        AddressingMode::ZeroPageX => Operand::ZeroPage(byte, ",X"),
+        AddressingMode::ZeroPageY => Operand::ZeroPage(byte, ",Y"),
+        AddressingMode::Relative => Operand::Relative(address.wrapping_add(2).wrapping_add(byte as i8 as u16)),
+        AddressingMode::Absolute => Operand::Absolute(word, ""),
+        AddressingMode::AbsoluteX => Operand::Absolute(word, ",X"),
+        AddressingMode::AbsoluteY => Operand::Absolute(word, ",Y"),
+        AddressingMode::Indirect => Operand::Indirect(word),
+        AddressingMode::IndirectX => Operand::IndirectX(byte),
+        AddressingMode::IndirectY => Operand::IndirectY(byte),
+        AddressingMode::ZeroPageIndirect => Operand::ZeroPageIndirect(byte),
+        AddressingMode::AbsoluteIndirectX => Operand::AbsoluteIndirectX(word),
+        AddressingMode::ZeroPageRelative => {
+            let offset = bytes.get(2).copied().unwrap_or(0) as i8 as u16;
+            Operand::ZeroPageRelative(byte, address.wrapping_add(3).wrapping_add(offset))
+        }
+    }
+}
+
+fn data_bytes(bytes: &[u8], address: u16) -> Instruction {
+    Instruction {
+        address,
+        bytes: bytes.to_vec(),
+        mnemonic: ".BYTE",
+        official: true,
+        operand: Operand::Data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::bus::mock_bus::MockBus;
+
+    fn lines(data: &[u8], origin: u16) -> Vec<String> {
+        disassemble(data, origin).iter().map(|instruction| instruction.to_string()).collect()
+    }
+
+    #[test]
+    fn test_disassemble_addressing_modes() {
+        let program = [
+            0xEA,             // NOP
+            0x0A,             // ASL A
+            0xA9, 0x10,       // LDA #$10
+            0xB6, 0x20,       // LDX $20,Y
+            0x9D, 0x00, 0x02, // STA $0200,X
+            0x6C, 0xFC, 0xFF, // JMP ($FFFC)
+            0xA1, 0x40,       // LDA ($40,X)
+            0x91, 0x42,       // STA ($42),Y
+        ];
+
+        assert_eq!(lines(&program, 0x8000), vec![
+            "8000  EA        NOP",
+            "8001  0A        ASL A",
+            "8002  A9 10     LDA #$10",
+            "8004  B6 20     LDX $20,Y",
+            "8006  9D 00 02  STA $0200,X",
+            "8009  6C FC FF  JMP ($FFFC)",
+            "800C  A1 40     LDA ($40,X)",
+            "800E  91 42     STA ($42),Y",
+        ]);
+    }
+
+    #[test]
+    fn test_disassemble_branch_targets() {
+        let instructions = disassemble(&[0xD0, 0xFE, 0x10, 0x02, 0xF0, 0x80], 0xC000);
+
+        assert_eq!(instructions[0].target(), Some(0xC000));
+        assert_eq!(instructions[1].target(), Some(0xC006));
+        assert_eq!(instructions[2].target(), Some(0xBF86));
+        assert_eq!(instructions[2].to_string(), "C004  F0 80     BEQ $BF86");
+    }
+
+    #[test]
+    fn test_disassemble_unofficial_and_truncated() {
+        assert_eq!(lines(&[0xA7, 0x10, 0x20, 0x00], 0x8000), vec![
+            "8000  A7 10    *LAX $10",
+            "8002  20 00     .BYTE $20,$00",
+        ]);
+    }
+
+    #[test]
+    fn test_disassemble_65c02() {
+        let program = [0xB2, 0x10, 0x7C, 0x00, 0x20, 0x8F, 0x10, 0xFD, 0x03];
+
+        assert_eq!(
+            disassemble_variant(&program, 0x8000, CpuVariant::Cmos65C02)
+                .iter()
+                .map(|instruction| instruction.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "8000  B2 10     LDA ($10)",
+                "8002  7C 00 20  JMP ($2000,X)",
+                "8005  8F 10 FD  BBS0 $10,$8005",
+                "8008  03       *NOP",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_label_substitution() {
+        let mut labels = Labels::new();
+        labels.insert(0x2002, "PPUSTATUS".to_string());
+        labels.insert(0x8000, "wait_vblank".to_string());
+
+        let instructions = disassemble(&[0x2C, 0x02, 0x20, 0x10, 0xFB, 0x60], 0x8000);
+
+        assert_eq!(listing(&instructions, &labels), "\
+wait_vblank:
+8000  2C 02 20  BIT PPUSTATUS
+8003  10 FB     BPL wait_vblank
+8005  60        RTS
+");
+    }
+
+    #[test]
+    fn test_disassemble_bus_range() {
+        let mut bus = MockBus::new();
+        bus.load_program(&[0xA2, 0x05, 0xCA, 0xD0, 0xFD], 0x0600);
+
+        let instructions = disassemble_bus(&bus, 0x0600, 0x0604);
+
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[2].to_string(), "0603  D0 FD     BNE $0602");
+        assert_eq!(instructions[2].next_address(), 0x0605);
+    }
+}
@@ -0,0 +1,344 @@
+use crate::emulator::bus::cpu_bus::CpuBus;
+use crate::emulator::cpu::addressing::AddressingMode;
+use crate::emulator::cpu::cmos_instructions::CmosInstructions;
+use crate::emulator::cpu::instructions::CpuInstructions;
+use crate::emulator::cpu::unofficial_instructions::UnofficialInstructions;
+use crate::emulator::cpu::{OperationCode, CPU, CPU_OPERATION_CODES};
+
+// Runs an instruction in the given addressing mode, the program counter points past the operation code
+pub (super) type Execute<B> = fn(&mut CPU<B>, &AddressingMode);
+
+pub (super) struct Dispatch<B: CpuBus> {
+    pub (super) execute: Execute<B>,
+    // the instruction sets the program counter itself (jumps, BRK, JAM)
+    pub (super) jump: bool,
+}
+
+// not derived, that would require the bus to be Copy
+impl<B: CpuBus> Clone for Dispatch<B> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<B: CpuBus> Copy for Dispatch<B> {}
+
+// Builds the 256-entry table at compile time, a code defined twice or left out fails the build
+struct DispatchTable<B: CpuBus> {
+    entries: [Dispatch<B>; 256],
+    defined: [bool; 256],
+}
+
+impl<B: CpuBus> DispatchTable<B> {
+    const fn new() -> Self {
+        DispatchTable {
+            entries: [Dispatch { execute: |_, _| {}, jump: false }; 256],
+            defined: [false; 256],
+        }
+    }
+
+    const fn with(mut self, codes: &[u8], execute: Execute<B>) -> Self {
+        self.set(codes, Dispatch { execute, jump: false });
+        self
+    }
+
+    const fn with_jump(mut self, codes: &[u8], execute: Execute<B>) -> Self {
+        self.set(codes, Dispatch { execute, jump: true });
+        self
+    }
+
+    // Takes over the entries of the documented operation codes of another table
+    const fn with_documented(mut self, dispatch: &[Dispatch<B>; 256], codes: &[OperationCode; 256]) -> Self {
+        let mut code = 0;
+        while code < 256 {
+            if codes[code].official {
+                self.set(&[code as u8], dispatch[code]);
+            }
+            code += 1;
+        }
+        self
+    }
+
+    const fn set(&mut self, codes: &[u8], dispatch: Dispatch<B>) {
+        let mut i = 0;
+        while i < codes.len() {
+            let code = codes[i] as usize;
+            if self.defined[code] {
+                panic!("Operation code dispatched twice");
+            }
+            self.defined[code] = true;
+            self.entries[code] = dispatch;
+            i += 1;
+        }
+    }
+
+    const fn build(self) -> [Dispatch<B>; 256] {
+        let mut code = 0;
+        while code < 256 {
+            if !self.defined[code] {
+                panic!("Operation code without an instruction");
+            }
+            code += 1;
+        }
+        self.entries
+    }
+}
+
+impl<B: CpuBus> CPU<B> {
+    // Indexed by operation code, the addressing mode and length come from CPU_OPERATION_CODES
+    pub (super) const DISPATCH: [Dispatch<B>; 256] = DispatchTable::new()
+        // BRK - Force Interrupt
+        .with_jump(&[0x00], |cpu, _| cpu.brk())
+        // ADC - Add with Carry
+        .with(&[0x69, 0x65, 0x75, 0x6D, 0x7D, 0x79, 0x61, 0x71], |cpu, mode| cpu.adc(mode))
+        // AND - Logical AND
+        .with(&[0x29, 0x25, 0x35, 0x2D, 0x3D, 0x39, 0x21, 0x31], |cpu, mode| cpu.and(mode))
+        // ASL - Arithmetic Shift Left
+        .with(&[0x0A, 0x06, 0x16, 0x0E, 0x1E], |cpu, mode| cpu.asl(mode))
+        // BCC - Branch if Carry Clear
+        .with(&[0x90], |cpu, _| cpu.bcc())
+        // BCS - Branch if Carry Set
+        .with(&[0xB0], |cpu, _| cpu.bcs())
+        // BEQ - Branch if Equal
+        .with(&[0xF0], |cpu, _| cpu.beq())
+        // BIT - Bit Test
+        .with(&[0x24, 0x2C], |cpu, mode| cpu.bit(mode))
+        // BMI - Branch if Minus
+        .with(&[0x30], |cpu, _| cpu.bmi())
+        // BNE - Branch if Not Equal
+        .with(&[0xD0], |cpu, _| cpu.bne())
+        // BPL - Branch if Positive
+        .with(&[0x10], |cpu, _| cpu.bpl())
+        // BVC - Branch if Overflow Clear
+        .with(&[0x50], |cpu, _| cpu.bvc())
+        // BVS - Branch if Overflow Set
+        .with(&[0x70], |cpu, _| cpu.bvs())
+        // CLC - Clear Carry Flag
+        .with(&[0x18], |cpu, _| cpu.clc())
+        // CLD - Clear Decimal Mode
+        .with(&[0xD8], |cpu, _| cpu.cld())
+        // CLI - Clear Interrupt Disable
+        .with(&[0x58], |cpu, _| cpu.cli())
+        // CLV - Clear Overflow Flag
+        .with(&[0xB8], |cpu, _| cpu.clv())
+        // CMP - Compare
+        .with(&[0xC9, 0xC5, 0xD5, 0xCD, 0xDD, 0xD9, 0xC1, 0xD1], |cpu, mode| cpu.cmp(mode))
+        // CPX - Compare X Register
+        .with(&[0xE0, 0xE4, 0xEC], |cpu, mode| cpu.cpx(mode))
+        // CPY - Compare Y Register
+        .with(&[0xC0, 0xC4, 0xCC], |cpu, mode| cpu.cpy(mode))
+        // DEC - Decrement Memory
+        .with(&[0xC6, 0xD6, 0xCE, 0xDE], |cpu, mode| cpu.dec(mode))
+        // DEX - Decrement X Register
+        .with(&[0xCA], |cpu, _| cpu.dex())
+        // DEY - Decrement Y Register
+        .with(&[0x88], |cpu, _| cpu.dey())
+        // EOR - Exclusive OR
+        .with(&[0x49, 0x45, 0x55, 0x4D, 0x5D, 0x59, 0x41, 0x51], |cpu, mode| cpu.eor(mode))
+        // INC - Increment Memory
+        .with(&[0xE6, 0xF6, 0xEE, 0xFE], |cpu, mode| cpu.inc(mode))
+        // INX - Increment X Register
+        .with(&[0xE8], |cpu, _| cpu.inx())
+        // INY - Increment Y Register
+        .with(&[0xC8], |cpu, _| cpu.iny())
+        // JMP - Jump
+        .with_jump(&[0x4C, 0x6C], |cpu, mode| cpu.jmp(mode))
+        // JSR - Jump to Subroutine
+        .with_jump(&[0x20], |cpu, _| cpu.jsr())
+        // LDA - Load Accumulator
+        .with(&[0xA9, 0xA5, 0xB5, 0xAD, 0xBD, 0xB9, 0xA1, 0xB1], |cpu, mode| cpu.lda(mode))
+        // LDX - Load X Register
+        .with(&[0xA2, 0xA6, 0xB6, 0xAE, 0xBE], |cpu, mode| cpu.ldx(mode))
+        // LDY - Load Y Register
+        .with(&[0xA0, 0xA4, 0xB4, 0xAC, 0xBC], |cpu, mode| cpu.ldy(mode))
+        // LSR - Logical Shift Right
+        .with(&[0x4A, 0x46, 0x56, 0x4E, 0x5E], |cpu, mode| cpu.lsr(mode))
+        // NOP - No Operation
+        .with(&[0xEA], |cpu, _| cpu.nop())
+        // ORA - Logical Inclusive OR
+        .with(&[0x09, 0x05, 0x15, 0x0D, 0x1D, 0x19, 0x01, 0x11], |cpu, mode| cpu.ora(mode))
+        // PHA - Push Accumulator
+        .with(&[0x48], |cpu, _| cpu.pha())
+        // PHP - Push Processor Status
+        .with(&[0x08], |cpu, _| cpu.php())
+        // PLA - Pull Accumulator
+        .with(&[0x68], |cpu, _| cpu.pla())
+        // PLP - Pull Processor Status
+        .with(&[0x28], |cpu, _| cpu.plp())
+        // ROL - Rotate Left
+        .with(&[0x2A, 0x26, 0x36, 0x2E, 0x3E], |cpu, mode| cpu.rol(mode))
+        // ROR - Rotate Right
+        .with(&[0x6A, 0x66, 0x76, 0x6E, 0x7E], |cpu, mode| cpu.ror(mode))
+        // RTI - Return from Interrupt
+        .with(&[0x40], |cpu, _| cpu.rti())
+        // RTS - Return from Subroutine
+        .with(&[0x60], |cpu, _| cpu.rts())
+        // SBC - Subtract with Carry
+        .with(&[0xE9, 0xE5, 0xF5, 0xED, 0xFD, 0xF9, 0xE1, 0xF1], |cpu, mode| cpu.sbc(mode))
+        // SEC - Set Carry Flag
+        .with(&[0x38], |cpu, _| cpu.sec())
+        // SED - Set Decimal Flag
+        .with(&[0xF8], |cpu, _| cpu.sed())
+        // SEI - Set Interrupt Disable
+        .with(&[0x78], |cpu, _| cpu.sei())
+        // STA - Store Accumulator
+        .with(&[0x85, 0x95, 0x8D, 0x9D, 0x99, 0x81, 0x91], |cpu, mode| cpu.sta(mode))
+        // STX - Store X Register
+        .with(&[0x86, 0x96, 0x8E], |cpu, mode| cpu.stx(mode))
+        // STY - Store Y Register
+        .with(&[0x84, 0x94, 0x8C], |cpu, mode| cpu.sty(mode))
+        // TAX - Transfer Accumulator to X
+        .with(&[0xAA], |cpu, _| cpu.tax())
+        // TAY - Transfer Accumulator to Y
+        .with(&[0xA8], |cpu, _| cpu.tay())
+        // TSX - Transfer Stack Pointer to X
+        .with(&[0xBA], |cpu, _| cpu.tsx())
+        // TXA - Transfer X to Accumulator
+        .with(&[0x8A], |cpu, _| cpu.txa())
+        // TXS - Transfer X to Stack Pointer
+        .with(&[0x9A], |cpu, _| cpu.txs())
+        // TYA - Transfer Y to Accumulator
+        .with(&[0x98], |cpu, _| cpu.tya())
+
+        // Unofficial operation codes
+        // ALR - AND + LSR
+        .with(&[0x4B], |cpu, mode| cpu.alr(mode))
+        // ANC - AND, Carry = Negative
+        .with(&[0x0B, 0x2B], |cpu, mode| cpu.anc(mode))
+        // ANE - unstable AND X + AND
+        .with(&[0x8B], |cpu, mode| cpu.ane(mode))
+        // ARR - AND + ROR
+        .with(&[0x6B], |cpu, mode| cpu.arr(mode))
+        // DCP - DEC + CMP
+        .with(&[0xC7, 0xD7, 0xCF, 0xDF, 0xDB, 0xC3, 0xD3], |cpu, mode| cpu.dcp(mode))
+        // ISC - INC + SBC
+        .with(&[0xE7, 0xF7, 0xEF, 0xFF, 0xFB, 0xE3, 0xF3], |cpu, mode| cpu.isc(mode))
+        // JAM - halts the CPU
+        .with_jump(&[0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2], |cpu, _| cpu.jam())
+        // LAS - AND with Stack Pointer into A, X and S
+        .with(&[0xBB], |cpu, mode| cpu.las(mode))
+        // LAX - LDA + LDX
+        .with(&[0xA7, 0xB7, 0xAF, 0xBF, 0xA3, 0xB3], |cpu, mode| cpu.lax(mode))
+        // LXA - unstable LDA + LDX
+        .with(&[0xAB], |cpu, mode| cpu.lxa(mode))
+        // NOP - No Operation, reads its operand
+        .with(&[
+            0x1A, 0x3A, 0x5A, 0x7A, 0xDA, 0xFA,
+            0x80, 0x82, 0x89, 0xC2, 0xE2,
+            0x04, 0x44, 0x64,
+            0x14, 0x34, 0x54, 0x74, 0xD4, 0xF4,
+            0x0C,
+            0x1C, 0x3C, 0x5C, 0x7C, 0xDC, 0xFC,
+        ], |cpu, mode| cpu.nop_read(mode))
+        // RLA - ROL + AND
+        .with(&[0x27, 0x37, 0x2F, 0x3F, 0x3B, 0x23, 0x33], |cpu, mode| cpu.rla(mode))
+        // RRA - ROR + ADC
+        .with(&[0x67, 0x77, 0x6F, 0x7F, 0x7B, 0x63, 0x73], |cpu, mode| cpu.rra(mode))
+        // SAX - Store A AND X
+        .with(&[0x87, 0x97, 0x8F, 0x83], |cpu, mode| cpu.sax(mode))
+        // SBC - Subtract with Carry
+        .with(&[0xEB], |cpu, mode| cpu.sbc(mode))
+        // SBX - (A AND X) - operand into X
+        .with(&[0xCB], |cpu, mode| cpu.sbx(mode))
+        // SHA - Store A AND X AND (high byte + 1)
+        .with(&[0x9F, 0x93], |cpu, mode| cpu.sha(mode))
+        // SHX - Store X AND (high byte + 1)
+        .with(&[0x9E], |cpu, mode| cpu.shx(mode))
+        // SHY - Store Y AND (high byte + 1)
+        .with(&[0x9C], |cpu, mode| cpu.shy(mode))
+        // SLO - ASL + ORA
+        .with(&[0x07, 0x17, 0x0F, 0x1F, 0x1B, 0x03, 0x13], |cpu, mode| cpu.slo(mode))
+        // SRE - LSR + EOR
+        .with(&[0x47, 0x57, 0x4F, 0x5F, 0x5B, 0x43, 0x53], |cpu, mode| cpu.sre(mode))
+        // TAS - A AND X into S, store S AND (high byte + 1)
+        .with(&[0x9B], |cpu, mode| cpu.tas(mode))
+        .build();
+
+    // 65C02, the documented instructions keep their operation codes and check the variant themselves
+    pub (super) const CMOS_DISPATCH: [Dispatch<B>; 256] = DispatchTable::new()
+        .with_documented(&Self::DISPATCH, &CPU_OPERATION_CODES)
+        // (zp) addressing for the accumulator instructions
+        .with(&[0x72], |cpu, mode| cpu.adc(mode))
+        .with(&[0x32], |cpu, mode| cpu.and(mode))
+        .with(&[0xD2], |cpu, mode| cpu.cmp(mode))
+        .with(&[0x52], |cpu, mode| cpu.eor(mode))
+        .with(&[0xB2], |cpu, mode| cpu.lda(mode))
+        .with(&[0x12], |cpu, mode| cpu.ora(mode))
+        .with(&[0xF2], |cpu, mode| cpu.sbc(mode))
+        .with(&[0x92], |cpu, mode| cpu.sta(mode))
+        // BBR - Branch on Bit Reset
+        .with(&[0x0F], |cpu, _| cpu.bbr(0))
+        .with(&[0x1F], |cpu, _| cpu.bbr(1))
+        .with(&[0x2F], |cpu, _| cpu.bbr(2))
+        .with(&[0x3F], |cpu, _| cpu.bbr(3))
+        .with(&[0x4F], |cpu, _| cpu.bbr(4))
+        .with(&[0x5F], |cpu, _| cpu.bbr(5))
+        .with(&[0x6F], |cpu, _| cpu.bbr(6))
+        .with(&[0x7F], |cpu, _| cpu.bbr(7))
+        // BBS - Branch on Bit Set
+        .with(&[0x8F], |cpu, _| cpu.bbs(0))
+        .with(&[0x9F], |cpu, _| cpu.bbs(1))
+        .with(&[0xAF], |cpu, _| cpu.bbs(2))
+        .with(&[0xBF], |cpu, _| cpu.bbs(3))
+        .with(&[0xCF], |cpu, _| cpu.bbs(4))
+        .with(&[0xDF], |cpu, _| cpu.bbs(5))
+        .with(&[0xEF], |cpu, _| cpu.bbs(6))
+        .with(&[0xFF], |cpu, _| cpu.bbs(7))
+        // BIT - Bit Test
+        .with(&[0x89, 0x34, 0x3C], |cpu, mode| cpu.bit(mode))
+        // BRA - Branch Always
+        .with(&[0x80], |cpu, _| cpu.bra())
+        // DEC - Decrement Accumulator
+        .with(&[0x3A], |cpu, mode| cpu.dec(mode))
+        // INC - Increment Accumulator
+        .with(&[0x1A], |cpu, mode| cpu.inc(mode))
+        // JMP - Jump (abs,X)
+        .with_jump(&[0x7C], |cpu, mode| cpu.jmp(mode))
+        // PHX - Push X Register
+        .with(&[0xDA], |cpu, _| cpu.phx())
+        // PHY - Push Y Register
+        .with(&[0x5A], |cpu, _| cpu.phy())
+        // PLX - Pull X Register
+        .with(&[0xFA], |cpu, _| cpu.plx())
+        // PLY - Pull Y Register
+        .with(&[0x7A], |cpu, _| cpu.ply())
+        // RMB - Reset Memory Bit
+        .with(&[0x07], |cpu, _| cpu.rmb(0))
+        .with(&[0x17], |cpu, _| cpu.rmb(1))
+        .with(&[0x27], |cpu, _| cpu.rmb(2))
+        .with(&[0x37], |cpu, _| cpu.rmb(3))
+        .with(&[0x47], |cpu, _| cpu.rmb(4))
+        .with(&[0x57], |cpu, _| cpu.rmb(5))
+        .with(&[0x67], |cpu, _| cpu.rmb(6))
+        .with(&[0x77], |cpu, _| cpu.rmb(7))
+        // SMB - Set Memory Bit
+        .with(&[0x87], |cpu, _| cpu.smb(0))
+        .with(&[0x97], |cpu, _| cpu.smb(1))
+        .with(&[0xA7], |cpu, _| cpu.smb(2))
+        .with(&[0xB7], |cpu, _| cpu.smb(3))
+        .with(&[0xC7], |cpu, _| cpu.smb(4))
+        .with(&[0xD7], |cpu, _| cpu.smb(5))
+        .with(&[0xE7], |cpu, _| cpu.smb(6))
+        .with(&[0xF7], |cpu, _| cpu.smb(7))
+        // STP - Stop the clock
+        .with_jump(&[0xDB], |cpu, _| cpu.stp())
+        // STZ - Store Zero
+        .with(&[0x64, 0x74, 0x9C, 0x9E], |cpu, mode| cpu.stz(mode))
+        // TRB - Test and Reset Bits
+        .with(&[0x14, 0x1C], |cpu, mode| cpu.trb(mode))
+        // TSB - Test and Set Bits
+        .with(&[0x04, 0x0C], |cpu, mode| cpu.tsb(mode))
+        // WAI - Wait for Interrupt
+        .with(&[0xCB], |cpu, _| cpu.wai())
+        // NOP - No Operation, reads its operand
+        .with(&[0x02, 0x22, 0x42, 0x62, 0x82, 0xC2, 0xE2, 0x44, 0x54, 0xD4, 0xF4, 0xDC, 0xFC], |cpu, mode| {
+            cpu.nop_read(mode)
+        })
+        .with(&[0x5C], |cpu, _| cpu.nop_long())
+        // NOP - single cycle
+        .with(&[
+            0x03, 0x13, 0x23, 0x33, 0x43, 0x53, 0x63, 0x73, 0x83, 0x93, 0xA3, 0xB3, 0xC3, 0xD3, 0xE3, 0xF3,
+            0x0B, 0x1B, 0x2B, 0x3B, 0x4B, 0x5B, 0x6B, 0x7B, 0x8B, 0x9B, 0xAB, 0xBB, 0xEB, 0xFB,
+        ], |cpu, _| cpu.nop())
+        .build();
+}
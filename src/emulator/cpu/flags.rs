@@ -13,10 +13,11 @@ NV1B DIZC
 |+-------- Overflow
 +--------- Negative
 */
+use crate::emulator::bus::cpu_bus::CpuBus;
 use crate::emulator::cpu::CPU;
 
 bitflags::bitflags! {
-    #[derive(Clone, Copy)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
     pub struct CpuFlags: u8 {
         const CARRY = 0b0000_0001;
         const ZERO = 0b0000_0010;
@@ -31,8 +32,6 @@ bitflags::bitflags! {
 
 
 pub trait FlagOperations {
-    fn get_status_register(&self) -> u8;
-
     fn set_flag(&mut self, flag: CpuFlags, value: bool);
 
     fn clear_flag(&mut self, flag: CpuFlags);
@@ -46,11 +45,7 @@ pub trait FlagOperations {
     fn update_zero_and_negative_flags(&mut self, result: u8);
 }
 
-impl<'a> FlagOperations for CPU<'a> {
-    fn get_status_register(&self) -> u8 {
-        self.flags.bits()
-    }
-
+impl<B: CpuBus> FlagOperations for CPU<B> {
     fn set_flag(&mut self, flag: CpuFlags, value: bool) {
         self.flags.set(flag, value);
     }
@@ -1,7 +1,9 @@
-
-use crate::emulator::cpu::addressing::AddressingMode;
+use crate::emulator::bus::cpu_bus::CpuBus;
+use crate::emulator::cpu::addressing::{page_crossed, AddressingMode};
 use crate::emulator::cpu::{AddressingModeOperations, CPU};
 use crate::emulator::cpu::flags::{CpuFlags, FlagOperations};
+use crate::emulator::cpu::interrupts;
+use crate::emulator::cpu::interrupts::CpuInterrupts;
 use crate::emulator::cpu::stack::StackOperations;
 
 pub trait CpuInstructions {
@@ -63,22 +65,11 @@ pub trait CpuInstructions {
     fn tya(&mut self);
 }
 
-impl<'a> CpuInstructions for CPU<'a> {
+impl<B: CpuBus> CpuInstructions for CPU<B> {
     fn adc(&mut self, mode: &AddressingMode) {
         let operand = self.get_operand(mode);
 
-        let carry = self.get_flag_value(CpuFlags::CARRY);
-
-        let sum = (self.register_a as u16) + (operand as u16) + carry;
-
-        let overflow = (!(self.register_a ^ operand) & (self.register_a ^ sum as u8) & 0x80) != 0;
-
-        self.set_flag(CpuFlags::CARRY, sum > 0xFF);
-        self.set_flag(CpuFlags::OVERFLOW, overflow);
-
-        self.register_a = sum as u8;
-
-        self.update_zero_and_negative_flags(self.register_a);
+        self.add_with_carry(operand);
     }
 
     fn and(&mut self, mode: &AddressingMode) {
@@ -100,11 +91,11 @@ impl<'a> CpuInstructions for CPU<'a> {
                 self.register_a = result;
             },
             _ => {
-                let addr = self.get_address(mode);
+                let addr = self.get_shift_address(mode);
                 let value = self.mem_read(addr);
                 self.set_flag(CpuFlags::CARRY, value & 0x80 != 0);
                 result = value.wrapping_shl(1);
-                self.mem_write(addr, result)
+                self.mem_write_modified(addr, value, result)
             }
         }
 
@@ -129,6 +120,12 @@ impl<'a> CpuInstructions for CPU<'a> {
     fn bit(&mut self, mode: &AddressingMode) {
         let value = self.get_operand(mode);
 
+        // 65C02 BIT #imm only sets Z
+        if *mode == AddressingMode::Immediate {
+            self.set_flag(CpuFlags::ZERO, self.register_a & value == 0);
+            return;
+        }
+
         if value & 0b1000_0000 != 0 {
             self.insert_flag(CpuFlags::NEGATIVE);
         } else {
@@ -163,19 +160,10 @@ impl<'a> CpuInstructions for CPU<'a> {
     }
 
     fn brk(&mut self) {
-        self.program_counter = self.program_counter.wrapping_add(2);
-
-        self.push_stack((self.program_counter >> 8) as u8);
-        self.push_stack(self.program_counter as u8);
-
-        self.insert_flag(CpuFlags::BREAK);
-        self.insert_flag(CpuFlags::INTERRUPT_DISABLE);
+        // the byte after BRK is padding, the return address skips it
+        self.program_counter = self.program_counter.wrapping_add(1);
 
-        self.push_stack(self.flags.bits());
-
-        let low_byte = self.mem_read(0xFFFE);
-        let high_byte = self.mem_read(0xFFFF);
-        self.program_counter = ((high_byte as u16) << 8) | (low_byte as u16);
+        self.handle_interrupt(interrupts::BRK);
     }
 
     fn bvc(&mut self) {
@@ -206,37 +194,38 @@ impl<'a> CpuInstructions for CPU<'a> {
 
     fn cmp(&mut self, mode: &AddressingMode) {
         let value = self.get_operand(mode);
-        let result = self.register_a.wrapping_sub(value);
 
-        self.set_flag(CpuFlags::CARRY, self.register_a >= value);
-        self.set_flag(CpuFlags::ZERO, self.register_a == value);
-        self.set_flag(CpuFlags::NEGATIVE, result & 0x80 != 0);
+        self.compare(self.register_a, value);
     }
 
     fn cpx(&mut self, mode: &AddressingMode) {
         let value = self.get_operand(mode);
-        let result = self.register_x.wrapping_sub(value);
 
-        self.set_flag(CpuFlags::CARRY, self.register_x >= value);
-        self.set_flag(CpuFlags::ZERO, self.register_x == value);
-        self.set_flag(CpuFlags::NEGATIVE, result & 0x80 != 0);
+        self.compare(self.register_x, value);
     }
 
     fn cpy(&mut self, mode: &AddressingMode) {
         let value = self.get_operand(mode);
-        let result = self.register_y.wrapping_sub(value);
 
-        self.set_flag(CpuFlags::CARRY, self.register_y >= value);
-        self.set_flag(CpuFlags::ZERO, self.register_y == value);
-        self.set_flag(CpuFlags::NEGATIVE, result & 0x80 != 0);
+        self.compare(self.register_y, value);
     }
 
     fn dec(&mut self, mode: &AddressingMode) {
-        let address = self.get_address(mode);
-        let value = self.mem_read(address);
-        let result = value.wrapping_sub(1);
+        let result = match mode {
+            // 65C02 DEC A
+            AddressingMode::Accumulator => {
+                self.register_a = self.register_a.wrapping_sub(1);
+                self.register_a
+            }
+            _ => {
+                let address = self.get_address(mode);
+                let value = self.mem_read(address);
+                let result = value.wrapping_sub(1);
+                self.mem_write_modified(address, value, result);
+                result
+            }
+        };
 
-        self.mem_write(address, result);
         self.update_zero_and_negative_flags(result);
     }
 
@@ -259,11 +248,21 @@ impl<'a> CpuInstructions for CPU<'a> {
     }
 
     fn inc(&mut self, mode: &AddressingMode) {
-        let address = self.get_address(mode);
-        let value = self.mem_read(address);
-        let result = value.wrapping_add(1);
+        let result = match mode {
+            // 65C02 INC A
+            AddressingMode::Accumulator => {
+                self.register_a = self.register_a.wrapping_add(1);
+                self.register_a
+            }
+            _ => {
+                let address = self.get_address(mode);
+                let value = self.mem_read(address);
+                let result = value.wrapping_add(1);
+                self.mem_write_modified(address, value, result);
+                result
+            }
+        };
 
-        self.mem_write(address, result);
         self.update_zero_and_negative_flags(result);
     }
 
@@ -287,7 +286,11 @@ impl<'a> CpuInstructions for CPU<'a> {
                 self.program_counter = address;
             }
             AddressingMode::Indirect => {
-                let target_address = if address & 0x00FF == 0x00FF {
+                let target_address = if self.variant.is_cmos() {
+                    // the 65C02 reads the high byte from the next page, which costs a cycle
+                    self.repeat_read();
+                    self.mem_read_u16(address)
+                } else if address & 0x00FF == 0x00FF {
                     let lo = self.mem_read(address);
                     let hi = self.mem_read(address & 0xFF00);
                     u16::from_le_bytes([lo, hi])
@@ -297,18 +300,26 @@ impl<'a> CpuInstructions for CPU<'a> {
 
                 self.program_counter = target_address;
             }
+            AddressingMode::AbsoluteIndirectX => {
+                self.repeat_read();
+                let pointer = address.wrapping_add(self.register_x as u16);
+                self.program_counter = self.mem_read_u16(pointer);
+            }
             _ => panic!("Unsupported addressing mode for JMP"),
         }
     }
 
     fn jsr(&mut self) {
-        let target_address = self.get_absolute_address();
+        // the high byte of the target is fetched after the return address is pushed
+        let low = self.mem_read(self.program_counter) as u16;
+        self.dummy_stack_read();
 
-        let return_address = self.program_counter.wrapping_add(2) - 1;
+        let return_address = self.program_counter.wrapping_add(1);
         self.push_stack((return_address >> 8) as u8);
         self.push_stack((return_address & 0xFF) as u8);
 
-        self.program_counter = target_address;
+        let high = self.mem_read(return_address) as u16;
+        self.program_counter = (high << 8) | low;
     }
 
     fn lda(&mut self, mode: &AddressingMode) {
@@ -344,13 +355,13 @@ impl<'a> CpuInstructions for CPU<'a> {
                 self.update_zero_and_negative_flags(self.register_a);
             }
             _ => {
-                let address = self.get_address(mode);
-                let mut value = self.mem_read(address);
+                let address = self.get_shift_address(mode);
+                let value = self.mem_read(address);
                 let carry = value & 0x01 != 0;
-                value >>= 1;
-                self.mem_write(address, value);
+                let result = value >> 1;
+                self.mem_write_modified(address, value, result);
                 self.set_flag(CpuFlags::CARRY, carry);
-                self.update_zero_and_negative_flags(value);
+                self.update_zero_and_negative_flags(result);
             }
         }
     }
@@ -377,21 +388,19 @@ impl<'a> CpuInstructions for CPU<'a> {
     }
 
     fn pla(&mut self) {
+        self.dummy_stack_read();
         self.register_a = self.pop_stack();
         self.update_zero_and_negative_flags(self.register_a);
     }
 
     fn plp(&mut self) {
-        let flags = self.pop_stack();
-        let current_break_unused = self.flags.bits() & (CpuFlags::BREAK.bits() | CpuFlags::UNUSED.bits());
-        let new_flags = (flags & !(CpuFlags::BREAK.bits() | CpuFlags::UNUSED.bits())) | current_break_unused;
-
-        self.flags = CpuFlags::from_bits_truncate(new_flags);
+        self.dummy_stack_read();
+        self.pull_status();
     }
 
     fn rol(&mut self, mode: &AddressingMode) {
         let carry = self.contains_flag(CpuFlags::CARRY) as u8;
-        let address = self.get_address(mode);
+        let address = self.get_shift_address(mode);
 
         let value = if *mode == AddressingMode::Accumulator {
             self.register_a
@@ -405,13 +414,13 @@ impl<'a> CpuInstructions for CPU<'a> {
 
         match mode {
             AddressingMode::Accumulator => self.register_a = result,
-            _ => self.mem_write(address, result),
+            _ => self.mem_write_modified(address, value, result),
         }
     }
 
     fn ror(&mut self, mode: &AddressingMode) {
         let carry = (self.contains_flag(CpuFlags::CARRY) as u8) << 7;
-        let address = self.get_address(mode);
+        let address = self.get_shift_address(mode);
 
         let value = if *mode == AddressingMode::Accumulator {
             self.register_a
@@ -425,42 +434,38 @@ impl<'a> CpuInstructions for CPU<'a> {
 
         match mode {
             AddressingMode::Accumulator => self.register_a = result,
-            _ => self.mem_write(address, result),
+            _ => self.mem_write_modified(address, value, result),
         }
     }
 
     fn rti(&mut self) {
-        let status = self.pop_stack();
+        self.dummy_stack_read();
+
+        // P is restored before the interrupt poll, so a cleared I takes effect right away
+        self.pull_status();
+
         let pc_low = self.pop_stack() as u16;
         let pc_high = self.pop_stack() as u16;
 
-        self.flags = CpuFlags::from_bits_truncate(status);
-        self.clear_flag(CpuFlags::BREAK);
-        self.insert_flag(CpuFlags::UNUSED);
-
         self.program_counter = (pc_high << 8) | pc_low;
     }
 
     fn rts(&mut self) {
+        self.dummy_stack_read();
         let pc_low = self.pop_stack() as u16;
         let pc_high = self.pop_stack() as u16;
 
-        self.program_counter = ((pc_high << 8) | pc_low).wrapping_add(1);
+        // the pulled address is read once more while it is incremented
+        let return_address = (pc_high << 8) | pc_low;
+        self.mem_read(return_address);
+
+        self.program_counter = return_address.wrapping_add(1);
     }
 
     fn sbc(&mut self, mode: &AddressingMode) {
         let value = self.get_operand(mode);
-        let carry = if self.contains_flag(CpuFlags::CARRY) { 0 } else { 1 };
 
-        let result = self.register_a as i16 - value as i16 - carry as i16;
-
-        self.set_flag(CpuFlags::CARRY, result >= 0);
-
-        let result = result as u8;
-        self.set_flag(CpuFlags::OVERFLOW, (self.register_a ^ value) & (self.register_a ^ result) & 0x80 != 0);
-
-        self.register_a = result;
-        self.update_zero_and_negative_flags(self.register_a);
+        self.subtract_with_carry(value);
     }
 
     fn sec(&mut self) {
@@ -521,20 +526,171 @@ impl<'a> CpuInstructions for CPU<'a> {
 }
 
 // helper
-impl<'a> CPU<'a> {
-    fn branch_helper(&mut self, condition: bool) {
-        if condition {
-            self.tick(1);
+impl<B: CpuBus> CPU<B> {
+    // PLP and RTI: B and bit 5 are not latches in the CPU, the pulled bits 4 and 5 are dropped
+    // and the held values are kept
+    fn pull_status(&mut self) {
+        let ignored = CpuFlags::BREAK | CpuFlags::UNUSED;
+        let status = CpuFlags::from_bits_truncate(self.pop_stack());
+
+        self.flags = (status - ignored) | (self.flags & ignored);
+    }
+
+    pub (super) fn add_with_carry(&mut self, operand: u8) {
+        if self.is_decimal_mode() {
+            self.add_decimal(operand);
+        } else {
+            self.add_binary(operand);
+        }
+    }
+
+    // A - M - (1 - C) is A + !M + C in two's complement
+    pub (super) fn subtract_with_carry(&mut self, operand: u8) {
+        if self.is_decimal_mode() {
+            self.subtract_decimal(operand);
+        } else {
+            self.add_binary(!operand);
+        }
+    }
+
+    // The 2A03 keeps the D flag but has no decimal mode
+    pub (super) fn is_decimal_mode(&self) -> bool {
+        self.variant.has_decimal_mode() && self.flags.contains(CpuFlags::DECIMAL_MODE)
+    }
+
+    fn add_binary(&mut self, operand: u8) {
+        let carry = self.get_flag_value(CpuFlags::CARRY);
+
+        let sum = (self.register_a as u16) + (operand as u16) + carry;
+
+        let overflow = (!(self.register_a ^ operand) & (self.register_a ^ sum as u8) & 0x80) != 0;
+
+        self.set_flag(CpuFlags::CARRY, sum > 0xFF);
+        self.set_flag(CpuFlags::OVERFLOW, overflow);
+
+        self.register_a = sum as u8;
+
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    // http://www.6502.org/tutorials/decimal_mode.html#A
+    fn add_decimal(&mut self, operand: u8) {
+        let a = self.register_a as u16;
+        let m = operand as u16;
+        let carry = self.get_flag_value(CpuFlags::CARRY);
+
+        let mut low = (a & 0x0F) + (m & 0x0F) + carry;
+        if low >= 0x0A {
+            low = ((low + 0x06) & 0x0F) + 0x10;
+        }
+        let mut sum = (a & 0xF0) + (m & 0xF0) + low;
+
+        // N and V are taken before the high digit is adjusted
+        let negative = sum & 0x80 != 0;
+        let overflow = (!(a ^ m) & (a ^ sum) & 0x80) != 0;
+
+        if sum >= 0xA0 {
+            sum += 0x60;
+        }
 
-            let offset = self.get_relative() as i8;
-            let old_pc = self.program_counter;
-            let new_pc = self.program_counter.wrapping_add(offset as u16);
+        self.set_flag(CpuFlags::CARRY, sum > 0xFF);
+        self.set_flag(CpuFlags::OVERFLOW, overflow);
+        self.register_a = sum as u8;
 
-            if (old_pc & 0xFF00) != (new_pc & 0xFF00) {
-                self.tick(1);
+        if self.variant.is_cmos() {
+            // the 65C02 takes a cycle to set N and Z from the decimal result
+            self.repeat_read();
+            self.update_zero_and_negative_flags(self.register_a);
+        } else {
+            // Z comes from the binary sum
+            self.set_flag(CpuFlags::ZERO, (a + m + carry) & 0xFF == 0);
+            self.set_flag(CpuFlags::NEGATIVE, negative);
+        }
+    }
+
+    // http://www.6502.org/tutorials/decimal_mode.html#A
+    fn subtract_decimal(&mut self, operand: u8) {
+        let a = self.register_a as i16;
+        let m = operand as i16;
+        let borrow = 1 - self.get_flag_value(CpuFlags::CARRY) as i16;
+
+        // C and V (and N and Z on the NMOS 6502) are the same as in binary mode
+        self.add_binary(!operand);
+
+        let mut low = (a & 0x0F) - (m & 0x0F) - borrow;
+        let result = if self.variant.is_cmos() {
+            let mut result = a - m - borrow;
+            if result < 0 {
+                result -= 0x60;
+            }
+            if low < 0 {
+                result -= 0x06;
+            }
+            result
+        } else {
+            if low < 0 {
+                low = ((low - 0x06) & 0x0F) - 0x10;
+            }
+            let mut result = (a & 0xF0) - (m & 0xF0) + low;
+            if result < 0 {
+                result -= 0x60;
+            }
+            result
+        };
+
+        self.register_a = result as u8;
+
+        if self.variant.is_cmos() {
+            self.repeat_read();
+            self.update_zero_and_negative_flags(self.register_a);
+        }
+    }
+
+    // The 65C02 only takes the fix-up cycle of shifts and rotates in absolute,X when a page is crossed
+    fn get_shift_address(&mut self, mode: &AddressingMode) -> u16 {
+        if self.variant.is_cmos() && *mode == AddressingMode::AbsoluteX {
+            let base = self.get_absolute_address();
+            self.indexed_address(base, self.register_x, false)
+        } else {
+            self.get_address(mode)
+        }
+    }
+
+    pub (super) fn compare(&mut self, register: u8, value: u8) {
+        let result = register.wrapping_sub(value);
+
+        self.set_flag(CpuFlags::CARRY, register >= value);
+        self.set_flag(CpuFlags::ZERO, register == value);
+        self.set_flag(CpuFlags::NEGATIVE, result & 0x80 != 0);
+    }
+
+    // https://www.nesdev.org/wiki/6502_cycle_times#Branches
+    pub (super) fn branch_helper(&mut self, condition: bool) {
+        let offset = self.get_relative() as i8;
+
+        if condition {
+            // the offset is relative to the next instruction, program counter is still on the operand
+            let next_pc = self.program_counter.wrapping_add(1);
+            let target = next_pc.wrapping_add(offset as u16);
+
+            // sampled at the end of the operation code fetch
+            let operand_poll = self.previous_poll;
+
+            // taken: the next operation code is read and thrown away while the offset is added
+            self.mem_read(next_pc);
+
+            if page_crossed(next_pc, target) {
+                // and the unfixed address is read while the high byte is fixed up
+                self.mem_read((next_pc & 0xFF00) | (target & 0x00FF));
+            } else {
+                // a taken branch without page crossing doesn't poll again,
+                // an interrupt arriving during it waits for one more instruction
+                // https://www.nesdev.org/wiki/CPU_interrupts#Branch_instructions_and_interrupts
+                self.previous_poll = operand_poll;
             }
 
-            self.program_counter = new_pc;
+            // process_operation steps over the operand
+            self.program_counter = target.wrapping_sub(1);
         }
     }
 }
\ No newline at end of file
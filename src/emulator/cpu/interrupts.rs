@@ -1,64 +1,131 @@
-use bitflags::Flags;
+use crate::emulator::bus::cpu_bus::CpuBus;
 use crate::emulator::cpu::CPU;
 use crate::emulator::cpu::flags::{CpuFlags, FlagOperations};
-use crate::emulator::cpu::instructions::CpuInstructions;
 use crate::emulator::cpu::stack::StackOperations;
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum InterruptType {
-    BRK,
-    IRQ, // APU, unimplemented mappers
-    NMI,
+    Brk,
+    Irq, // APU, mappers
+    Nmi,
 }
 
-#[derive(PartialEq, Eq)]
+// Every interrupt sequence takes 7 cycles: two discarded reads, three pushes and the vector fetch
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub (super) struct Interrupt {
     pub(super) interrupt_type: InterruptType,
     pub(super) vector_addr: u16,
     pub(super) b_flag_mask: u8,
-    pub(super) cpu_cycles: u8,
 }
 
+pub (super) const RESET_VECTOR: u16 = 0xFFFC;
+
 pub (super) const BRK: Interrupt = Interrupt {
-    interrupt_type: InterruptType::BRK,
+    interrupt_type: InterruptType::Brk,
     vector_addr: 0xFFFE,
     b_flag_mask: 0b00110000,
-    cpu_cycles: 7, // BRK op_code
 };
 
 pub (super) const IRQ: Interrupt = Interrupt {
-    interrupt_type: InterruptType::IRQ,
+    interrupt_type: InterruptType::Irq,
     vector_addr: 0xFFFE,
     b_flag_mask: 0b00100000,
-    cpu_cycles: 7 // 2
 };
 
 pub (super) const NMI: Interrupt = Interrupt {
-    interrupt_type: InterruptType::NMI,
+    interrupt_type: InterruptType::Nmi,
     vector_addr: 0xFFFA,
     b_flag_mask: 0b00100000,
-    cpu_cycles: 8 // 2
 };
 
+// Interrupt inputs as seen at the end of a cycle. The sample from the penultimate cycle of an
+// instruction decides whether an interrupt sequence runs before the next one.
+// https://www.nesdev.org/wiki/CPU_interrupts#Detailed_interrupt_behavior
+#[derive(Clone, Copy, Default)]
+pub (super) struct InterruptPoll {
+    pub (super) nmi: bool,
+    pub (super) irq_line: bool,
+    pub (super) irq_enabled: bool,
+}
+
+impl InterruptPoll {
+    fn irq(&self) -> bool {
+        self.irq_line && self.irq_enabled
+    }
+}
+
 pub trait CpuInterrupts {
-    fn handle_interrupt(&mut self, interrupt: Interrupt);
+    fn handle_interrupt(&mut self, interrupt: Interrupt) -> InterruptType;
 }
 
-impl<'a> CpuInterrupts for CPU<'a> {
-    fn handle_interrupt(&mut self, interrupt: Interrupt) {
+impl<B: CpuBus> CpuInterrupts for CPU<B> {
+    // Pushes PC and P, then loads the vector. Returns the interrupt whose vector was used.
+    fn handle_interrupt(&mut self, interrupt: Interrupt) -> InterruptType {
         self.push_stack_u16(self.program_counter);
-        let mut flag = self.flags.clone();
 
-        flag.set(CpuFlags::BREAK, interrupt.b_flag_mask & 0b010000 == 1);
-        flag.set(CpuFlags::UNUSED, interrupt.b_flag_mask & 0b100000 == 1);
+        // an NMI detected by now hijacks a BRK or IRQ sequence, the pushed B flag is kept
+        // https://www.nesdev.org/wiki/CPU_interrupts#Interrupt_hijacking
+        let vector = if interrupt.interrupt_type != InterruptType::Nmi && self.nmi_pending {
+            self.nmi_pending = false;
+            NMI
+        } else {
+            interrupt
+        };
+
+        // B only exists on the stack: set for BRK (and PHP), clear for IRQ and NMI, bit 5 is always set
+        let mut flag = self.flags;
+        flag.set(CpuFlags::BREAK, interrupt.b_flag_mask & CpuFlags::BREAK.bits() != 0);
+        flag.set(CpuFlags::UNUSED, interrupt.b_flag_mask & CpuFlags::UNUSED.bits() != 0);
 
         self.push_stack(flag.bits());
 
         self.insert_flag(CpuFlags::INTERRUPT_DISABLE);
+        // the 65C02 also leaves decimal mode
+        if self.variant.is_cmos() {
+            self.clear_flag(CpuFlags::DECIMAL_MODE);
+        }
 
-        self.tick(interrupt.cpu_cycles);
-
-        let vector_address = self.mem_read_u16(interrupt.vector_addr);
+        let vector_address = self.mem_read_u16(vector.vector_addr);
         self.program_counter = vector_address;
+
+        vector.interrupt_type
     }
-}
\ No newline at end of file
+}
+
+// helper
+impl<B: CpuBus> CPU<B> {
+    // At the end of every cycle: latch an NMI edge and sample the IRQ line and I flag
+    pub (super) fn poll_interrupts(&mut self) {
+        if self.bus.fetch_nmi().is_some() {
+            self.nmi_pending = true;
+        }
+
+        self.previous_poll = self.poll;
+        self.poll = InterruptPoll {
+            nmi: self.nmi_pending,
+            irq_line: self.bus.is_irq_asserted(),
+            irq_enabled: !self.contains_flag(CpuFlags::INTERRUPT_DISABLE),
+        };
+    }
+
+    // TimingMode::Instruction ticks the bus after the accesses, so the lines are sampled once it has,
+    // as if at the penultimate cycle. The I flag keeps its per-cycle sample.
+    pub (super) fn poll_interrupt_lines(&mut self) {
+        if self.bus.fetch_nmi().is_some() {
+            self.nmi_pending = true;
+        }
+
+        self.previous_poll.nmi = self.nmi_pending;
+        self.previous_poll.irq_line = self.bus.is_irq_asserted();
+    }
+
+    pub (super) fn pending_interrupt(&self) -> Option<Interrupt> {
+        if self.previous_poll.nmi {
+            Some(NMI)
+        } else if self.previous_poll.irq() {
+            Some(IRQ)
+        } else {
+            None
+        }
+    }
+}
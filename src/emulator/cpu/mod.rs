@@ -1,274 +1,315 @@
 mod addressing;
 mod operation_codes;
+mod dispatch;
 mod flags;
 mod instructions;
 mod interrupts;
 mod stack;
+mod unofficial_instructions;
+mod cmos_instructions;
+mod variant;
+mod state;
+pub mod trace;
+pub mod disasm;
+pub mod asm;
 
 use crate::emulator::bus::cpu_bus::CpuBus;
 pub use operation_codes::*;
 pub use addressing::*;
-use crate::emulator::cpu::flags::{CpuFlags, FlagOperations};
-use crate::emulator::cpu::instructions::{CpuInstructions};
-use crate::emulator::cpu::interrupts::{CpuInterrupts, Interrupt};
-
-pub struct CPU<'a> {
+use crate::emulator::cpu::flags::FlagOperations;
+use crate::emulator::cpu::interrupts::{CpuInterrupts, InterruptPoll};
+pub use crate::emulator::cpu::flags::CpuFlags;
+pub use crate::emulator::cpu::interrupts::InterruptType;
+pub use crate::emulator::cpu::state::CpuState;
+pub use crate::emulator::cpu::variant::CpuVariant;
+
+pub struct CPU<B: CpuBus> {
     pub (super) register_a: u8,
     pub (super) register_x: u8,
     pub (super) register_y: u8,
     pub (super) stack_pointer: u8,
     pub program_counter: u16,
     pub (super) flags: flags::CpuFlags,
-    pub bus: Box<dyn CpuBus + 'a>,
+    pub cycles: usize,
+    // set by a JAM operation code (STP on the 65C02), cleared by reset
+    pub (super) jammed: bool,
+    // set by WAI, cleared when an interrupt line is asserted
+    pub (super) waiting: bool,
+    // NMI edge latched from the bus, cleared when the interrupt sequence starts
+    nmi_pending: bool,
+    poll: InterruptPoll,
+    previous_poll: InterruptPoll,
+    pub timing_mode: TimingMode,
+    // memory accesses not yet ticked on the bus in TimingMode::Instruction
+    pending_cycles: u8,
+    pub variant: CpuVariant,
+    // address of the last memory access, the 65C02 reads it again in its dummy cycles
+    last_address: u16,
+    bus: B,
+}
+
+// Every memory access of the 6502 takes one cycle, including the dummy reads and writes,
+// so the bus is ticked once per access. The mode decides when.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimingMode {
+    // all accesses of an instruction happen first, then the bus is ticked with the whole cost
+    #[default]
+    Instruction,
+    // the bus is ticked one cycle before each access, so the PPU and APU see it when it happens
+    Cycle,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepResult {
+    Executed { operation_code: u8, cycles: usize },
+    InterruptServiced { interrupt: InterruptType, cycles: usize },
+    // the CPU locked up and only a reset brings it back
+    Jammed,
+    // WAI: one idle cycle spent waiting for an interrupt
+    Waiting,
+}
+
+impl StepResult {
+    pub fn is_halted(&self) -> bool {
+        matches!(self, StepResult::Jammed)
+    }
 }
 
-impl<'a> CPU<'a> {
-    pub fn new<'b>(bus: Box<dyn CpuBus + 'b>) -> CPU<'b> {
-        return CPU {
+impl<B: CpuBus> CPU<B> {
+    pub fn new(bus: B) -> Self {
+        CPU {
             register_a: 0,
             register_x: 0,
             register_y: 0,
             stack_pointer: 0xfd,
             program_counter: 0,
             flags: CpuFlags::INTERRUPT_DISABLE | CpuFlags::BREAK | CpuFlags::UNUSED,
+            cycles: 0,
+            jammed: false,
+            waiting: false,
+            nmi_pending: false,
+            poll: InterruptPoll::default(),
+            previous_poll: InterruptPoll::default(),
+            timing_mode: TimingMode::default(),
+            pending_cycles: 0,
+            variant: CpuVariant::default(),
+            last_address: 0,
             bus,
-        };
+        }
     }
 
-    pub fn interpret(&mut self, program_end: usize) {
-        while (self.program_counter as usize) < program_end {
-            let operation_code = self.mem_read(self.program_counter);
+    pub fn bus(&self) -> &B {
+        &self.bus
+    }
 
-            println!("interpret: op_code: {:#X}", operation_code);
+    pub fn bus_mut(&mut self) -> &mut B {
+        &mut self.bus
+    }
 
-            if !self.process_operation(operation_code) {
-                println!("Program end");
-                break
-            }
+    // Gives the bus back, e.g. when the machine is torn down
+    pub fn into_bus(self) -> B {
+        self.bus
+    }
+
+    // Power-up state: A, X, Y = 0, P = $34, then the reset sequence brings S from $00 to $FD
+    // https://www.nesdev.org/wiki/CPU_power_up_state
+    pub fn power_on(&mut self) {
+        self.register_a = 0;
+        self.register_x = 0;
+        self.register_y = 0;
+        self.stack_pointer = 0;
+        self.flags = CpuFlags::INTERRUPT_DISABLE | CpuFlags::BREAK | CpuFlags::UNUSED;
+
+        self.reset();
+    }
+
+    // Reset button: resets the rest of the machine, then runs the 7-cycle interrupt-like sequence
+    // with the stack writes turned into reads, so S is decremented by 3 and nothing is pushed
+    pub fn reset(&mut self) {
+        self.bus.reset();
+        self.jammed = false;
+        self.waiting = false;
+        self.pending_cycles = 0;
+
+        // opcode fetch and operand read, both discarded
+        self.mem_read(self.program_counter);
+        self.mem_read(self.program_counter);
+
+        for _ in 0..3 {
+            self.mem_read(0x0100 | self.stack_pointer as u16);
+            self.stack_pointer = self.stack_pointer.wrapping_sub(1);
+        }
+
+        self.insert_flag(CpuFlags::INTERRUPT_DISABLE);
+        self.program_counter = self.mem_read_u16(interrupts::RESET_VECTOR);
+
+        self.flush_cycles();
+
+        self.nmi_pending = false;
+        self.poll = InterruptPoll::default();
+        self.previous_poll = InterruptPoll::default();
+    }
+
+    // Executes one instruction, or services a pending interrupt instead
+    pub fn step(&mut self) -> StepResult {
+        let start_cycles = self.cycles;
+
+        if self.jammed {
+            return StepResult::Jammed;
+        }
+
+        // accesses made from outside of an instruction (debuggers, tests) don't take cycles
+        self.pending_cycles = 0;
+
+        if self.waiting && !self.wake_up() {
+            return StepResult::Waiting;
+        }
+
+        if let Some(interrupt) = self.pending_interrupt() {
+            return self.service_interrupt(interrupt, start_cycles);
+        }
+
+        let operation_code = self.mem_read(self.program_counter);
+
+        self.process_operation(operation_code);
+        self.end_step();
+
+        if let Some(page) = self.bus.take_oam_dma() {
+            self.oam_dma(page);
+        }
+
+        if self.jammed {
+            return StepResult::Jammed;
+        }
+
+        StepResult::Executed {
+            operation_code,
+            cycles: self.cycles - start_cycles,
+        }
+    }
+
+    fn service_interrupt(&mut self, interrupt: interrupts::Interrupt, start_cycles: usize) -> StepResult {
+        if interrupt.interrupt_type == InterruptType::Nmi {
+            self.nmi_pending = false;
+        }
+
+        // the operation code fetch and the operand read are thrown away, the program counter stays
+        self.mem_read(self.program_counter);
+        self.mem_read(self.program_counter);
+
+        let interrupt_type = self.handle_interrupt(interrupt);
+        self.end_step();
+
+        // the handler's first instruction always runs
+        self.previous_poll = InterruptPoll::default();
+
+        StepResult::InterruptServiced {
+            interrupt: interrupt_type,
+            cycles: self.cycles - start_cycles,
         }
     }
 
-    // test with skip_brk to prevent read 0x00 (and pc++) in single instruction test
-    pub fn interpret_for_test(&mut self, program_end: usize, skip_brk: bool) {
-        while (self.program_counter as usize) <= program_end {
-            let operation_code = self.mem_read(self.program_counter);
+    // https://www.nesdev.org/wiki/DMA#OAM_DMA
+    // The CPU halts for a cycle, waits one more if the next is a put (odd) cycle, then reads the page
+    // on get cycles and writes it to OAMDATA on put cycles: 513 or 514 cycles while the PPU keeps running
+    fn oam_dma(&mut self, page: u8) {
+        self.tick(1);
+        if self.cycles % 2 == 1 {
+            self.tick(1);
+        }
+
+        for offset in 0..=0xFF {
+            self.tick(1);
+            let data = self.bus.read(u16::from_le_bytes([offset, page]));
+            self.tick(1);
+            self.bus.write(0x2004, data);
+        }
+
+        // interrupts raised during the copy are taken before the next instruction
+        self.poll_interrupt_lines();
+    }
 
-            println!("interpret: op_code: {:#X}", operation_code);
+    fn end_step(&mut self) {
+        self.flush_cycles();
 
-            if operation_code == 0x00 && skip_brk {
-                println!("End of test program reached");
-                break;
+        if self.timing_mode == TimingMode::Instruction {
+            self.poll_interrupt_lines();
+        }
+    }
+
+    pub fn is_jammed(&self) -> bool {
+        self.jammed
+    }
+
+    pub fn is_waiting(&self) -> bool {
+        self.waiting
+    }
+
+    // WAI idles a cycle at a time until NMI or IRQ is asserted. A masked IRQ only resumes execution.
+    fn wake_up(&mut self) -> bool {
+        self.tick(1);
+        self.poll_interrupts();
+
+        if !self.poll.nmi && !self.poll.irq_line {
+            return false;
+        }
+
+        self.waiting = false;
+        self.previous_poll = self.poll;
+        true
+    }
+
+    // Runs until at least `cycles` CPU cycles have elapsed
+    pub fn run_cycles(&mut self, cycles: usize) -> StepResult {
+        let target = self.cycles + cycles;
+        loop {
+            let result = self.step();
+            if result.is_halted() || self.cycles >= target {
+                return result;
             }
+        }
+    }
 
-            if !self.process_operation(operation_code) {
-                println!("Program end");
-                break;
+    // Runs until the program counter reaches `address`, without executing the instruction there
+    pub fn run_until_pc(&mut self, address: u16) -> StepResult {
+        loop {
+            let result = self.step();
+            if result.is_halted() || self.program_counter == address {
+                return result;
             }
         }
     }
 
-    fn process_operation(&mut self, operation_code: u8) -> bool {
-        if let Some(_nmi) = self.bus.fetch_nmi() {
-            self.handle_interrupt(interrupts::NMI);
-        }
-
-        self.program_counter += 1;
-
-        let mut is_jump = false;
-
-        if let Some(op_code_info) = CPU_OPERATION_CODES_MAP.get(&operation_code) {
-            match operation_code {
-                // BRK - Force Interrupt
-                0x00 => {
-                    self.handle_interrupt(interrupts::BRK);
-                    return false;
-                },
-                // ADC - Add with Carry
-                0x69 | 0x65 | 0x75 | 0x6D | 0x7D | 0x79 | 0x61 | 0x71
-                => self.adc(&op_code_info.addressing_mode),
-                // AND - Logical AND
-                0x29 | 0x25 | 0x35 | 0x2D | 0x3D | 0x39 | 0x21 | 0x31
-                => self.and(&op_code_info.addressing_mode),
-                // ASL - Arithmetic Shift Left
-                0x0A | 0x06 | 0x16 | 0x0E | 0x1E
-                => self.asl(&op_code_info.addressing_mode),
-                // BCC - Branch if Carry Clear
-                0x90
-                => self.bcc(),
-                // BCS - Branch if Carry Set
-                0xB0
-                => self.bcs(),
-                // BEQ - Branch if Equal
-                0xF0
-                => self.beq(),
-                // BIT - Bit Test
-                0x24 | 0x2C
-                => self.bit(&op_code_info.addressing_mode),
-                // BMI - Branch if Minus
-                0x30
-                => self.bmi(),
-                // BNE - Branch if Not Equal
-                0xD0
-                => self.bne(),
-                // BPL - Branch if Positive
-                0x10
-                => self.bpl(),
-                // BVC - Branch if Overflow Clear
-                0x50
-                => self.bvc(),
-                // BVS - Branch if Overflow Set
-                0x70
-                => self.bvs(),
-                // CLC - Clear Carry Flag
-                0x18
-                => self.clc(),
-                // CLD - Clear Decimal Mode
-                0xD8
-                => self.cld(),
-                // CLI - Clear Interrupt Disable
-                0x58
-                => self.cli(),
-                // CLV - Clear Overflow Flag
-                0xB8
-                => self.clv(),
-                // CMP - Compare
-                0xC9 | 0xC5 | 0xD5 | 0xCD | 0xDD | 0xD9 | 0xC1 | 0xD1
-                => self.cmp(&op_code_info.addressing_mode),
-                // CPX - Compare X Register
-                0xE0 | 0xE4 | 0xEC
-                => self.cpx(&op_code_info.addressing_mode),
-                // CPY - Compare Y Register
-                0xC0 | 0xC4 | 0xCC
-                => self.cpy(&op_code_info.addressing_mode),
-                // DEC - Decrement Memory
-                0xC6 | 0xD6 | 0xCE | 0xDE
-                => self.dec(&op_code_info.addressing_mode),
-                // DEX - Decrement X Register
-                0xCA
-                => self.dex(),
-                // DEY - Decrement Y Register
-                0x88
-                => self.dey(),
-                // EOR - Exclusive OR
-                0x49 | 0x45 | 0x55 | 0x4D | 0x5D | 0x59 | 0x41 | 0x51
-                => self.eor(&op_code_info.addressing_mode),
-                // INC - Increment Memory
-                0xE6 | 0xF6 | 0xEE | 0xFE
-                => self.inc(&op_code_info.addressing_mode),
-                // INX - Increment X Register
-                0xE8
-                => self.inx(),
-                // INY - Increment Y Register
-                0xC8
-                => self.iny(),
-                // JMP - Jump
-                0x4C | 0x6C => {
-                    self.jmp(&op_code_info.addressing_mode);
-                    is_jump = true
-                },
-                // JSR - Jump to Subroutine
-                0x20 => {
-                    self.jsr();
-                    is_jump = true
-                },
-                // LDA - Load Accumulator
-                0xA9 | 0xA5 | 0xB5 | 0xAD | 0xBD | 0xB9 | 0xA1 | 0xB1
-                => self.lda(&op_code_info.addressing_mode),
-                // LDX - Load X Register
-                0xA2 | 0xA6 | 0xB6 | 0xAE | 0xBE
-                => self.ldx(&op_code_info.addressing_mode),
-                // LDY - Load Y Register
-                0xA0 | 0xA4 | 0xB4 | 0xAC | 0xBC
-                => self.ldy(&op_code_info.addressing_mode),
-                // LSR - Logical Shift Right
-                0x4A | 0x46 | 0x56 | 0x4E | 0x5E
-                => self.lsr(&op_code_info.addressing_mode),
-                // NOP - No Operation
-                0xEA
-                => self.nop(),
-                // ORA - Logical Inclusive OR
-                0x09 | 0x05 | 0x15 | 0x0D | 0x1D | 0x19 | 0x01 | 0x11
-                => self.ora(&op_code_info.addressing_mode),
-                // PHA - Push Accumulator
-                0x48
-                => self.pha(),
-                // PHP - Push Processor Status
-                0x08
-                => self.php(),
-                // PLA - Pull Accumulator
-                0x68
-                => self.pla(),
-                // PLP - Pull Processor Status
-                0x28
-                => self.plp(),
-                // ROL - Rotate Left
-                0x2A | 0x26 | 0x36 | 0x2E | 0x3E
-                => self.rol(&op_code_info.addressing_mode),
-                // ROR - Rotate Right
-                0x6A | 0x66 | 0x76 | 0x6E | 0x7E
-                => self.ror(&op_code_info.addressing_mode),
-                // RTI - Return from Interrupt
-                0x40
-                => self.rti(),
-                // RTS - Return from Subroutine
-                0x60
-                => self.rts(),
-                // SBC - Subtract with Carry
-                0xE9 | 0xE5 | 0xF5 | 0xED | 0xFD | 0xF9 | 0xE1 | 0xF1
-                => self.sbc(&op_code_info.addressing_mode),
-                // SEC - Set Carry Flag
-                0x38
-                => self.sec(),
-                // SED - Set Decimal Flag
-                0xF8
-                => self.sed(),
-                // SEI - Set Interrupt Disable
-                0x78
-                => self.sei(),
-                // STA - Store Accumulator
-                0x85 | 0x95 | 0x8D | 0x9D | 0x99 | 0x81 | 0x91
-                => self.sta(&op_code_info.addressing_mode),
-                // STX - Store X Register
-                0x86 | 0x96 | 0x8E
-                => self.stx(&op_code_info.addressing_mode),
-                // STY - Store Y Register
-                0x84 | 0x94 | 0x8C
-                => self.sty(&op_code_info.addressing_mode),
-                // TAX - Transfer Accumulator to X
-                0xAA
-                => self.tax(),
-                // TAY - Transfer Accumulator to Y
-                0xA8
-                => self.tay(),
-                // TSX - Transfer Stack Pointer to X
-                0xBA
-                => self.tsx(),
-                // TXA - Transfer X to Accumulator
-                0x8A
-                => self.txa(),
-                // TXS - Transfer X to Stack Pointer
-                0x9A
-                => self.txs(),
-                // TYA - Transfer Y to Accumulator
-                0x98
-                => self.tya(),
-                _ => {
-                    println!("Unknown operation_code: {}", operation_code);
-                    return false;
-                }
+    // Runs until the bus reports a completed video frame
+    pub fn run_frame(&mut self) -> StepResult {
+        loop {
+            let result = self.step();
+            if result.is_halted() || self.bus.is_frame_complete() {
+                return result;
             }
+        }
+    }
 
-            self.tick(op_code_info.cycles);
+    fn process_operation(&mut self, operation_code: u8) {
+        let operation = &self.variant.operation_codes()[operation_code as usize];
+        let dispatch = match self.variant {
+            CpuVariant::Cmos65C02 => Self::CMOS_DISPATCH[operation_code as usize],
+            _ => Self::DISPATCH[operation_code as usize],
+        };
 
-            if !is_jump {
-                self.program_counter = self.program_counter + (op_code_info.bytes - 1) as u16;
-            }
+        self.program_counter = self.program_counter.wrapping_add(1);
 
-            true
+        // single byte instructions read the following byte anyway and ignore it,
+        // except for the single cycle NOPs of the 65C02
+        if matches!(operation.addressing_mode, AddressingMode::Implicit | AddressingMode::Accumulator)
+            && operation.cycles > 1 {
+            self.mem_read(self.program_counter);
         }
-        else {
-            println!("Unknown operation_code: {}", operation_code);
-            false
+
+        (dispatch.execute)(self, &operation.addressing_mode);
+
+        if !dispatch.jump {
+            self.program_counter = self.program_counter.wrapping_add((operation.bytes - 1) as u16);
         }
     }
 
@@ -287,7 +328,8 @@ impl<'a> CPU<'a> {
             AddressingMode::Indirect => self.get_indirect(),
             AddressingMode::IndirectX => self.get_indirect_x(),
             AddressingMode::IndirectY => self.get_indirect_y(),
-            _ => panic!("Unimplemented addressing mode")
+            AddressingMode::ZeroPageIndirect => self.get_zero_page_indirect(),
+            _ => panic!("Unsupported addressing mode for get_operand")
         }
     }
 
@@ -304,38 +346,64 @@ impl<'a> CPU<'a> {
             AddressingMode::Indirect => self.get_indirect_address(),
             AddressingMode::IndirectX => self.get_indirect_x_address(),
             AddressingMode::IndirectY => self.get_indirect_y_address(),
+            AddressingMode::ZeroPageIndirect => self.get_zero_page_indirect_address(),
             _ => panic!("Unsupported addressing mode for get_address")
         }
     }
 
     pub (super) fn mem_read(&mut self, pos: u16) -> u8 {
-        self.bus.read(pos)
+        self.last_address = pos;
+        self.access_cycle();
+        let data = self.bus.read(pos);
+        self.poll_interrupts();
+        data
     }
 
     pub (super) fn mem_read_u16(&mut self, pos: u16) -> u16 {
-        self.bus.read_u16(pos)
+        let lo = self.mem_read(pos) as u16;
+        let hi = self.mem_read(pos.wrapping_add(1)) as u16;
+        (hi << 8) | lo
     }
 
     pub (super) fn mem_write(&mut self, pos: u16, data: u8) {
+        self.last_address = pos;
+        self.access_cycle();
         self.bus.write(pos, data);
+        self.poll_interrupts();
     }
 
-    pub (super) fn set_register_a(&mut self, data: u8) {
-        self.register_a = data;
-        self.update_zero_and_negative_flags(self.register_a);
+    // Read-modify-write instructions write the unmodified value back before the result,
+    // the 65C02 reads it again instead
+    pub (super) fn mem_write_modified(&mut self, pos: u16, original: u8, result: u8) {
+        if self.variant.is_cmos() {
+            self.mem_read(pos);
+        } else {
+            self.mem_write(pos, original);
+        }
+        self.mem_write(pos, result);
     }
 
-    pub (super) fn set_register_x(&mut self, data: u8) {
-        self.register_x = data;
-        self.update_zero_and_negative_flags(self.register_x);
+    // 65C02 dummy cycle
+    pub (super) fn repeat_read(&mut self) {
+        self.mem_read(self.last_address);
+    }
+
+    fn access_cycle(&mut self) {
+        match self.timing_mode {
+            TimingMode::Instruction => self.pending_cycles = self.pending_cycles.saturating_add(1),
+            TimingMode::Cycle => self.tick(1),
+        }
     }
 
-    pub (super) fn set_register_y(&mut self, data: u8) {
-        self.register_y = data;
-        self.update_zero_and_negative_flags(self.register_y);
+    fn flush_cycles(&mut self) {
+        let cycles = std::mem::take(&mut self.pending_cycles);
+        if cycles > 0 {
+            self.tick(cycles);
+        }
     }
 
     pub (super) fn tick(&mut self, cycles: u8) {
+        self.cycles += cycles as usize;
         self.bus.tick(cycles)
     }
 }
@@ -343,31 +411,123 @@ impl<'a> CPU<'a> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::emulator::bus::cpu_bus::IrqSource;
+    use crate::emulator::bus::mock_bus;
     use crate::emulator::bus::mock_bus::MockBus;
     use crate::emulator::cpu::stack::StackOperations;
 
-    fn prepare_test_cpu(program: &[u8]) -> CPU {
+    fn prepare_test_cpu(program: &[u8]) -> CPU<MockBus> {
         let mut bus = MockBus::new();
 
-        bus.load_program(&program, 0x8000);
+        bus.load_program(program, 0x8000);
 
-        let mut cpu = CPU::new(Box::new(bus));
+        let mut cpu = CPU::new(bus);
 
         cpu.program_counter = 0x8000;
 
         cpu
     }
 
-    fn get_program_end(pc: u16, program_len: usize) -> usize {
-        (pc + program_len as u16) as usize
+    #[test]
+    fn test_bus_accessors() {
+        let mut cpu = prepare_test_cpu(&[0xA9, 0x42, 0x85, 0x10]); // LDA #$42, STA $10
+
+        cpu.bus_mut().memory[0x0011] = 0x24;
+        cpu.step();
+        cpu.step();
+
+        assert_eq!(cpu.bus().memory[0x0010], 0x42);
+        assert_eq!(cpu.bus().cycles, 5);
+
+        let bus = cpu.into_bus();
+        assert_eq!(bus.memory[0x0011], 0x24);
+    }
+
+    #[test]
+    fn test_cpu_on_boxed_bus() {
+        let mut bus = MockBus::new();
+        bus.load_program(&[0xE8], 0x8000); // INX
+
+        let mut cpu: CPU<Box<dyn CpuBus>> = CPU::new(Box::new(bus));
+        cpu.program_counter = 0x8000;
+        cpu.step();
+
+        assert_eq!(cpu.register_x, 1);
+    }
+
+    #[test]
+    fn test_reset_loads_reset_vector() {
+        let mut cpu = prepare_test_cpu(&[]);
+        cpu.mem_write(0xFFFC, 0x34);
+        cpu.mem_write(0xFFFD, 0x92);
+        cpu.stack_pointer = 0xFF;
+        cpu.clear_flag(CpuFlags::INTERRUPT_DISABLE);
+        cpu.register_a = 0x12;
+
+        cpu.reset();
+
+        assert_eq!(cpu.program_counter, 0x9234);
+        assert_eq!(cpu.stack_pointer, 0xFC);
+        assert!(cpu.contains_flag(CpuFlags::INTERRUPT_DISABLE));
+        assert_eq!(cpu.register_a, 0x12);
+    }
+
+    #[test]
+    fn test_reset_does_not_write_stack() {
+        let mut cpu = prepare_test_cpu(&[]);
+        cpu.mem_write(0x01FD, 0xAA);
+        cpu.mem_write(0x01FC, 0xBB);
+        cpu.mem_write(0x01FB, 0xCC);
+
+        cpu.reset();
+
+        assert_eq!(cpu.mem_read(0x01FD), 0xAA);
+        assert_eq!(cpu.mem_read(0x01FC), 0xBB);
+        assert_eq!(cpu.mem_read(0x01FB), 0xCC);
+    }
+
+    #[test]
+    fn test_power_on() {
+        let mut cpu = prepare_test_cpu(&[]);
+        cpu.mem_write(0xFFFC, 0x00);
+        cpu.mem_write(0xFFFD, 0xC0);
+        cpu.register_x = 0x12;
+        cpu.register_y = 0x34;
+        cpu.flags = CpuFlags::CARRY;
+
+        cpu.power_on();
+
+        assert_eq!(cpu.program_counter, 0xC000);
+        assert_eq!(cpu.stack_pointer, 0xFD);
+        assert_eq!(cpu.register_a, 0);
+        assert_eq!(cpu.register_x, 0);
+        assert_eq!(cpu.register_y, 0);
+        assert_eq!(cpu.flags.bits(), 0x34);
+    }
+
+    fn prepare_assembled_cpu(source: &str) -> CPU<MockBus> {
+        let program = asm::assemble(source).unwrap();
+        let mut bus = MockBus::new();
+
+        bus.load_program(&program.bytes, program.origin);
+
+        let mut cpu = CPU::new(bus);
+
+        cpu.program_counter = program.origin;
+
+        cpu
     }
 
     #[test]
     fn test_program_execution() {
-        let program = [0xA9, 0x05, 0x85, 0x00, 0x00]; // LDA #$05, STA $00, BRK
-        let mut cpu = prepare_test_cpu(&program);
+        let mut cpu = prepare_assembled_cpu("
+            .org $8000
+            lda #$05
+            sta $00
+            brk
+        ");
 
-        cpu.interpret(get_program_end(cpu.program_counter, program.len()));
+        cpu.run_until_pc(0x8004);
 
         assert_eq!(cpu.register_a, 0x05);
         assert_eq!(cpu.mem_read(0x00), 0x05);
@@ -378,15 +538,14 @@ mod test {
         let program = vec![0x00];
         let mut cpu = prepare_test_cpu(&program);
 
-        let program_end = get_program_end(cpu.program_counter, program.len());
-
         cpu.clear_flag(CpuFlags::INTERRUPT_DISABLE);
 
         cpu.mem_write(0xFFFE, 0x34); // lo
         cpu.mem_write(0xFFFF, 0x12); // hi
 
-        cpu.interpret(program_end);
+        let result = cpu.step();
 
+        assert_eq!(result, StepResult::Executed { operation_code: 0x00, cycles: 7 });
         assert_eq!(cpu.program_counter, 0x1234);
 
         assert!(cpu.contains_flag(CpuFlags::BREAK));
@@ -395,6 +554,150 @@ mod test {
         assert_eq!(cpu.stack_pointer, 0xFA);
     }
 
+    #[test]
+    fn test_brk_return_address_skips_padding_byte() {
+        let program = vec![0x00, 0xFF, 0xEA]; // BRK, padding, NOP
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.mem_write(0xFFFE, 0x00);
+        cpu.mem_write(0xFFFF, 0x90);
+        cpu.mem_write(0x9000, 0x40); // RTI
+
+        cpu.step();
+        cpu.step();
+
+        assert_eq!(cpu.program_counter, 0x8002);
+    }
+
+    #[test]
+    fn test_step_reports_cycles() {
+        let program = vec![0xA9, 0x05, 0x8D, 0x00, 0x02]; // LDA #$05, STA $0200
+        let mut cpu = prepare_test_cpu(&program);
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xA9, cycles: 2 });
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x8D, cycles: 4 });
+        assert_eq!(cpu.cycles, 6);
+    }
+
+    #[test]
+    fn test_step_jam() {
+        let program = vec![0x02];
+        let mut cpu = prepare_test_cpu(&program);
+
+        let result = cpu.step();
+
+        assert_eq!(result, StepResult::Jammed);
+        assert!(result.is_halted());
+        assert!(cpu.is_jammed());
+        assert_eq!(cpu.program_counter, 0x8000);
+
+        // only a reset gets it going again
+        assert_eq!(cpu.step(), StepResult::Jammed);
+        assert_eq!(cpu.program_counter, 0x8000);
+    }
+
+    #[test]
+    fn test_reset_clears_jam() {
+        let program = vec![0x12];
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.mem_write(0xFFFC, 0x00);
+        cpu.mem_write(0xFFFD, 0x80);
+
+        assert_eq!(cpu.step(), StepResult::Jammed);
+
+        cpu.mem_write(0x8000, 0xEA);
+        cpu.reset();
+
+        assert!(!cpu.is_jammed());
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xEA, cycles: 2 });
+    }
+
+    #[test]
+    fn test_operation_code_table_is_complete() {
+        let mut seen = [false; 256];
+        for op in CPU_OPERATION_CODES_LIST.iter() {
+            assert!(!seen[op.code as usize], "duplicate operation code {:#04X}", op.code);
+            seen[op.code as usize] = true;
+        }
+
+        assert!(seen.iter().all(|&s| s));
+        assert_eq!(CPU_OPERATION_CODES_LIST.iter().filter(|op| op.official).count(), 151);
+    }
+
+    #[test]
+    fn test_decode_table_is_indexed_by_operation_code() {
+        for (code, op) in CPU_OPERATION_CODES.iter().enumerate() {
+            assert_eq!(op.code as usize, code);
+        }
+    }
+
+    #[test]
+    fn test_step_services_nmi() {
+        let program = vec![0xEA, 0xEA]; // NOP, NOP
+        let mut bus = MockBus::new();
+        bus.load_program(&program, 0x8000);
+        bus.nmi_interrupt = Some(0xFF);
+        bus.memory[0xFFFA] = 0x00;
+        bus.memory[0xFFFB] = 0x90;
+        let mut cpu = CPU::new(bus);
+        cpu.program_counter = 0x8000;
+
+        // the NMI is polled during the first instruction and serviced after it
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xEA, cycles: 2 });
+        let result = cpu.step();
+
+        assert_eq!(result, StepResult::InterruptServiced { interrupt: InterruptType::Nmi, cycles: 7 });
+        assert_eq!(cpu.program_counter, 0x9000);
+        assert_eq!(cpu.pop_stack(), 0b0010_0100); // B clear, bit 5 and I set
+        assert_eq!(cpu.pop_stack(), 0x01);
+        assert_eq!(cpu.pop_stack(), 0x80);
+    }
+
+    #[test]
+    fn test_run_until_pc() {
+        // LDX #$00; loop: INX; CPX #$10; BNE loop; NOP
+        let program = vec![0xA2, 0x00, 0xE8, 0xE0, 0x10, 0xD0, 0xFB, 0xEA];
+        let mut cpu = prepare_test_cpu(&program);
+
+        cpu.run_until_pc(0x8007);
+
+        assert_eq!(cpu.register_x, 0x10);
+        assert_eq!(cpu.program_counter, 0x8007);
+    }
+
+    #[test]
+    fn test_run_cycles() {
+        let program = vec![0x4C, 0x00, 0x80]; // JMP $8000
+        let mut cpu = prepare_test_cpu(&program);
+
+        cpu.run_cycles(10);
+
+        assert_eq!(cpu.cycles, 12);
+    }
+
+    #[test]
+    fn test_run_frame() {
+        let program = vec![0x4C, 0x00, 0x80]; // JMP $8000
+        let mut cpu = prepare_test_cpu(&program);
+
+        cpu.run_frame();
+        assert!(cpu.cycles >= mock_bus::CYCLES_PER_FRAME);
+        assert!(cpu.cycles < mock_bus::CYCLES_PER_FRAME + 3);
+
+        cpu.run_frame();
+        assert!(cpu.cycles >= 2 * mock_bus::CYCLES_PER_FRAME);
+    }
+
+    #[test]
+    fn test_run_stops_on_jam() {
+        let program = vec![0xEA, 0xEA, 0x02];
+        let mut cpu = prepare_test_cpu(&program);
+
+        let result = cpu.run_frame();
+
+        assert_eq!(result, StepResult::Jammed);
+        assert_eq!(cpu.program_counter, 0x8002);
+    }
+
     #[test]
     fn test_adc_immediate() {
         let program = vec![0x69, 0x03]; // 0x69 => ADC Immediate
@@ -403,7 +706,7 @@ mod test {
 
         cpu.register_a = 0x05;
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), false);
+        cpu.step();
 
         assert_eq!(cpu.register_a, 0x08); // (0x05 + 0x03)
         assert!(!cpu.flags.contains(CpuFlags::ZERO));
@@ -418,7 +721,7 @@ mod test {
 
         cpu.register_a = 0xFF;
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), false);
+        cpu.step();
 
         assert_eq!(cpu.register_a, 0x00); // ADC #0x01 (0xFF + 0x01 = 0x00)
         assert!(cpu.flags.contains(CpuFlags::CARRY));
@@ -433,7 +736,7 @@ mod test {
 
         cpu.register_a = 0xFE;
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), false);
+        cpu.step();
 
         assert_eq!(cpu.register_a, 0x00); // ADC #0x02 (0xFE + 0x02 = 0x00)
         assert!(cpu.flags.contains(CpuFlags::ZERO));
@@ -448,7 +751,7 @@ mod test {
         cpu.register_a = 0b11001100; // 204
         cpu.mem_write(0x2000, 0b10101010); // 170
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), false);
+        cpu.step();
 
         assert_eq!(cpu.register_a, 0b10001000); // 136
     }
@@ -461,7 +764,7 @@ mod test {
         cpu.register_a = 0b11001100; // 204
         cpu.mem_write(0x00, 0b10101010); // 170
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), false);
+        cpu.step();
 
         assert_eq!(cpu.register_a, 0b10001000); // 136
     }
@@ -473,7 +776,7 @@ mod test {
 
         cpu.register_a = 0b11001100; // 204
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), false);
+        cpu.step();
 
         assert_eq!(cpu.register_a, 0b10011000); // 152
         assert!(cpu.flags.contains(CpuFlags::CARRY));
@@ -486,7 +789,7 @@ mod test {
 
         cpu.mem_write(0x00, 0b11001100); // 204
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), false);
+        cpu.step();
 
         assert_eq!(cpu.mem_read(0x00), 0b10011000); // 152
         assert!(cpu.flags.contains(CpuFlags::CARRY));
@@ -499,7 +802,7 @@ mod test {
 
         cpu.flags.remove(CpuFlags::CARRY);
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), false);
+        cpu.step();
 
         assert_eq!(cpu.program_counter, 0x8007);
     }
@@ -511,7 +814,7 @@ mod test {
 
         cpu.flags.insert(CpuFlags::CARRY);
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), true);
+        cpu.step();
 
         assert_eq!(cpu.program_counter, 0x8002);
     }
@@ -523,7 +826,7 @@ mod test {
 
         cpu.flags.remove(CpuFlags::CARRY);
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), true);
+        cpu.step();
 
         // Expected PC: 0x8000 (start) + 2 (instruction length) - 5 (offset) = 0x7FFD
         assert_eq!(cpu.program_counter, 0x7FFD);
@@ -536,7 +839,7 @@ mod test {
 
         cpu.flags.insert(CpuFlags::CARRY);
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), true);
+        cpu.step();
 
         assert_eq!(cpu.program_counter, 0x8007);
     }
@@ -548,7 +851,7 @@ mod test {
 
         cpu.flags.remove(CpuFlags::CARRY);
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), true);
+        cpu.step();
 
         assert_eq!(cpu.program_counter, 0x8002);
     }
@@ -560,7 +863,7 @@ mod test {
 
         cpu.flags.insert(CpuFlags::CARRY);
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), true);
+        cpu.step();
 
         assert_eq!(cpu.program_counter, 0x8000u16.wrapping_add(2).wrapping_sub(5));
     }
@@ -572,7 +875,7 @@ mod test {
 
         cpu.flags.insert(CpuFlags::CARRY);
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), true);
+        cpu.step();
 
         assert_eq!(cpu.program_counter, 0x8007);
     }
@@ -582,7 +885,7 @@ mod test {
         let program = vec![0x4C, 0x34, 0x12]; // JMP Absolute 0x4C 0x1234
         let mut cpu = prepare_test_cpu(&program);
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), true);
+        cpu.step();
 
         assert_eq!(cpu.program_counter, 0x1234);
     }
@@ -596,7 +899,7 @@ mod test {
         cpu.mem_write(0x2000, 0x78); // LSB
         cpu.mem_write(0x2001, 0x56); // MSB
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), true);
+        cpu.step();
 
         assert_eq!(cpu.program_counter, 0x5678);
     }
@@ -608,7 +911,7 @@ mod test {
 
         cpu.stack_pointer = 0xFF;
 
-        cpu.interpret_for_test(get_program_end(cpu.program_counter, program.len()), true);
+        cpu.step();
 
         assert_eq!(cpu.program_counter, 0x1234, "Program counter should be set to the target address");
 
@@ -618,4 +921,876 @@ mod test {
         assert_eq!(cpu.pop_stack(), 0x02, "Low byte of return address should be on the stack");
         assert_eq!(cpu.pop_stack(), 0x80, "High byte of return address should be on the stack");
     }
+
+    #[test]
+    fn test_lax_zero_page() {
+        let program = vec![0xA7, 0x10]; // LAX $10
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.mem_write(0x10, 0x80);
+
+        cpu.step();
+
+        assert_eq!(cpu.register_a, 0x80);
+        assert_eq!(cpu.register_x, 0x80);
+        assert!(cpu.contains_flag(CpuFlags::NEGATIVE));
+    }
+
+    #[test]
+    fn test_sax_zero_page() {
+        let program = vec![0x87, 0x10]; // SAX $10
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.register_a = 0xF0;
+        cpu.register_x = 0x3C;
+        cpu.flags = CpuFlags::empty();
+
+        cpu.step();
+
+        assert_eq!(cpu.mem_read(0x10), 0x30);
+        assert!(cpu.flags.is_empty());
+    }
+
+    #[test]
+    fn test_dcp_absolute() {
+        let program = vec![0xCF, 0x00, 0x02]; // DCP $0200
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.mem_write(0x0200, 0x43);
+        cpu.register_a = 0x42;
+
+        let result = cpu.step();
+
+        assert_eq!(result, StepResult::Executed { operation_code: 0xCF, cycles: 6 });
+        assert_eq!(cpu.mem_read(0x0200), 0x42);
+        assert!(cpu.contains_flag(CpuFlags::ZERO));
+        assert!(cpu.contains_flag(CpuFlags::CARRY));
+    }
+
+    #[test]
+    fn test_isc_zero_page() {
+        let program = vec![0xE7, 0x10]; // ISC $10
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.mem_write(0x10, 0x04);
+        cpu.register_a = 0x10;
+        cpu.insert_flag(CpuFlags::CARRY);
+
+        cpu.step();
+
+        assert_eq!(cpu.mem_read(0x10), 0x05);
+        assert_eq!(cpu.register_a, 0x0B);
+        assert!(cpu.contains_flag(CpuFlags::CARRY));
+    }
+
+    #[test]
+    fn test_slo_zero_page() {
+        let program = vec![0x07, 0x10]; // SLO $10
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.mem_write(0x10, 0x81);
+        cpu.register_a = 0x01;
+
+        cpu.step();
+
+        assert_eq!(cpu.mem_read(0x10), 0x02);
+        assert_eq!(cpu.register_a, 0x03);
+        assert!(cpu.contains_flag(CpuFlags::CARRY));
+    }
+
+    #[test]
+    fn test_rra_uses_rotated_carry() {
+        let program = vec![0x67, 0x10]; // RRA $10
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.mem_write(0x10, 0x03);
+        cpu.register_a = 0x10;
+        cpu.clear_flag(CpuFlags::CARRY);
+
+        cpu.step();
+
+        // $03 >> 1 = $01 with carry out, A = $10 + $01 + 1
+        assert_eq!(cpu.mem_read(0x10), 0x01);
+        assert_eq!(cpu.register_a, 0x12);
+        assert!(!cpu.contains_flag(CpuFlags::CARRY));
+    }
+
+    #[test]
+    fn test_arr_flags() {
+        let program = vec![0x6B, 0xFF]; // ARR #$FF
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.register_a = 0xC0;
+        cpu.clear_flag(CpuFlags::CARRY);
+
+        cpu.step();
+
+        // $C0 >> 1 = $60: bit 6 set, bit 5 set
+        assert_eq!(cpu.register_a, 0x60);
+        assert!(cpu.contains_flag(CpuFlags::CARRY));
+        assert!(!cpu.contains_flag(CpuFlags::OVERFLOW));
+    }
+
+    #[test]
+    fn test_sbx_immediate() {
+        let program = vec![0xCB, 0x02]; // SBX #$02
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.register_a = 0x0F;
+        cpu.register_x = 0x05;
+
+        cpu.step();
+
+        assert_eq!(cpu.register_x, 0x03);
+        assert_eq!(cpu.register_a, 0x0F);
+        assert!(cpu.contains_flag(CpuFlags::CARRY));
+    }
+
+    #[test]
+    fn test_unofficial_nops() {
+        // NOP, NOP #$12, NOP $12, NOP $1234,X
+        let program = vec![0x1A, 0x80, 0x12, 0x04, 0x12, 0x1C, 0x34, 0x12];
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.register_a = 0x42;
+
+        cpu.run_until_pc(0x8008);
+
+        assert_eq!(cpu.register_a, 0x42);
+        assert_eq!(cpu.cycles, 2 + 2 + 3 + 4);
+    }
+
+    #[test]
+    fn test_shx_page_cross_corrupts_address() {
+        let program = vec![0x9E, 0xFF, 0x02]; // SHX $02FF,Y
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.register_x = 0x01;
+        cpu.register_y = 0x01;
+
+        cpu.step();
+
+        // X & ($02 + 1) = $01 is written, and replaces the high byte of $0300
+        assert_eq!(cpu.mem_read(0x0100), 0x01);
+        assert_eq!(cpu.mem_read(0x0300), 0x00);
+    }
+
+    #[test]
+    fn test_absolute_x_read_page_cross_cycles() {
+        let program = vec![0xBD, 0x80, 0x02, 0xBD, 0xF0, 0x02]; // LDA $0280,X; LDA $02F0,X
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.register_x = 0x20;
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xBD, cycles: 4 });
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xBD, cycles: 5 });
+    }
+
+    #[test]
+    fn test_absolute_x_write_has_no_page_cross_penalty() {
+        let program = vec![0x9D, 0xF0, 0x02]; // STA $02F0,X
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.register_x = 0x20;
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x9D, cycles: 5 });
+    }
+
+    #[test]
+    fn test_indirect_y_read_page_cross_cycles() {
+        let program = vec![0xB1, 0x10]; // LDA ($10),Y
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.mem_write(0x10, 0xFF);
+        cpu.mem_write(0x11, 0x02);
+        cpu.mem_write(0x0300, 0x42);
+        cpu.register_y = 0x01;
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xB1, cycles: 6 });
+        assert_eq!(cpu.register_a, 0x42);
+    }
+
+    #[test]
+    fn test_branch_cycles() {
+        let program = vec![0xD0, 0x00, 0xF0, 0x00]; // BNE +0 (taken), BEQ +0 (not taken)
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.clear_flag(CpuFlags::ZERO);
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xD0, cycles: 3 });
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xF0, cycles: 2 });
+    }
+
+    #[test]
+    fn test_branch_page_cross_is_relative_to_next_instruction() {
+        let mut cpu = prepare_test_cpu(&[]);
+        cpu.mem_write(0x80FD, 0xD0); // BNE +1
+        cpu.mem_write(0x80FE, 0x01);
+        cpu.program_counter = 0x80FD;
+        cpu.clear_flag(CpuFlags::ZERO);
+
+        // next instruction is at $80FF, the target $8100 is on the next page
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xD0, cycles: 4 });
+        assert_eq!(cpu.program_counter, 0x8100);
+    }
+
+    fn prepare_irq_test_cpu(program: &[u8]) -> CPU<MockBus> {
+        let mut cpu = prepare_test_cpu(program);
+        cpu.mem_write(0xFFFE, 0x00);
+        cpu.mem_write(0xFFFF, 0x90);
+        cpu.bus.assert_irq(IrqSource::EXTERNAL);
+        cpu
+    }
+
+    #[test]
+    fn test_irq_serviced_after_instruction() {
+        let program = vec![0xEA, 0xEA]; // NOP, NOP
+        let mut cpu = prepare_irq_test_cpu(&program);
+        cpu.clear_flag(CpuFlags::INTERRUPT_DISABLE);
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xEA, cycles: 2 });
+        assert_eq!(cpu.step(), StepResult::InterruptServiced { interrupt: InterruptType::Irq, cycles: 7 });
+        assert_eq!(cpu.program_counter, 0x9000);
+        assert!(cpu.contains_flag(CpuFlags::INTERRUPT_DISABLE));
+
+        cpu.pop_stack(); // status
+        assert_eq!(cpu.pop_stack(), 0x01);
+        assert_eq!(cpu.pop_stack(), 0x80);
+    }
+
+    #[test]
+    fn test_irq_masked_by_interrupt_disable() {
+        let program = vec![0xEA, 0xEA];
+        let mut cpu = prepare_irq_test_cpu(&program);
+        cpu.insert_flag(CpuFlags::INTERRUPT_DISABLE);
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xEA, cycles: 2 });
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xEA, cycles: 2 });
+    }
+
+    #[test]
+    fn test_irq_acknowledged_before_poll() {
+        let program = vec![0xEA, 0xEA];
+        let mut cpu = prepare_irq_test_cpu(&program);
+        cpu.clear_flag(CpuFlags::INTERRUPT_DISABLE);
+        cpu.bus.acknowledge_irq(IrqSource::EXTERNAL);
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xEA, cycles: 2 });
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xEA, cycles: 2 });
+    }
+
+    #[test]
+    fn test_irq_delayed_one_instruction_after_cli() {
+        let program = vec![0x58, 0xEA, 0xEA]; // CLI, NOP, NOP
+        let mut cpu = prepare_irq_test_cpu(&program);
+        cpu.insert_flag(CpuFlags::INTERRUPT_DISABLE);
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x58, cycles: 2 });
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xEA, cycles: 2 });
+        assert!(matches!(cpu.step(), StepResult::InterruptServiced { interrupt: InterruptType::Irq, .. }));
+        cpu.pop_stack(); // status
+        assert_eq!(cpu.pop_stack(), 0x02);
+        assert_eq!(cpu.pop_stack(), 0x80);
+    }
+
+    #[test]
+    fn test_irq_still_taken_after_sei() {
+        let program = vec![0x78, 0xEA]; // SEI, NOP
+        let mut cpu = prepare_irq_test_cpu(&program);
+        cpu.clear_flag(CpuFlags::INTERRUPT_DISABLE);
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x78, cycles: 2 });
+        assert!(matches!(cpu.step(), StepResult::InterruptServiced { interrupt: InterruptType::Irq, .. }));
+        assert_eq!(cpu.pop_stack() & CpuFlags::INTERRUPT_DISABLE.bits(), CpuFlags::INTERRUPT_DISABLE.bits());
+    }
+
+    #[test]
+    fn test_irq_delayed_one_instruction_after_plp() {
+        let program = vec![0x28, 0xEA, 0xEA]; // PLP, NOP, NOP
+        let mut cpu = prepare_irq_test_cpu(&program);
+        cpu.insert_flag(CpuFlags::INTERRUPT_DISABLE);
+        cpu.push_stack(0x00);
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x28, cycles: 4 });
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xEA, cycles: 2 });
+        assert!(matches!(cpu.step(), StepResult::InterruptServiced { interrupt: InterruptType::Irq, .. }));
+    }
+
+    #[test]
+    fn test_irq_immediate_after_rti() {
+        let program = vec![0x40]; // RTI
+        let mut cpu = prepare_irq_test_cpu(&program);
+        cpu.insert_flag(CpuFlags::INTERRUPT_DISABLE);
+        cpu.push_stack_u16(0x8100);
+        cpu.push_stack(0x00); // status with I clear
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x40, cycles: 6 });
+        assert!(matches!(cpu.step(), StepResult::InterruptServiced { interrupt: InterruptType::Irq, .. }));
+        cpu.pop_stack(); // status
+        assert_eq!(cpu.pop_stack(), 0x00);
+        assert_eq!(cpu.pop_stack(), 0x81);
+    }
+
+    fn assert_cycles_match(variant: CpuVariant) {
+        for op in variant.operation_codes().iter() {
+            if matches!(op.mnemonic, "JAM" | "STP")
+                || matches!(op.addressing_mode, AddressingMode::Relative | AddressingMode::ZeroPageRelative) {
+                continue;
+            }
+
+            for mode in [TimingMode::Instruction, TimingMode::Cycle] {
+                // zeroed operands and index registers, so no page is crossed
+                let mut cpu = prepare_test_cpu(&[op.code, 0x00, 0x00]);
+                cpu.register_x = 0;
+                cpu.register_y = 0;
+                cpu.timing_mode = mode;
+                cpu.variant = variant;
+
+                let result = cpu.step();
+
+                assert_eq!(
+                    result,
+                    StepResult::Executed { operation_code: op.code, cycles: op.cycles as usize },
+                    "{} ({:#04X}) in {:?} mode on {:?}", op.mnemonic, op.code, mode, variant
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cycles_match_operation_code_table() {
+        assert_cycles_match(CpuVariant::Ricoh2A03);
+        assert_cycles_match(CpuVariant::Nmos6502);
+        assert_cycles_match(CpuVariant::Cmos65C02);
+    }
+
+    #[test]
+    fn test_brk_hijacked_by_nmi() {
+        let mut bus = MockBus::new();
+        bus.load_program(&[0x00], 0x8000); // BRK
+        bus.memory[0xFFFA] = 0x00;
+        bus.memory[0xFFFB] = 0x90;
+        bus.memory[0xFFFE] = 0x00;
+        bus.memory[0xFFFF] = 0xA0;
+        bus.nmi_interrupt = Some(0xFF);
+        let mut cpu = CPU::new(bus);
+        cpu.program_counter = 0x8000;
+        cpu.flags = CpuFlags::UNUSED;
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x00, cycles: 7 });
+
+        // NMI vector, but the pushed status still has B set
+        assert_eq!(cpu.program_counter, 0x9000);
+        assert_eq!(cpu.pop_stack(), 0b0011_0000);
+        assert_eq!(cpu.pop_stack(), 0x02);
+
+        // the NMI was consumed by the BRK sequence
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x00, cycles: 7 });
+    }
+
+    #[test]
+    fn test_brk_and_php_push_break_flag() {
+        let program = vec![0x08, 0x00]; // PHP, BRK
+        let mut cpu = prepare_test_cpu(&program);
+        cpu.flags = CpuFlags::CARRY;
+
+        cpu.step();
+        assert_eq!(cpu.pop_stack(), 0b0011_0001);
+
+        cpu.step();
+        assert_eq!(cpu.pop_stack(), 0b0011_0001);
+    }
+
+    #[test]
+    fn test_plp_and_rti_pull_status_the_same_way() {
+        for code in [0x28, 0x40] { // PLP, RTI
+            for (held, pulled) in [(CpuFlags::BREAK | CpuFlags::UNUSED, 0b1100_0011), (CpuFlags::empty(), 0b1111_0011)] {
+                let mut cpu = prepare_test_cpu(&[code]);
+                cpu.flags = held;
+                cpu.push_stack_u16(0x9000);
+                cpu.push_stack(pulled);
+
+                cpu.step();
+
+                // the pulled bits 4 and 5 are dropped, the held ones stay
+                assert_eq!(cpu.flags.bits(), 0b1100_0011 | held.bits(), "{:02X} {:02X}", code, pulled);
+            }
+        }
+    }
+
+    #[test]
+    fn test_irq_pushes_status_without_break_flag() {
+        let program = vec![0xEA];
+        let mut cpu = prepare_irq_test_cpu(&program);
+        cpu.flags = CpuFlags::CARRY | CpuFlags::BREAK;
+
+        cpu.step();
+        assert!(matches!(cpu.step(), StepResult::InterruptServiced { interrupt: InterruptType::Irq, .. }));
+
+        assert_eq!(cpu.pop_stack(), 0b0010_0001);
+    }
+
+    // Raises NMI or IRQ once the bus reaches a given cycle
+    struct TimedInterruptBus {
+        bus: MockBus,
+        nmi_cycle: Option<usize>,
+        irq_cycle: Option<usize>,
+    }
+
+    impl CpuBus for TimedInterruptBus {
+        fn read(&mut self, addr: u16) -> u8 {
+            self.bus.read(addr)
+        }
+
+        fn peek(&self, addr: u16) -> u8 {
+            self.bus.peek(addr)
+        }
+
+        fn write(&mut self, addr: u16, data: u8) {
+            self.bus.write(addr, data)
+        }
+
+        fn tick(&mut self, cycles: u8) {
+            self.bus.tick(cycles);
+            if self.nmi_cycle == Some(self.bus.cycles) {
+                self.bus.nmi_interrupt = Some(0xFF);
+            }
+            if self.irq_cycle.is_some_and(|cycle| self.bus.cycles >= cycle) {
+                self.bus.assert_irq(IrqSource::EXTERNAL);
+            }
+        }
+
+        fn fetch_nmi(&mut self) -> Option<u8> {
+            self.bus.fetch_nmi()
+        }
+
+        fn assert_irq(&mut self, source: IrqSource) {
+            self.bus.assert_irq(source)
+        }
+
+        fn acknowledge_irq(&mut self, source: IrqSource) {
+            self.bus.acknowledge_irq(source)
+        }
+
+        fn is_irq_asserted(&self) -> bool {
+            self.bus.is_irq_asserted()
+        }
+
+        fn is_frame_complete(&mut self) -> bool {
+            self.bus.is_frame_complete()
+        }
+    }
+
+    fn prepare_timed_cpu(program: &[u8], nmi_cycle: Option<usize>, irq_cycle: Option<usize>) -> CPU<TimedInterruptBus> {
+        let mut bus = MockBus::new();
+        bus.load_program(program, 0x8000);
+        bus.memory[0xFFFA] = 0x00;
+        bus.memory[0xFFFB] = 0x90;
+        bus.memory[0xFFFE] = 0x00;
+        bus.memory[0xFFFF] = 0xA0;
+
+        let mut cpu = CPU::new(TimedInterruptBus { bus, nmi_cycle, irq_cycle });
+        cpu.program_counter = 0x8000;
+        cpu.flags = CpuFlags::UNUSED;
+        cpu.timing_mode = TimingMode::Cycle;
+        cpu
+    }
+
+    #[test]
+    fn test_nmi_polled_at_penultimate_cycle() {
+        let program = [0xEA, 0xEA, 0xEA]; // NOP, NOP, NOP
+
+        // during the first cycle of a 2-cycle NOP: serviced right after it
+        let mut cpu = prepare_timed_cpu(&program, Some(1), None);
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xEA, cycles: 2 });
+        assert!(matches!(cpu.step(), StepResult::InterruptServiced { interrupt: InterruptType::Nmi, .. }));
+
+        // during its last cycle: too late, the next instruction runs first
+        let mut cpu = prepare_timed_cpu(&program, Some(2), None);
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xEA, cycles: 2 });
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xEA, cycles: 2 });
+        assert!(matches!(cpu.step(), StepResult::InterruptServiced { interrupt: InterruptType::Nmi, .. }));
+    }
+
+    #[test]
+    fn test_taken_branch_delays_irq() {
+        let program = [0xD0, 0x00, 0xEA, 0xEA]; // BNE +0, NOP, NOP
+
+        // raised during the operand fetch, a 3-cycle branch doesn't see it
+        let mut cpu = prepare_timed_cpu(&program, None, Some(2));
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xD0, cycles: 3 });
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xEA, cycles: 2 });
+        assert!(matches!(cpu.step(), StepResult::InterruptServiced { interrupt: InterruptType::Irq, .. }));
+
+        // raised during the operation code fetch it does
+        let mut cpu = prepare_timed_cpu(&program, None, Some(1));
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xD0, cycles: 3 });
+        assert!(matches!(cpu.step(), StepResult::InterruptServiced { interrupt: InterruptType::Irq, .. }));
+    }
+
+    #[test]
+    fn test_nmi_hijacks_irq() {
+        // IRQ from the start, NMI during the IRQ sequence before the status push
+        let mut cpu = prepare_timed_cpu(&[0xEA], Some(5), Some(0));
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xEA, cycles: 2 });
+        assert_eq!(cpu.step(), StepResult::InterruptServiced { interrupt: InterruptType::Nmi, cycles: 7 });
+        assert_eq!(cpu.program_counter, 0x9000);
+        assert_eq!(cpu.pop_stack(), 0b0010_0000); // pushed as an IRQ
+    }
+
+    #[test]
+    fn test_interrupt_sequence_takes_seven_cycles() {
+        let mut bus = MockBus::new();
+        bus.load_program(&[0xEA], 0x8000);
+        bus.nmi_interrupt = Some(0xFF);
+        let mut cpu = CPU::new(bus);
+        cpu.program_counter = 0x8000;
+
+        cpu.step();
+
+        assert_eq!(cpu.step(), StepResult::InterruptServiced { interrupt: InterruptType::Nmi, cycles: 7 });
+    }
+
+    fn record_step(program: &[u8], timing_mode: TimingMode, x: u8) -> Vec<mock_bus::BusAccess> {
+        let mut bus = MockBus::new();
+        bus.load_program(program, 0x8000);
+        bus.memory[0x10] = 0x41;
+        bus.record_accesses = true;
+
+        {
+            let mut cpu = CPU::new(&mut bus);
+            cpu.program_counter = 0x8000;
+            cpu.register_x = x;
+            cpu.timing_mode = timing_mode;
+            cpu.step();
+        }
+
+        bus.accesses
+    }
+
+    fn access(cycle: usize, address: u16, data: u8, kind: mock_bus::BusAccessKind) -> mock_bus::BusAccess {
+        mock_bus::BusAccess { cycle, address, data, kind }
+    }
+
+    #[test]
+    fn test_cycle_mode_read_modify_write_double_write() {
+        use mock_bus::BusAccessKind::{Read, Write};
+
+        let accesses = record_step(&[0xE6, 0x10], TimingMode::Cycle, 0); // INC $10
+
+        assert_eq!(accesses, vec![
+            access(1, 0x8000, 0xE6, Read),
+            access(2, 0x8001, 0x10, Read),
+            access(3, 0x0010, 0x41, Read),
+            access(4, 0x0010, 0x41, Write),
+            access(5, 0x0010, 0x42, Write),
+        ]);
+    }
+
+    #[test]
+    fn test_cycle_mode_indexed_read_page_cross_dummy_read() {
+        use mock_bus::BusAccessKind::Read;
+
+        let accesses = record_step(&[0xBD, 0xF0, 0x02], TimingMode::Cycle, 0x20); // LDA $02F0,X
+
+        assert_eq!(accesses, vec![
+            access(1, 0x8000, 0xBD, Read),
+            access(2, 0x8001, 0xF0, Read),
+            access(3, 0x8002, 0x02, Read),
+            access(4, 0x0210, 0x00, Read),
+            access(5, 0x0310, 0x00, Read),
+        ]);
+    }
+
+    #[test]
+    fn test_cycle_mode_indexed_write_always_dummy_reads() {
+        use mock_bus::BusAccessKind::{Read, Write};
+
+        let accesses = record_step(&[0x9D, 0x80, 0x02], TimingMode::Cycle, 0x01); // STA $0280,X
+
+        assert_eq!(accesses, vec![
+            access(1, 0x8000, 0x9D, Read),
+            access(2, 0x8001, 0x80, Read),
+            access(3, 0x8002, 0x02, Read),
+            access(4, 0x0281, 0x00, Read),
+            access(5, 0x0281, 0x00, Write),
+        ]);
+    }
+
+    #[test]
+    fn test_instruction_mode_ticks_after_accesses() {
+        let accesses = record_step(&[0xE6, 0x10], TimingMode::Instruction, 0); // INC $10
+
+        assert_eq!(accesses.len(), 5);
+        assert!(accesses.iter().all(|access| access.cycle == 0));
+    }
+
+    fn prepare_variant_cpu(program: &[u8], variant: CpuVariant) -> CPU<MockBus> {
+        let mut cpu = prepare_test_cpu(program);
+        cpu.variant = variant;
+        cpu
+    }
+
+    #[test]
+    fn test_2a03_ignores_decimal_mode() {
+        let mut cpu = prepare_variant_cpu(&[0xF8, 0x69, 0x01], CpuVariant::Ricoh2A03); // SED, ADC #$01
+        cpu.register_a = 0x09;
+
+        cpu.step();
+        cpu.step();
+
+        assert_eq!(cpu.register_a, 0x0A);
+    }
+
+    #[test]
+    fn test_nmos_decimal_adc() {
+        let mut cpu = prepare_variant_cpu(&[0xF8, 0x69, 0x01, 0x69, 0x01], CpuVariant::Nmos6502); // SED, ADC #$01, ADC #$01
+        cpu.register_a = 0x09;
+
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.register_a, 0x10);
+        assert!(!cpu.contains_flag(CpuFlags::CARRY));
+
+        cpu.register_a = 0x99;
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x69, cycles: 2 });
+        assert_eq!(cpu.register_a, 0x00);
+        assert!(cpu.contains_flag(CpuFlags::CARRY));
+        // Z and N come from the binary sum $9A
+        assert!(!cpu.contains_flag(CpuFlags::ZERO));
+        assert!(cpu.contains_flag(CpuFlags::NEGATIVE));
+    }
+
+    #[test]
+    fn test_nmos_decimal_sbc() {
+        let mut cpu = prepare_variant_cpu(&[0xF8, 0xE9, 0x01, 0xE9, 0x01], CpuVariant::Nmos6502); // SED, SBC #$01, SBC #$01
+        cpu.register_a = 0x10;
+        cpu.insert_flag(CpuFlags::CARRY);
+
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.register_a, 0x09);
+        assert!(cpu.contains_flag(CpuFlags::CARRY));
+
+        cpu.register_a = 0x00;
+        cpu.step();
+        assert_eq!(cpu.register_a, 0x99);
+        assert!(!cpu.contains_flag(CpuFlags::CARRY));
+    }
+
+    // One decode table, ADC and SBC differ only in the adder
+    #[test]
+    fn test_2a03_and_nmos_decimal_adc_sbc() {
+        assert!(std::ptr::eq(CpuVariant::Ricoh2A03.operation_codes(), CpuVariant::Nmos6502.operation_codes()));
+
+        // SED, ADC #$01, SEC, SBC #$01
+        let program = [0xF8, 0x69, 0x01, 0x38, 0xE9, 0x01];
+        for (variant, sum, difference) in [(CpuVariant::Ricoh2A03, 0x0A, 0x0F), (CpuVariant::Nmos6502, 0x10, 0x09)] {
+            let mut cpu = prepare_variant_cpu(&program, variant);
+            cpu.register_a = 0x09;
+
+            cpu.step();
+            assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x69, cycles: 2 });
+            assert_eq!(cpu.register_a, sum, "{:?}", variant);
+
+            cpu.register_a = 0x10;
+            cpu.step();
+            assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xE9, cycles: 2 });
+            assert_eq!(cpu.register_a, difference, "{:?}", variant);
+        }
+    }
+
+    #[test]
+    fn test_nmos_decimal_arr() {
+        let mut cpu = prepare_variant_cpu(&[0xF8, 0x6B, 0xFF], CpuVariant::Nmos6502); // SED, ARR #$FF
+        cpu.register_a = 0x99;
+
+        cpu.step();
+        cpu.step();
+
+        // $99 >> 1 = $4C, both digits of the AND result are above 5 so both get adjusted
+        assert_eq!(cpu.register_a, 0xA2);
+        assert!(cpu.contains_flag(CpuFlags::CARRY));
+    }
+
+    #[test]
+    fn test_cmos_decimal_flags_and_extra_cycle() {
+        let mut cpu = prepare_variant_cpu(&[0xF8, 0x69, 0x01, 0xE9, 0x01], CpuVariant::Cmos65C02); // SED, ADC #$01, SBC #$01
+        cpu.register_a = 0x99;
+
+        cpu.step();
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x69, cycles: 3 });
+        assert_eq!(cpu.register_a, 0x00);
+        assert!(cpu.contains_flag(CpuFlags::CARRY));
+        assert!(cpu.contains_flag(CpuFlags::ZERO));
+        assert!(!cpu.contains_flag(CpuFlags::NEGATIVE));
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xE9, cycles: 3 });
+        assert_eq!(cpu.register_a, 0x99);
+        assert!(cpu.contains_flag(CpuFlags::NEGATIVE));
+    }
+
+    #[test]
+    fn test_program_counter_wraps_at_end_of_memory() {
+        let mut bus = MockBus::new();
+        bus.memory[0xFFFF] = 0xEA; // NOP
+        let mut cpu = CPU::new(bus);
+        cpu.program_counter = 0xFFFF;
+
+        cpu.step();
+        assert_eq!(cpu.program_counter, 0x0000);
+
+        cpu.mem_write(0xFFFE, 0xA9); // LDA #$42
+        cpu.mem_write(0xFFFF, 0x42);
+        cpu.program_counter = 0xFFFE;
+
+        cpu.step();
+        assert_eq!(cpu.program_counter, 0x0000);
+        assert_eq!(cpu.register_a, 0x42);
+    }
+
+    #[test]
+    fn test_jmp_indirect_page_wrap() {
+        for (variant, target) in [(CpuVariant::Nmos6502, 0x1234), (CpuVariant::Cmos65C02, 0x5634)] {
+            let mut cpu = prepare_variant_cpu(&[0x6C, 0xFF, 0x10], variant); // JMP ($10FF)
+            cpu.mem_write(0x10FF, 0x34);
+            cpu.mem_write(0x1000, 0x12);
+            cpu.mem_write(0x1100, 0x56);
+
+            cpu.step();
+
+            assert_eq!(cpu.program_counter, target, "{:?}", variant);
+        }
+    }
+
+    #[test]
+    fn test_cmos_jmp_absolute_indexed_indirect() {
+        let mut cpu = prepare_variant_cpu(&[0x7C, 0x00, 0x20], CpuVariant::Cmos65C02); // JMP ($2000,X)
+        cpu.register_x = 0x04;
+        cpu.mem_write(0x2004, 0x78);
+        cpu.mem_write(0x2005, 0x56);
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x7C, cycles: 6 });
+        assert_eq!(cpu.program_counter, 0x5678);
+    }
+
+    #[test]
+    fn test_cmos_operation_codes_are_nops_on_nmos() {
+        // STZ $10 on the 65C02 is an unofficial zero page NOP on the 2A03
+        let mut cpu = prepare_variant_cpu(&[0x64, 0x10], CpuVariant::Ricoh2A03);
+        cpu.mem_write(0x10, 0x42);
+        cpu.step();
+        assert_eq!(cpu.mem_read(0x10), 0x42);
+
+        let mut cpu = prepare_variant_cpu(&[0x64, 0x10], CpuVariant::Cmos65C02);
+        cpu.mem_write(0x10, 0x42);
+        cpu.step();
+        assert_eq!(cpu.mem_read(0x10), 0x00);
+    }
+
+    #[test]
+    fn test_cmos_instructions() {
+        let program = [
+            0xA9, 0x0F,       // LDA #$0F
+            0x1A,             // INC A
+            0x04, 0x20,       // TSB $20
+            0x14, 0x21,       // TRB $21
+            0xB2, 0x22,       // LDA ($22)
+            0x89, 0x01,       // BIT #$01
+            0xDA,             // PHX
+            0x7A,             // PLY
+            0x97, 0x20,       // SMB1 $20
+            0x07, 0x21,       // RMB0 $21
+        ];
+        let mut cpu = prepare_variant_cpu(&program, CpuVariant::Cmos65C02);
+        cpu.register_x = 0x77;
+        cpu.mem_write(0x20, 0x01);
+        cpu.mem_write(0x21, 0xFF);
+        cpu.mem_write(0x22, 0x00);
+        cpu.mem_write(0x23, 0x03);
+        cpu.mem_write(0x0300, 0x80);
+
+        for _ in 0..6 {
+            cpu.step();
+        }
+
+        assert_eq!(cpu.register_a, 0x80);
+        // BIT #imm leaves N and V alone
+        assert!(cpu.contains_flag(CpuFlags::ZERO));
+        assert!(cpu.contains_flag(CpuFlags::NEGATIVE));
+
+        for _ in 0..4 {
+            cpu.step();
+        }
+
+        assert_eq!(cpu.mem_read(0x20), 0x13);
+        assert_eq!(cpu.mem_read(0x21), 0xEE);
+        assert_eq!(cpu.register_y, 0x77);
+    }
+
+    #[test]
+    fn test_cmos_branches() {
+        let program = [
+            0x80, 0x02,       // BRA +2
+            0x00, 0x00,
+            0x0F, 0x10, 0x02, // BBR0 $10, +2
+            0x00, 0x00,
+            0x8F, 0x10, 0xFB, // BBS0 $10, -5 (not taken)
+            0xEA,             // NOP
+        ];
+        let mut cpu = prepare_variant_cpu(&program, CpuVariant::Cmos65C02);
+        cpu.mem_write(0x10, 0xFE);
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x80, cycles: 3 });
+        assert_eq!(cpu.program_counter, 0x8004);
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x0F, cycles: 6 });
+        assert_eq!(cpu.program_counter, 0x8009);
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0x8F, cycles: 5 });
+        assert_eq!(cpu.program_counter, 0x800C);
+    }
+
+    #[test]
+    fn test_cmos_read_modify_write_dummy_read() {
+        use mock_bus::BusAccessKind::{Read, Write};
+
+        let mut bus = MockBus::new();
+        bus.load_program(&[0xE6, 0x10], 0x8000); // INC $10
+        bus.memory[0x10] = 0x41;
+        bus.record_accesses = true;
+
+        let mut cpu = prepare_variant_cpu(&[], CpuVariant::Cmos65C02);
+        *cpu.bus_mut() = bus;
+        cpu.timing_mode = TimingMode::Cycle;
+        cpu.step();
+
+        assert_eq!(cpu.bus().accesses, vec![
+            access(1, 0x8000, 0xE6, Read),
+            access(2, 0x8001, 0x10, Read),
+            access(3, 0x0010, 0x41, Read),
+            access(4, 0x0010, 0x41, Read),
+            access(5, 0x0010, 0x42, Write),
+        ]);
+    }
+
+    #[test]
+    fn test_cmos_interrupt_clears_decimal_mode() {
+        for (variant, decimal) in [(CpuVariant::Nmos6502, true), (CpuVariant::Cmos65C02, false)] {
+            let mut cpu = prepare_variant_cpu(&[0xF8, 0x00], variant); // SED, BRK
+
+            cpu.step();
+            cpu.step();
+
+            assert_eq!(cpu.contains_flag(CpuFlags::DECIMAL_MODE), decimal, "{:?}", variant);
+        }
+    }
+
+    #[test]
+    fn test_cmos_wai_and_stp() {
+        let mut cpu = prepare_variant_cpu(&[0xCB, 0xE8, 0xDB], CpuVariant::Cmos65C02); // WAI, INX, STP
+        cpu.insert_flag(CpuFlags::INTERRUPT_DISABLE);
+
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xCB, cycles: 3 });
+        assert_eq!(cpu.step(), StepResult::Waiting);
+        assert_eq!(cpu.step(), StepResult::Waiting);
+        assert!(cpu.is_waiting());
+
+        // a masked IRQ resumes without being serviced
+        cpu.bus_mut().irq = IrqSource::EXTERNAL;
+        assert_eq!(cpu.step(), StepResult::Executed { operation_code: 0xE8, cycles: 3 });
+        assert_eq!(cpu.register_x, 1);
+
+        assert_eq!(cpu.step(), StepResult::Jammed);
+        assert_eq!(cpu.program_counter, 0x8002);
+        cpu.mem_write(0xFFFC, 0x00);
+        cpu.mem_write(0xFFFD, 0x80);
+        cpu.reset();
+        assert!(!cpu.is_jammed());
+    }
 }
@@ -1,5 +1,3 @@
-use lazy_static::lazy_static;
-use std::collections::HashMap;
 use crate::emulator::cpu::addressing::AddressingMode;
 
 /*
@@ -14,284 +12,634 @@ pub struct OperationCode {
     pub (super) addressing_mode: AddressingMode,
     pub (super) bytes: u8,
     pub cycles: u8,
+    pub official: bool,
 }
 
 impl OperationCode {
-    fn new(code: u8, mnemonic: &'static str, addressing_mode: AddressingMode, bytes: u8, cycles: u8)
-        -> Self { OperationCode { code, mnemonic, addressing_mode, bytes, cycles } }
+    const fn new(code: u8, mnemonic: &'static str, addressing_mode: AddressingMode, bytes: u8, cycles: u8)
+        -> Self { OperationCode { code, mnemonic, addressing_mode, bytes, cycles, official: true } }
+
+    const fn unofficial(code: u8, mnemonic: &'static str, addressing_mode: AddressingMode, bytes: u8, cycles: u8)
+        -> Self { OperationCode { code, mnemonic, addressing_mode, bytes, cycles, official: false } }
+}
+
+// Grouped by instruction
+pub static CPU_OPERATION_CODES_LIST: [OperationCode; 256] = [
+    // ADC - Add with Carry
+    OperationCode::new(0x69, "ADC", AddressingMode::Immediate, 2, 2),
+    OperationCode::new(0x65, "ADC", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0x75, "ADC", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::new(0x6D, "ADC", AddressingMode::Absolute, 3, 4),
+    OperationCode::new(0x7D, "ADC", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::new(0x79, "ADC", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::new(0x61, "ADC", AddressingMode::IndirectX, 2, 6),
+    OperationCode::new(0x71, "ADC", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
+
+    // AND - Logical AND
+    OperationCode::new(0x29, "AND", AddressingMode::Immediate, 2, 2),
+    OperationCode::new(0x25, "AND", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0x35, "AND", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::new(0x2D, "AND", AddressingMode::Absolute, 3, 4),
+    OperationCode::new(0x3D, "AND", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::new(0x39, "AND", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::new(0x21, "AND", AddressingMode::IndirectX, 2, 6),
+    OperationCode::new(0x31, "AND", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
+
+    // ASL - Arithmetic Shift Left
+    OperationCode::new(0x0A, "ASL", AddressingMode::Accumulator, 1, 2),
+    OperationCode::new(0x06, "ASL", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0x16, "ASL", AddressingMode::ZeroPageX, 2, 6),
+    OperationCode::new(0x0E, "ASL", AddressingMode::Absolute, 3, 6),
+    OperationCode::new(0x1E, "ASL", AddressingMode::AbsoluteX, 3, 7),
+
+    // BCC - Branch if Carry Clear
+    OperationCode::new(0x90, "BCC", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
+
+    // BCS - Branch if Carry Set
+    OperationCode::new(0xB0, "BCS", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
+
+    // BEQ - Branch if Equal
+    OperationCode::new(0xF0, "BEQ", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
+
+    // BIT - Bit Test
+    OperationCode::new(0x24, "BIT", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0x2C, "BIT", AddressingMode::Absolute, 3, 4),
+
+    // BMI - Branch if Minus
+    OperationCode::new(0x30, "BMI", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
+
+    // BNE - Branch if Not Equal
+    OperationCode::new(0xD0, "BNE", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
+
+    // BPL - Branch if Positive
+    OperationCode::new(0x10, "BPL", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
+
+    // BRK - Force Interrupt
+    OperationCode::new(0x00, "BRK", AddressingMode::Implicit, 1, 7),
+
+    // BVC - Branch if Overflow Clear
+    OperationCode::new(0x50, "BVC", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
+
+    // BVS - Branch if Overflow Set
+    OperationCode::new(0x70, "BVS", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
+
+    // CLC - Clear Carry Flag
+    OperationCode::new(0x18, "CLC", AddressingMode::Implicit, 1, 2),
+
+    // CLD - Clear Decimal Mode
+    OperationCode::new(0xD8, "CLD", AddressingMode::Implicit, 1, 2),
+
+    // CLI - Clear Interrupt Disable
+    OperationCode::new(0x58, "CLI", AddressingMode::Implicit, 1, 2),
+
+    // CLV - Clear Overflow Flag
+    OperationCode::new(0xB8, "CLV", AddressingMode::Implicit, 1, 2),
+
+    // CMP - Compare
+    OperationCode::new(0xC9, "CMP", AddressingMode::Immediate, 2, 2),
+    OperationCode::new(0xC5, "CMP", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0xD5, "CMP", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::new(0xCD, "CMP", AddressingMode::Absolute, 3, 4),
+    OperationCode::new(0xDD, "CMP", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::new(0xD9, "CMP", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::new(0xC1, "CMP", AddressingMode::IndirectX, 2, 6),
+    OperationCode::new(0xD1, "CMP", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
+
+    // CPX - Compare X Register
+    OperationCode::new(0xE0, "CPX", AddressingMode::Immediate, 2, 2),
+    OperationCode::new(0xE4, "CPX", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0xEC, "CPX", AddressingMode::Absolute, 3, 4),
+
+    // CPY - Compare Y Register
+    OperationCode::new(0xC0, "CPY", AddressingMode::Immediate, 2, 2),
+    OperationCode::new(0xC4, "CPY", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0xCC, "CPY", AddressingMode::Absolute, 3, 4),
+
+    // DEC - Decrement Memory
+    OperationCode::new(0xC6, "DEC", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0xD6, "DEC", AddressingMode::ZeroPageX, 2, 6),
+    OperationCode::new(0xCE, "DEC", AddressingMode::Absolute, 3, 6),
+    OperationCode::new(0xDE, "DEC", AddressingMode::AbsoluteX, 3, 7),
+
+    // DEX - Decrement X Register
+    OperationCode::new(0xCA, "DEX", AddressingMode::Implicit, 1, 2),
+
+    // DEY - Decrement Y Register
+    OperationCode::new(0x88, "DEY", AddressingMode::Implicit, 1, 2),
+
+    // EOR - Exclusive OR
+    OperationCode::new(0x49, "EOR", AddressingMode::Immediate, 2, 2),
+    OperationCode::new(0x45, "EOR", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0x55, "EOR", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::new(0x4D, "EOR", AddressingMode::Absolute, 3, 4),
+    OperationCode::new(0x5D, "EOR", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::new(0x59, "EOR", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::new(0x41, "EOR", AddressingMode::IndirectX, 2, 6),
+    OperationCode::new(0x51, "EOR", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
+
+    // INC - Increment Memory
+    OperationCode::new(0xE6, "INC", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0xF6, "INC", AddressingMode::ZeroPageX, 2, 6),
+    OperationCode::new(0xEE, "INC", AddressingMode::Absolute, 3, 6),
+    OperationCode::new(0xFE, "INC", AddressingMode::AbsoluteX, 3, 7),
+
+    // INX - Increment X Register
+    OperationCode::new(0xE8, "INX", AddressingMode::Implicit, 1, 2),
+
+    // INY - Increment Y Register
+    OperationCode::new(0xC8, "INY", AddressingMode::Implicit, 1, 2),
+
+    // JMP - Jump
+    OperationCode::new(0x4C, "JMP", AddressingMode::Absolute, 3, 3),
+    OperationCode::new(0x6C, "JMP", AddressingMode::Indirect, 3, 5),
+
+    // JSR - Jump to Subroutine
+    OperationCode::new(0x20, "JSR", AddressingMode::Absolute, 3, 6),
+
+    // LDA - Load Accumulator
+    OperationCode::new(0xA9, "LDA", AddressingMode::Immediate, 2, 2),
+    OperationCode::new(0xA5, "LDA", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0xB5, "LDA", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::new(0xAD, "LDA", AddressingMode::Absolute, 3, 4),
+    OperationCode::new(0xBD, "LDA", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::new(0xB9, "LDA", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::new(0xA1, "LDA", AddressingMode::IndirectX, 2, 6),
+    OperationCode::new(0xB1, "LDA", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
+
+    // LDX - Load X Register
+    OperationCode::new(0xA2, "LDX", AddressingMode::Immediate, 2, 2),
+    OperationCode::new(0xA6, "LDX", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0xB6, "LDX", AddressingMode::ZeroPageY, 2, 4),
+    OperationCode::new(0xAE, "LDX", AddressingMode::Absolute, 3, 4),
+    OperationCode::new(0xBE, "LDX", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
+
+    // LDY - Load Y Register
+    OperationCode::new(0xA0, "LDY", AddressingMode::Immediate, 2, 2),
+    OperationCode::new(0xA4, "LDY", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0xB4, "LDY", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::new(0xAC, "LDY", AddressingMode::Absolute, 3, 4),
+    OperationCode::new(0xBC, "LDY", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+
+    // LSR - Logical Shift Right
+    OperationCode::new(0x4A, "LSR", AddressingMode::Accumulator, 1, 2),
+    OperationCode::new(0x46, "LSR", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0x56, "LSR", AddressingMode::ZeroPageX, 2, 6),
+    OperationCode::new(0x4E, "LSR", AddressingMode::Absolute, 3, 6),
+    OperationCode::new(0x5E, "LSR", AddressingMode::AbsoluteX, 3, 7),
+
+    // NOP - No Operation
+    OperationCode::new(0xEA, "NOP", AddressingMode::Implicit, 1, 2),
+
+    // ORA - Logical Inclusive OR
+    OperationCode::new(0x09, "ORA", AddressingMode::Immediate, 2, 2),
+    OperationCode::new(0x05, "ORA", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0x15, "ORA", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::new(0x0D, "ORA", AddressingMode::Absolute, 3, 4),
+    OperationCode::new(0x1D, "ORA", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::new(0x19, "ORA", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::new(0x01, "ORA", AddressingMode::IndirectX, 2, 6),
+    OperationCode::new(0x11, "ORA", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
+
+    // PHA - Push Accumulator
+    OperationCode::new(0x48, "PHA", AddressingMode::Implicit, 1, 3),
+
+    // PHP - Push Processor Status
+    OperationCode::new(0x08, "PHP", AddressingMode::Implicit, 1, 3),
+
+    // PLA - Pull Accumulator
+    OperationCode::new(0x68, "PLA", AddressingMode::Implicit, 1, 4),
+
+    // PLP - Pull Processor Status
+    OperationCode::new(0x28, "PLP", AddressingMode::Implicit, 1, 4),
+
+    // ROL - Rotate Left
+    OperationCode::new(0x2A, "ROL", AddressingMode::Accumulator, 1, 2),
+    OperationCode::new(0x26, "ROL", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0x36, "ROL", AddressingMode::ZeroPageX, 2, 6),
+    OperationCode::new(0x2E, "ROL", AddressingMode::Absolute, 3, 6),
+    OperationCode::new(0x3E, "ROL", AddressingMode::AbsoluteX, 3, 7),
+
+    // ROR - Rotate Right
+    OperationCode::new(0x6A, "ROR", AddressingMode::Accumulator, 1, 2),
+    OperationCode::new(0x66, "ROR", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0x76, "ROR", AddressingMode::ZeroPageX, 2, 6),
+    OperationCode::new(0x6E, "ROR", AddressingMode::Absolute, 3, 6),
+    OperationCode::new(0x7E, "ROR", AddressingMode::AbsoluteX, 3, 7),
+
+    // RTI - Return from Interrupt
+    OperationCode::new(0x40, "RTI", AddressingMode::Implicit, 1, 6),
+
+    // RTS - Return from Subroutine
+    OperationCode::new(0x60, "RTS", AddressingMode::Implicit, 1, 6),
+
+    // SBC - Subtract with Carry
+    OperationCode::new(0xE9, "SBC", AddressingMode::Immediate, 2, 2),
+    OperationCode::new(0xE5, "SBC", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0xF5, "SBC", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::new(0xED, "SBC", AddressingMode::Absolute, 3, 4),
+    OperationCode::new(0xFD, "SBC", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::new(0xF9, "SBC", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::new(0xE1, "SBC", AddressingMode::IndirectX, 2, 6),
+    OperationCode::new(0xF1, "SBC", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
+
+    // SEC - Set Carry Flag
+    OperationCode::new(0x38, "SEC", AddressingMode::Implicit, 1, 2),
+
+    // SED - Set Decimal Flag
+    OperationCode::new(0xF8, "SED", AddressingMode::Implicit, 1, 2),
+
+    // SEI - Set Interrupt Disable
+    OperationCode::new(0x78, "SEI", AddressingMode::Implicit, 1, 2),
+
+    // STA - Store Accumulator
+    OperationCode::new(0x85, "STA", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0x95, "STA", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::new(0x8D, "STA", AddressingMode::Absolute, 3, 4),
+    OperationCode::new(0x9D, "STA", AddressingMode::AbsoluteX, 3, 5),
+    OperationCode::new(0x99, "STA", AddressingMode::AbsoluteY, 3, 5),
+    OperationCode::new(0x81, "STA", AddressingMode::IndirectX, 2, 6),
+    OperationCode::new(0x91, "STA", AddressingMode::IndirectY, 2, 6),
+
+    // STX - Store X Register
+    OperationCode::new(0x86, "STX", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0x96, "STX", AddressingMode::ZeroPageY, 2, 4),
+    OperationCode::new(0x8E, "STX", AddressingMode::Absolute, 3, 4),
+
+    // STY - Store Y Register
+    OperationCode::new(0x84, "STY", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0x94, "STY", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::new(0x8C, "STY", AddressingMode::Absolute, 3, 4),
+
+    // TAX - Transfer Accumulator to X
+    OperationCode::new(0xAA, "TAX", AddressingMode::Implicit, 1, 2),
+
+    // TAY - Transfer Accumulator to Y
+    OperationCode::new(0xA8, "TAY", AddressingMode::Implicit, 1, 2),
+
+    // TSX - Transfer Stack Pointer to X
+    OperationCode::new(0xBA, "TSX", AddressingMode::Implicit, 1, 2),
+
+    // TXA - Transfer X to Accumulator
+    OperationCode::new(0x8A, "TXA", AddressingMode::Implicit, 1, 2),
+
+    // TXS - Transfer X to Stack Pointer
+    OperationCode::new(0x9A, "TXS", AddressingMode::Implicit, 1, 2),
+
+    // TYA - Transfer Y to Accumulator
+    OperationCode::new(0x98, "TYA", AddressingMode::Implicit, 1, 2),
+
+    /*
+    Unofficial operation codes
+    https://www.nesdev.org/wiki/CPU_unofficial_opcodes
+    https://www.nesdev.org/6502_cpu.txt
+    */
+
+    // ALR - AND + LSR
+    OperationCode::unofficial(0x4B, "ALR", AddressingMode::Immediate, 2, 2),
+
+    // ANC - AND, Carry = Negative
+    OperationCode::unofficial(0x0B, "ANC", AddressingMode::Immediate, 2, 2),
+    OperationCode::unofficial(0x2B, "ANC", AddressingMode::Immediate, 2, 2),
+
+    // ANE (XAA) - unstable, (A | magic) & X & operand
+    OperationCode::unofficial(0x8B, "ANE", AddressingMode::Immediate, 2, 2),
+
+    // ARR - AND + ROR
+    OperationCode::unofficial(0x6B, "ARR", AddressingMode::Immediate, 2, 2),
+
+    // DCP - DEC + CMP
+    OperationCode::unofficial(0xC7, "DCP", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::unofficial(0xD7, "DCP", AddressingMode::ZeroPageX, 2, 6),
+    OperationCode::unofficial(0xCF, "DCP", AddressingMode::Absolute, 3, 6),
+    OperationCode::unofficial(0xDF, "DCP", AddressingMode::AbsoluteX, 3, 7),
+    OperationCode::unofficial(0xDB, "DCP", AddressingMode::AbsoluteY, 3, 7),
+    OperationCode::unofficial(0xC3, "DCP", AddressingMode::IndirectX, 2, 8),
+    OperationCode::unofficial(0xD3, "DCP", AddressingMode::IndirectY, 2, 8),
+
+    // ISC - INC + SBC
+    OperationCode::unofficial(0xE7, "ISC", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::unofficial(0xF7, "ISC", AddressingMode::ZeroPageX, 2, 6),
+    OperationCode::unofficial(0xEF, "ISC", AddressingMode::Absolute, 3, 6),
+    OperationCode::unofficial(0xFF, "ISC", AddressingMode::AbsoluteX, 3, 7),
+    OperationCode::unofficial(0xFB, "ISC", AddressingMode::AbsoluteY, 3, 7),
+    OperationCode::unofficial(0xE3, "ISC", AddressingMode::IndirectX, 2, 8),
+    OperationCode::unofficial(0xF3, "ISC", AddressingMode::IndirectY, 2, 8),
+
+    // JAM (KIL) - halts the CPU
+    OperationCode::unofficial(0x02, "JAM", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0x12, "JAM", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0x22, "JAM", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0x32, "JAM", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0x42, "JAM", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0x52, "JAM", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0x62, "JAM", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0x72, "JAM", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0x92, "JAM", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0xB2, "JAM", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0xD2, "JAM", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0xF2, "JAM", AddressingMode::Implicit, 1, 2),
+
+    // LAS - memory & S into A, X and S
+    OperationCode::unofficial(0xBB, "LAS", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
+
+    // LAX - LDA + LDX
+    OperationCode::unofficial(0xA7, "LAX", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::unofficial(0xB7, "LAX", AddressingMode::ZeroPageY, 2, 4),
+    OperationCode::unofficial(0xAF, "LAX", AddressingMode::Absolute, 3, 4),
+    OperationCode::unofficial(0xBF, "LAX", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::unofficial(0xA3, "LAX", AddressingMode::IndirectX, 2, 6),
+    OperationCode::unofficial(0xB3, "LAX", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
+
+    // LXA - unstable, (A | magic) & operand into A and X
+    OperationCode::unofficial(0xAB, "LXA", AddressingMode::Immediate, 2, 2),
+
+    // NOP - No Operation (with operand reads)
+    OperationCode::unofficial(0x1A, "NOP", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0x3A, "NOP", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0x5A, "NOP", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0x7A, "NOP", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0xDA, "NOP", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0xFA, "NOP", AddressingMode::Implicit, 1, 2),
+    OperationCode::unofficial(0x80, "NOP", AddressingMode::Immediate, 2, 2),
+    OperationCode::unofficial(0x82, "NOP", AddressingMode::Immediate, 2, 2),
+    OperationCode::unofficial(0x89, "NOP", AddressingMode::Immediate, 2, 2),
+    OperationCode::unofficial(0xC2, "NOP", AddressingMode::Immediate, 2, 2),
+    OperationCode::unofficial(0xE2, "NOP", AddressingMode::Immediate, 2, 2),
+    OperationCode::unofficial(0x04, "NOP", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::unofficial(0x44, "NOP", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::unofficial(0x64, "NOP", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::unofficial(0x14, "NOP", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::unofficial(0x34, "NOP", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::unofficial(0x54, "NOP", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::unofficial(0x74, "NOP", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::unofficial(0xD4, "NOP", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::unofficial(0xF4, "NOP", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::unofficial(0x0C, "NOP", AddressingMode::Absolute, 3, 4),
+    OperationCode::unofficial(0x1C, "NOP", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::unofficial(0x3C, "NOP", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::unofficial(0x5C, "NOP", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::unofficial(0x7C, "NOP", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::unofficial(0xDC, "NOP", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+    OperationCode::unofficial(0xFC, "NOP", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+
+    // RLA - ROL + AND
+    OperationCode::unofficial(0x27, "RLA", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::unofficial(0x37, "RLA", AddressingMode::ZeroPageX, 2, 6),
+    OperationCode::unofficial(0x2F, "RLA", AddressingMode::Absolute, 3, 6),
+    OperationCode::unofficial(0x3F, "RLA", AddressingMode::AbsoluteX, 3, 7),
+    OperationCode::unofficial(0x3B, "RLA", AddressingMode::AbsoluteY, 3, 7),
+    OperationCode::unofficial(0x23, "RLA", AddressingMode::IndirectX, 2, 8),
+    OperationCode::unofficial(0x33, "RLA", AddressingMode::IndirectY, 2, 8),
+
+    // RRA - ROR + ADC
+    OperationCode::unofficial(0x67, "RRA", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::unofficial(0x77, "RRA", AddressingMode::ZeroPageX, 2, 6),
+    OperationCode::unofficial(0x6F, "RRA", AddressingMode::Absolute, 3, 6),
+    OperationCode::unofficial(0x7F, "RRA", AddressingMode::AbsoluteX, 3, 7),
+    OperationCode::unofficial(0x7B, "RRA", AddressingMode::AbsoluteY, 3, 7),
+    OperationCode::unofficial(0x63, "RRA", AddressingMode::IndirectX, 2, 8),
+    OperationCode::unofficial(0x73, "RRA", AddressingMode::IndirectY, 2, 8),
+
+    // SAX - store A & X
+    OperationCode::unofficial(0x87, "SAX", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::unofficial(0x97, "SAX", AddressingMode::ZeroPageY, 2, 4),
+    OperationCode::unofficial(0x8F, "SAX", AddressingMode::Absolute, 3, 4),
+    OperationCode::unofficial(0x83, "SAX", AddressingMode::IndirectX, 2, 6),
+
+    // SBC - same as the official $E9
+    OperationCode::unofficial(0xEB, "SBC", AddressingMode::Immediate, 2, 2),
+
+    // SBX (AXS) - X = (A & X) - operand
+    OperationCode::unofficial(0xCB, "SBX", AddressingMode::Immediate, 2, 2),
+
+    // SHA (AHX) - unstable, store A & X & (high byte of address + 1)
+    OperationCode::unofficial(0x9F, "SHA", AddressingMode::AbsoluteY, 3, 5),
+    OperationCode::unofficial(0x93, "SHA", AddressingMode::IndirectY, 2, 6),
+
+    // SHX - unstable, store X & (high byte of address + 1)
+    OperationCode::unofficial(0x9E, "SHX", AddressingMode::AbsoluteY, 3, 5),
+
+    // SHY - unstable, store Y & (high byte of address + 1)
+    OperationCode::unofficial(0x9C, "SHY", AddressingMode::AbsoluteX, 3, 5),
+
+    // SLO - ASL + ORA
+    OperationCode::unofficial(0x07, "SLO", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::unofficial(0x17, "SLO", AddressingMode::ZeroPageX, 2, 6),
+    OperationCode::unofficial(0x0F, "SLO", AddressingMode::Absolute, 3, 6),
+    OperationCode::unofficial(0x1F, "SLO", AddressingMode::AbsoluteX, 3, 7),
+    OperationCode::unofficial(0x1B, "SLO", AddressingMode::AbsoluteY, 3, 7),
+    OperationCode::unofficial(0x03, "SLO", AddressingMode::IndirectX, 2, 8),
+    OperationCode::unofficial(0x13, "SLO", AddressingMode::IndirectY, 2, 8),
+
+    // SRE - LSR + EOR
+    OperationCode::unofficial(0x47, "SRE", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::unofficial(0x57, "SRE", AddressingMode::ZeroPageX, 2, 6),
+    OperationCode::unofficial(0x4F, "SRE", AddressingMode::Absolute, 3, 6),
+    OperationCode::unofficial(0x5F, "SRE", AddressingMode::AbsoluteX, 3, 7),
+    OperationCode::unofficial(0x5B, "SRE", AddressingMode::AbsoluteY, 3, 7),
+    OperationCode::unofficial(0x43, "SRE", AddressingMode::IndirectX, 2, 8),
+    OperationCode::unofficial(0x53, "SRE", AddressingMode::IndirectY, 2, 8),
+
+    // TAS (SHS) - unstable, S = A & X, store S & (high byte of address + 1)
+    OperationCode::unofficial(0x9B, "TAS", AddressingMode::AbsoluteY, 3, 5)
+];
+
+// Indexed by operation code, built at compile time
+pub static CPU_OPERATION_CODES: [OperationCode; 256] = decode_table(&CPU_OPERATION_CODES_LIST);
+
+const fn decode_table(list: &[OperationCode; 256]) -> [OperationCode; 256] {
+    let mut table = [list[0]; 256];
+    let mut defined = [false; 256];
+
+    let mut i = 0;
+    while i < list.len() {
+        let code = list[i].code as usize;
+        if defined[code] {
+            panic!("Operation code defined twice");
+        }
+        defined[code] = true;
+        table[code] = list[i];
+        i += 1;
+    }
+
+    table
 }
 
-lazy_static! {
-    pub static ref CPU_OPERATION_CODES_VEC: Vec<OperationCode> = vec![
-        // ADC - Add with Carry
-        OperationCode::new(0x69, "ADC", AddressingMode::Immediate, 2, 2),
-        OperationCode::new(0x65, "ADC", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0x75, "ADC", AddressingMode::ZeroPageX, 2, 4),
-        OperationCode::new(0x6D, "ADC", AddressingMode::Absolute, 3, 4),
-        OperationCode::new(0x7D, "ADC", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
-        OperationCode::new(0x79, "ADC", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
-        OperationCode::new(0x61, "ADC", AddressingMode::IndirectX, 2, 6),
-        OperationCode::new(0x71, "ADC", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
-
-        // AND - Logical AND
-        OperationCode::new(0x29, "AND", AddressingMode::Immediate, 2, 2),
-        OperationCode::new(0x25, "AND", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0x35, "AND", AddressingMode::ZeroPageX, 2, 4),
-        OperationCode::new(0x2D, "AND", AddressingMode::Absolute, 3, 4),
-        OperationCode::new(0x3D, "AND", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
-        OperationCode::new(0x39, "AND", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
-        OperationCode::new(0x21, "AND", AddressingMode::IndirectX, 2, 6),
-        OperationCode::new(0x31, "AND", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
-
-        // ASL - Arithmetic Shift Left
-        OperationCode::new(0x0A, "ASL", AddressingMode::Accumulator, 1, 2),
-        OperationCode::new(0x06, "ASL", AddressingMode::ZeroPage, 2, 5),
-        OperationCode::new(0x16, "ASL", AddressingMode::ZeroPageX, 2, 6),
-        OperationCode::new(0x0E, "ASL", AddressingMode::Absolute, 3, 6),
-        OperationCode::new(0x1E, "ASL", AddressingMode::AbsoluteX, 3, 7),
-
-        // BCC - Branch if Carry Clear
-        OperationCode::new(0x90, "BCC", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
-
-        // BCS - Branch if Carry Set
-        OperationCode::new(0xB0, "BCS", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
-
-        // BEQ - Branch if Equal
-        OperationCode::new(0xF0, "BEQ", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
-
-        // BIT - Bit Test
-        OperationCode::new(0x24, "BIT", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0x2C, "BIT", AddressingMode::Absolute, 3, 4),
-
-        // BMI - Branch if Minus
-        OperationCode::new(0x30, "BMI", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
-
-        // BNE - Branch if Not Equal
-        OperationCode::new(0xD0, "BNE", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
-
-        // BPL - Branch if Positive
-        OperationCode::new(0x10, "BPL", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
-
-        // BRK - Force Interrupt
-        OperationCode::new(0x00, "BRK", AddressingMode::Implicit, 1, 7),
-
-        // BVC - Branch if Overflow Clear
-        OperationCode::new(0x50, "BVC", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
-
-        // BVS - Branch if Overflow Set
-        OperationCode::new(0x70, "BVS", AddressingMode::Relative, 2, 2 /* (+1 if branch succeeds+2 if to a new page) */),
-
-        // CLC - Clear Carry Flag
-        OperationCode::new(0x18, "CLC", AddressingMode::Implicit, 1, 2),
-
-        // CLD - Clear Decimal Mode
-        OperationCode::new(0xD8, "CLD", AddressingMode::Implicit, 1, 2),
-
-        // CLI - Clear Interrupt Disable
-        OperationCode::new(0x58, "CLI", AddressingMode::Implicit, 1, 2),
-
-        // CLV - Clear Overflow Flag
-        OperationCode::new(0xB8, "CLV", AddressingMode::Implicit, 1, 2),
-
-        // CMP - Compare
-        OperationCode::new(0xC9, "CMP", AddressingMode::Immediate, 2, 2),
-        OperationCode::new(0xC5, "CMP", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0xD5, "CMP", AddressingMode::ZeroPageX, 2, 4),
-        OperationCode::new(0xCD, "CMP", AddressingMode::Absolute, 3, 4),
-        OperationCode::new(0xDD, "CMP", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
-        OperationCode::new(0xD9, "CMP", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
-        OperationCode::new(0xC1, "CMP", AddressingMode::IndirectX, 2, 6),
-        OperationCode::new(0xD1, "CMP", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
-
-        // CPX - Compare X Register
-        OperationCode::new(0xE0, "CPX", AddressingMode::Immediate, 2, 2),
-        OperationCode::new(0xE4, "CPX", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0xEC, "CPX", AddressingMode::Absolute, 3, 4),
-
-        // CPY - Compare Y Register
-        OperationCode::new(0xC0, "CPY", AddressingMode::Immediate, 2, 2),
-        OperationCode::new(0xC4, "CPY", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0xCC, "CPY", AddressingMode::Absolute, 3, 4),
-
-        // DEC - Decrement Memory
-        OperationCode::new(0xC6, "DEC", AddressingMode::ZeroPage, 2, 5),
-        OperationCode::new(0xD6, "DEC", AddressingMode::ZeroPageX, 2, 6),
-        OperationCode::new(0xCE, "DEC", AddressingMode::Absolute, 3, 6),
-        OperationCode::new(0xDE, "DEC", AddressingMode::AbsoluteX, 3, 7),
-
-        // DEX - Decrement X Register
-        OperationCode::new(0xCA, "DEX", AddressingMode::Implicit, 1, 2),
-
-        // DEY - Decrement Y Register
-        OperationCode::new(0x88, "DEY", AddressingMode::Implicit, 1, 2),
-
-        // EOR - Exclusive OR
-        OperationCode::new(0x49, "EOR", AddressingMode::Immediate, 2, 2),
-        OperationCode::new(0x45, "EOR", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0x55, "EOR", AddressingMode::ZeroPageX, 2, 4),
-        OperationCode::new(0x4D, "EOR", AddressingMode::Absolute, 3, 4),
-        OperationCode::new(0x5D, "EOR", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
-        OperationCode::new(0x59, "EOR", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
-        OperationCode::new(0x41, "EOR", AddressingMode::IndirectX, 2, 6),
-        OperationCode::new(0x51, "EOR", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
-
-        // INC - Increment Memory
-        OperationCode::new(0xE6, "INC", AddressingMode::ZeroPage, 2, 5),
-        OperationCode::new(0xF6, "INC", AddressingMode::ZeroPageX, 2, 6),
-        OperationCode::new(0xEE, "INC", AddressingMode::Absolute, 3, 6),
-        OperationCode::new(0xFE, "INC", AddressingMode::AbsoluteX, 3, 7),
-
-        // INX - Increment X Register
-        OperationCode::new(0xE8, "INX", AddressingMode::Implicit, 1, 2),
-
-        // INY - Increment Y Register
-        OperationCode::new(0xC8, "INY", AddressingMode::Implicit, 1, 2),
-
-        // JMP - Jump
-        OperationCode::new(0x4C, "JMP", AddressingMode::Absolute, 3, 3),
-        OperationCode::new(0x6C, "JMP", AddressingMode::Indirect, 3, 5),
-
-        // JSR - Jump to Subroutine
-        OperationCode::new(0x20, "JSR", AddressingMode::Absolute, 3, 6),
-
-        // LDA - Load Accumulator
-        OperationCode::new(0xA9, "LDA", AddressingMode::Immediate, 2, 2),
-        OperationCode::new(0xA5, "LDA", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0xB5, "LDA", AddressingMode::ZeroPageX, 2, 4),
-        OperationCode::new(0xAD, "LDA", AddressingMode::Absolute, 3, 4),
-        OperationCode::new(0xBD, "LDA", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
-        OperationCode::new(0xB9, "LDA", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
-        OperationCode::new(0xA1, "LDA", AddressingMode::IndirectX, 2, 6),
-        OperationCode::new(0xB1, "LDA", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
-
-        // LDX - Load X Register
-        OperationCode::new(0xA2, "LDX", AddressingMode::Immediate, 2, 2),
-        OperationCode::new(0xA6, "LDX", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0xB6, "LDX", AddressingMode::ZeroPageY, 2, 4),
-        OperationCode::new(0xAE, "LDX", AddressingMode::Absolute, 3, 4),
-        OperationCode::new(0xBE, "LDX", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
-
-        // LDY - Load Y Register
-        OperationCode::new(0xA0, "LDY", AddressingMode::Immediate, 2, 2),
-        OperationCode::new(0xA4, "LDY", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0xB4, "LDY", AddressingMode::ZeroPageX, 2, 4),
-        OperationCode::new(0xAC, "LDY", AddressingMode::Absolute, 3, 4),
-        OperationCode::new(0xBC, "LDY", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
-
-        // LSR - Logical Shift Right
-        OperationCode::new(0x4A, "LSR", AddressingMode::Accumulator, 1, 2),
-        OperationCode::new(0x46, "LSR", AddressingMode::ZeroPage, 2, 5),
-        OperationCode::new(0x56, "LSR", AddressingMode::ZeroPageX, 2, 6),
-        OperationCode::new(0x4E, "LSR", AddressingMode::Absolute, 3, 6),
-        OperationCode::new(0x5E, "LSR", AddressingMode::AbsoluteX, 3, 7),
-
-        // NOP - No Operation
-        OperationCode::new(0xEA, "NOP", AddressingMode::Implicit, 1, 2),
-
-        // ORA - Logical Inclusive OR
-        OperationCode::new(0x09, "ORA", AddressingMode::Immediate, 2, 2),
-        OperationCode::new(0x05, "ORA", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0x15, "ORA", AddressingMode::ZeroPageX, 2, 4),
-        OperationCode::new(0x0D, "ORA", AddressingMode::Absolute, 3, 4),
-        OperationCode::new(0x1D, "ORA", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
-        OperationCode::new(0x19, "ORA", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
-        OperationCode::new(0x01, "ORA", AddressingMode::IndirectX, 2, 6),
-        OperationCode::new(0x11, "ORA", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
-
-        // PHA - Push Accumulator
-        OperationCode::new(0x48, "PHA", AddressingMode::Implicit, 1, 3),
-
-        // PHP - Push Processor Status
-        OperationCode::new(0x08, "PHP", AddressingMode::Implicit, 1, 3),
-
-        // PLA - Pull Accumulator
-        OperationCode::new(0x68, "PLA", AddressingMode::Implicit, 1, 4),
-
-        // PLP - Pull Processor Status
-        OperationCode::new(0x28, "PLP", AddressingMode::Implicit, 1, 4),
-
-        // ROL - Rotate Left
-        OperationCode::new(0x2A, "ROL", AddressingMode::Accumulator, 1, 2),
-        OperationCode::new(0x26, "ROL", AddressingMode::ZeroPage, 2, 5),
-        OperationCode::new(0x36, "ROL", AddressingMode::ZeroPageX, 2, 6),
-        OperationCode::new(0x2E, "ROL", AddressingMode::Absolute, 3, 6),
-        OperationCode::new(0x3E, "ROL", AddressingMode::AbsoluteX, 3, 7),
-
-        // ROR - Rotate Right
-        OperationCode::new(0x6A, "ROR", AddressingMode::Accumulator, 1, 2),
-        OperationCode::new(0x66, "ROR", AddressingMode::ZeroPage, 2, 5),
-        OperationCode::new(0x76, "ROR", AddressingMode::ZeroPageX, 2, 6),
-        OperationCode::new(0x6E, "ROR", AddressingMode::Absolute, 3, 6),
-        OperationCode::new(0x7E, "ROR", AddressingMode::AbsoluteX, 3, 7),
-
-        // RTI - Return from Interrupt
-        OperationCode::new(0x40, "RTI", AddressingMode::Implicit, 1, 6),
-
-        // RTS - Return from Subroutine
-        OperationCode::new(0x60, "RTS", AddressingMode::Implicit, 1, 6),
-
-        // SBC - Subtract with Carry
-        OperationCode::new(0xE9, "SBC", AddressingMode::Immediate, 2, 2),
-        OperationCode::new(0xE5, "SBC", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0xF5, "SBC", AddressingMode::ZeroPageX, 2, 4),
-        OperationCode::new(0xED, "SBC", AddressingMode::Absolute, 3, 4),
-        OperationCode::new(0xFD, "SBC", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
-        OperationCode::new(0xF9, "SBC", AddressingMode::AbsoluteY, 3, 4 /* (+1 if page crossed) */),
-        OperationCode::new(0xE1, "SBC", AddressingMode::IndirectX, 2, 6),
-        OperationCode::new(0xF1, "SBC", AddressingMode::IndirectY, 2, 5 /* (+1 if page crossed) */),
-
-        // SEC - Set Carry Flag
-        OperationCode::new(0x38, "SEC", AddressingMode::Implicit, 1, 2),
-
-        // SED - Set Decimal Flag
-        OperationCode::new(0xF8, "SED", AddressingMode::Implicit, 1, 2),
-
-        // SEI - Set Interrupt Disable
-        OperationCode::new(0x78, "SEI", AddressingMode::Implicit, 1, 2),
-
-        // STA - Store Accumulator
-        OperationCode::new(0x85, "STA", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0x95, "STA", AddressingMode::ZeroPageX, 2, 4),
-        OperationCode::new(0x8D, "STA", AddressingMode::Absolute, 3, 4),
-        OperationCode::new(0x9D, "STA", AddressingMode::AbsoluteX, 3, 5),
-        OperationCode::new(0x99, "STA", AddressingMode::AbsoluteY, 3, 5),
-        OperationCode::new(0x81, "STA", AddressingMode::IndirectX, 2, 6),
-        OperationCode::new(0x91, "STA", AddressingMode::IndirectY, 2, 6),
-
-        // STX - Store X Register
-        OperationCode::new(0x86, "STX", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0x96, "STX", AddressingMode::ZeroPageY, 2, 4),
-        OperationCode::new(0x8E, "STX", AddressingMode::Absolute, 3, 4),
-
-        // STY - Store Y Register
-        OperationCode::new(0x84, "STY", AddressingMode::ZeroPage, 2, 3),
-        OperationCode::new(0x94, "STY", AddressingMode::ZeroPageX, 2, 4),
-        OperationCode::new(0x8C, "STY", AddressingMode::Absolute, 3, 4),
-
-        // TAX - Transfer Accumulator to X
-        OperationCode::new(0xAA, "TAX", AddressingMode::Implicit, 1, 2),
-
-        // TAY - Transfer Accumulator to Y
-        OperationCode::new(0xA8, "TAY", AddressingMode::Implicit, 1, 2),
-
-        // TSX - Transfer Stack Pointer to X
-        OperationCode::new(0xBA, "TSX", AddressingMode::Implicit, 1, 2),
-
-        // TXA - Transfer X to Accumulator
-        OperationCode::new(0x8A, "TXA", AddressingMode::Implicit, 1, 2),
-
-        // TXS - Transfer X to Stack Pointer
-        OperationCode::new(0x9A, "TXS", AddressingMode::Implicit, 1, 2),
-
-        // TYA - Transfer Y to Accumulator
-        OperationCode::new(0x98, "TYA", AddressingMode::Implicit, 1, 2)
-    ];
-
-    pub static ref CPU_OPERATION_CODES_MAP: HashMap<u8, &'static OperationCode> = {
-       let mut map = HashMap::new();
-       for opcode in CPU_OPERATION_CODES_VEC.iter() {
-           map.insert(opcode.code, opcode);
-       }
-       map
-   };
-}
\ No newline at end of file
+// 65C02 changes to the NMOS table, the documented operation codes it does not list are kept as they are
+// http://www.6502.org/tutorials/65c02opcodes.html
+pub static CMOS_OPERATION_CODES_LIST: [OperationCode; 110] = [
+    // ADC, AND, CMP, EOR, LDA, ORA, SBC, STA - new (zp) addressing mode
+    OperationCode::new(0x72, "ADC", AddressingMode::ZeroPageIndirect, 2, 5),
+    OperationCode::new(0x32, "AND", AddressingMode::ZeroPageIndirect, 2, 5),
+    OperationCode::new(0xD2, "CMP", AddressingMode::ZeroPageIndirect, 2, 5),
+    OperationCode::new(0x52, "EOR", AddressingMode::ZeroPageIndirect, 2, 5),
+    OperationCode::new(0xB2, "LDA", AddressingMode::ZeroPageIndirect, 2, 5),
+    OperationCode::new(0x12, "ORA", AddressingMode::ZeroPageIndirect, 2, 5),
+    OperationCode::new(0xF2, "SBC", AddressingMode::ZeroPageIndirect, 2, 5),
+    OperationCode::new(0x92, "STA", AddressingMode::ZeroPageIndirect, 2, 5),
+
+    // ASL, LSR, ROL, ROR - absolute,X only takes the extra cycle when a page is crossed
+    OperationCode::new(0x1E, "ASL", AddressingMode::AbsoluteX, 3, 6 /* (+1 if page crossed) */),
+    OperationCode::new(0x5E, "LSR", AddressingMode::AbsoluteX, 3, 6 /* (+1 if page crossed) */),
+    OperationCode::new(0x3E, "ROL", AddressingMode::AbsoluteX, 3, 6 /* (+1 if page crossed) */),
+    OperationCode::new(0x7E, "ROR", AddressingMode::AbsoluteX, 3, 6 /* (+1 if page crossed) */),
+
+    // BBR - Branch on Bit Reset
+    OperationCode::new(0x0F, "BBR0", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+    OperationCode::new(0x1F, "BBR1", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+    OperationCode::new(0x2F, "BBR2", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+    OperationCode::new(0x3F, "BBR3", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+    OperationCode::new(0x4F, "BBR4", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+    OperationCode::new(0x5F, "BBR5", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+    OperationCode::new(0x6F, "BBR6", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+    OperationCode::new(0x7F, "BBR7", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+
+    // BBS - Branch on Bit Set
+    OperationCode::new(0x8F, "BBS0", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+    OperationCode::new(0x9F, "BBS1", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+    OperationCode::new(0xAF, "BBS2", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+    OperationCode::new(0xBF, "BBS3", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+    OperationCode::new(0xCF, "BBS4", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+    OperationCode::new(0xDF, "BBS5", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+    OperationCode::new(0xEF, "BBS6", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+    OperationCode::new(0xFF, "BBS7", AddressingMode::ZeroPageRelative, 3, 5 /* (+1 if branch succeeds+2 if to a new page) */),
+
+    // BIT - new immediate and indexed modes
+    OperationCode::new(0x89, "BIT", AddressingMode::Immediate, 2, 2),
+    OperationCode::new(0x34, "BIT", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::new(0x3C, "BIT", AddressingMode::AbsoluteX, 3, 4 /* (+1 if page crossed) */),
+
+    // BRA - Branch Always
+    OperationCode::new(0x80, "BRA", AddressingMode::Relative, 2, 3 /* (+1 if to a new page) */),
+
+    // DEC, INC - Accumulator
+    OperationCode::new(0x3A, "DEC", AddressingMode::Accumulator, 1, 2),
+    OperationCode::new(0x1A, "INC", AddressingMode::Accumulator, 1, 2),
+
+    // JMP - Indirect without the page wrap bug, and indexed indirect
+    OperationCode::new(0x6C, "JMP", AddressingMode::Indirect, 3, 6),
+    OperationCode::new(0x7C, "JMP", AddressingMode::AbsoluteIndirectX, 3, 6),
+
+    // PHX, PHY, PLX, PLY - Push and Pull X and Y
+    OperationCode::new(0xDA, "PHX", AddressingMode::Implicit, 1, 3),
+    OperationCode::new(0x5A, "PHY", AddressingMode::Implicit, 1, 3),
+    OperationCode::new(0xFA, "PLX", AddressingMode::Implicit, 1, 4),
+    OperationCode::new(0x7A, "PLY", AddressingMode::Implicit, 1, 4),
+
+    // RMB - Reset Memory Bit
+    OperationCode::new(0x07, "RMB0", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0x17, "RMB1", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0x27, "RMB2", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0x37, "RMB3", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0x47, "RMB4", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0x57, "RMB5", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0x67, "RMB6", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0x77, "RMB7", AddressingMode::ZeroPage, 2, 5),
+
+    // SMB - Set Memory Bit
+    OperationCode::new(0x87, "SMB0", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0x97, "SMB1", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0xA7, "SMB2", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0xB7, "SMB3", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0xC7, "SMB4", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0xD7, "SMB5", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0xE7, "SMB6", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0xF7, "SMB7", AddressingMode::ZeroPage, 2, 5),
+
+    // STP - Stop until reset
+    OperationCode::new(0xDB, "STP", AddressingMode::Implicit, 1, 3),
+
+    // STZ - Store Zero
+    OperationCode::new(0x64, "STZ", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::new(0x74, "STZ", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::new(0x9C, "STZ", AddressingMode::Absolute, 3, 4),
+    OperationCode::new(0x9E, "STZ", AddressingMode::AbsoluteX, 3, 5),
+
+    // TRB - Test and Reset Bits
+    OperationCode::new(0x14, "TRB", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0x1C, "TRB", AddressingMode::Absolute, 3, 6),
+
+    // TSB - Test and Set Bits
+    OperationCode::new(0x04, "TSB", AddressingMode::ZeroPage, 2, 5),
+    OperationCode::new(0x0C, "TSB", AddressingMode::Absolute, 3, 6),
+
+    // WAI - Wait for Interrupt
+    OperationCode::new(0xCB, "WAI", AddressingMode::Implicit, 1, 3),
+
+    // NOP - every other operation code, reads its operand
+    OperationCode::unofficial(0x02, "NOP", AddressingMode::Immediate, 2, 2),
+    OperationCode::unofficial(0x22, "NOP", AddressingMode::Immediate, 2, 2),
+    OperationCode::unofficial(0x42, "NOP", AddressingMode::Immediate, 2, 2),
+    OperationCode::unofficial(0x62, "NOP", AddressingMode::Immediate, 2, 2),
+    OperationCode::unofficial(0x82, "NOP", AddressingMode::Immediate, 2, 2),
+    OperationCode::unofficial(0xC2, "NOP", AddressingMode::Immediate, 2, 2),
+    OperationCode::unofficial(0xE2, "NOP", AddressingMode::Immediate, 2, 2),
+    OperationCode::unofficial(0x44, "NOP", AddressingMode::ZeroPage, 2, 3),
+    OperationCode::unofficial(0x54, "NOP", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::unofficial(0xD4, "NOP", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::unofficial(0xF4, "NOP", AddressingMode::ZeroPageX, 2, 4),
+    OperationCode::unofficial(0xDC, "NOP", AddressingMode::Absolute, 3, 4),
+    OperationCode::unofficial(0xFC, "NOP", AddressingMode::Absolute, 3, 4),
+    OperationCode::unofficial(0x5C, "NOP", AddressingMode::Absolute, 3, 8),
+    // $x3 and $xB take a single cycle, there is no dummy read
+    OperationCode::unofficial(0x03, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x13, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x23, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x33, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x43, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x53, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x63, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x73, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x83, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x93, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0xA3, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0xB3, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0xC3, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0xD3, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0xE3, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0xF3, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x0B, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x1B, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x2B, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x3B, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x4B, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x5B, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x6B, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x7B, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x8B, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0x9B, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0xAB, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0xBB, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0xEB, "NOP", AddressingMode::Implicit, 1, 1),
+    OperationCode::unofficial(0xFB, "NOP", AddressingMode::Implicit, 1, 1),
+];
+
+// Indexed by operation code, built at compile time
+pub static CMOS_OPERATION_CODES: [OperationCode; 256] = cmos_decode_table(&CPU_OPERATION_CODES, &CMOS_OPERATION_CODES_LIST);
+
+// Starts from the documented NMOS operation codes, every undocumented one has to be replaced
+const fn cmos_decode_table(nmos: &[OperationCode; 256], changes: &[OperationCode]) -> [OperationCode; 256] {
+    let mut table = *nmos;
+    let mut changed = [false; 256];
+
+    let mut i = 0;
+    while i < changes.len() {
+        let code = changes[i].code as usize;
+        if changed[code] {
+            panic!("Operation code defined twice");
+        }
+        changed[code] = true;
+        table[code] = changes[i];
+        i += 1;
+    }
+
+    let mut code = 0;
+    while code < 256 {
+        if !nmos[code].official && !changed[code] {
+            panic!("Undocumented NMOS operation code left in the 65C02 table");
+        }
+        code += 1;
+    }
+
+    table
+}
@@ -1,11 +1,13 @@
+use crate::emulator::bus::cpu_bus::CpuBus;
 use crate::emulator::cpu::CPU;
 
 pub trait StackOperations {
     fn push_stack(&mut self, value: u8);
     fn push_stack_u16(&mut self, value: u16);
     fn pop_stack(&mut self) -> u8;
+    fn dummy_stack_read(&mut self);
 }
-impl<'a> StackOperations for CPU<'a> {
+impl<B: CpuBus> StackOperations for CPU<B> {
     fn push_stack(&mut self, value: u8) {
         self.mem_write(0x0100 | self.stack_pointer as u16, value);
         self.stack_pointer = self.stack_pointer.wrapping_sub(1);
@@ -20,4 +22,9 @@ impl<'a> StackOperations for CPU<'a> {
         self.stack_pointer = self.stack_pointer.wrapping_add(1);
         self.mem_read(0x0100 + self.stack_pointer as u16)
     }
+
+    // Pulls (and JSR) spend a cycle reading the current stack slot before the pointer moves
+    fn dummy_stack_read(&mut self) {
+        self.mem_read(0x0100 | self.stack_pointer as u16);
+    }
 }
\ No newline at end of file
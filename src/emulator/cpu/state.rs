@@ -0,0 +1,121 @@
+use crate::emulator::bus::cpu_bus::CpuBus;
+use crate::emulator::cpu::flags::CpuFlags;
+use crate::emulator::cpu::interrupts::InterruptPoll;
+use crate::emulator::cpu::{InterruptType, CPU};
+
+// Registers and interrupt state of the CPU, for frontends, debuggers and test harnesses.
+// The bus is not part of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpuState {
+    pub register_a: u8,
+    pub register_x: u8,
+    pub register_y: u8,
+    pub stack_pointer: u8,
+    pub program_counter: u16,
+    // P as held by the CPU. B and bit 5 keep the value they were given (set at power-on), PLP and RTI
+    // leave them alone and pushes replace them, so they never affect execution.
+    pub flags: CpuFlags,
+    pub cycles: usize,
+    // NMI edge detected and not serviced yet
+    pub nmi_pending: bool,
+    // interrupt sequence that runs instead of the next instruction
+    pub pending_interrupt: Option<InterruptType>,
+    pub jammed: bool,
+    pub waiting: bool,
+}
+
+impl<B: CpuBus> CPU<B> {
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            register_a: self.register_a,
+            register_x: self.register_x,
+            register_y: self.register_y,
+            stack_pointer: self.stack_pointer,
+            program_counter: self.program_counter,
+            flags: self.flags,
+            cycles: self.cycles,
+            nmi_pending: self.nmi_pending,
+            pending_interrupt: self.pending_interrupt().map(|interrupt| interrupt.interrupt_type),
+            jammed: self.jammed,
+            waiting: self.waiting,
+        }
+    }
+
+    // Loads every register, the bus is left alone
+    pub fn set_state(&mut self, state: CpuState) {
+        self.register_a = state.register_a;
+        self.register_x = state.register_x;
+        self.register_y = state.register_y;
+        self.stack_pointer = state.stack_pointer;
+        self.program_counter = state.program_counter;
+        self.flags = state.flags;
+        self.cycles = state.cycles;
+        self.nmi_pending = state.nmi_pending;
+        self.jammed = state.jammed;
+        self.waiting = state.waiting;
+
+        self.previous_poll = match state.pending_interrupt {
+            Some(InterruptType::Nmi) => InterruptPoll { nmi: true, ..InterruptPoll::default() },
+            Some(InterruptType::Irq) => InterruptPoll { nmi: false, irq_line: true, irq_enabled: true },
+            // BRK is an instruction, it is never pending
+            Some(InterruptType::Brk) | None => InterruptPoll::default(),
+        };
+        self.poll = self.previous_poll;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::bus::mock_bus::MockBus;
+    use crate::emulator::cpu::StepResult;
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut bus = MockBus::new();
+        bus.load_program(&[0xA9, 0x42, 0xAA], 0x8000); // LDA #$42, TAX
+        let mut cpu = CPU::new(bus);
+        cpu.program_counter = 0x8000;
+        cpu.step();
+        cpu.step();
+
+        let state = cpu.state();
+        assert_eq!(state.register_a, 0x42);
+        assert_eq!(state.register_x, 0x42);
+        assert_eq!(state.program_counter, 0x8003);
+        assert_eq!(state.cycles, 4);
+        assert_eq!(state.pending_interrupt, None);
+
+        let mut other = CPU::new(MockBus::new());
+        other.set_state(state);
+        assert_eq!(other.state(), state);
+    }
+
+    #[test]
+    fn test_set_state_with_pending_interrupt() {
+        let mut bus = MockBus::new();
+        bus.memory[0xFFFA] = 0x00;
+        bus.memory[0xFFFB] = 0x90;
+        let mut cpu = CPU::new(bus);
+
+        let state = CpuState {
+            register_a: 0,
+            register_x: 0,
+            register_y: 0,
+            stack_pointer: 0xFD,
+            program_counter: 0x8000,
+            flags: CpuFlags::UNUSED | CpuFlags::CARRY,
+            cycles: 0,
+            nmi_pending: true,
+            pending_interrupt: Some(InterruptType::Nmi),
+            jammed: false,
+            waiting: false,
+        };
+        cpu.set_state(state);
+
+        assert_eq!(cpu.step(), StepResult::InterruptServiced { interrupt: InterruptType::Nmi, cycles: 7 });
+        assert_eq!(cpu.state().program_counter, 0x9000);
+        assert!(!cpu.state().nmi_pending);
+        assert!(cpu.state().flags.contains(CpuFlags::INTERRUPT_DISABLE | CpuFlags::CARRY));
+    }
+}
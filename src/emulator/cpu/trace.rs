@@ -0,0 +1,276 @@
+use std::io;
+use std::io::Write;
+
+use crate::emulator::bus::cpu_bus::CpuBus;
+use crate::emulator::cpu::addressing::AddressingMode;
+use crate::emulator::cpu::flags::CpuFlags;
+use crate::emulator::cpu::{disasm, OperationCode, StepResult, CPU};
+
+/* nestest.log (Nintendulator) format, one line per instruction before it runs
+C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+C5F7  86 00     STX $00 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 36 CYC:12
+Unofficial operation codes are marked with '*'
+*/
+pub struct Tracer<W: Write> {
+    out: W,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(out: W) -> Self {
+        Tracer { out }
+    }
+
+    // Logs the instruction at PC and executes it. Interrupt sequences are not logged, like in nestest.log
+    pub fn step<B: CpuBus>(&mut self, cpu: &mut CPU<B>) -> io::Result<StepResult> {
+        if !cpu.is_jammed() && !cpu.is_waiting() && cpu.pending_interrupt().is_none() {
+            writeln!(self.out, "{}", cpu.trace())?;
+        }
+
+        Ok(cpu.step())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<B: CpuBus> CPU<B> {
+    // Trace line for the instruction at PC, before it runs
+    pub fn trace(&self) -> String {
+        let pc = self.program_counter;
+        let code = self.peek(pc);
+
+        let instruction = &self.variant.operation_codes()[code as usize];
+
+        let bytes = disasm::peek_bytes(&self.bus, pc, instruction.bytes as usize);
+        let hex = bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(" ");
+
+        let marker = if instruction.official { ' ' } else { '*' };
+        let operand = self.trace_operand(instruction, &bytes);
+        let disassembly = if operand.is_empty() {
+            nestest_mnemonic(instruction).to_string()
+        } else {
+            format!("{} {}", nestest_mnemonic(instruction), operand)
+        };
+
+        // B is not a real flag, bit 5 always reads as set
+        let status = (self.flags.bits() & !CpuFlags::BREAK.bits()) | CpuFlags::UNUSED.bits();
+        let (scanline, dot) = self.bus.ppu_position();
+
+        format!(
+            "{:04X}  {:<8} {}{:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+            pc, hex, marker, disassembly,
+            self.register_a, self.register_x, self.register_y, status, self.stack_pointer,
+            scanline, dot, self.cycles
+        )
+    }
+
+    // Operand with the effective address and the value there, as they are before the instruction runs
+    fn trace_operand(&self, instruction: &OperationCode, bytes: &[u8]) -> String {
+        let pc = self.program_counter;
+        let byte = bytes.get(1).copied().unwrap_or(0);
+        let word = u16::from_le_bytes([byte, bytes.get(2).copied().unwrap_or(0)]);
+
+        match instruction.addressing_mode {
+            AddressingMode::Implicit => String::new(),
+            AddressingMode::Accumulator => "A".to_string(),
+            AddressingMode::Immediate => format!("#${:02X}", byte),
+            AddressingMode::ZeroPage => format!("${:02X} = {:02X}", byte, self.peek(byte as u16)),
+            AddressingMode::ZeroPageX => {
+                let address = byte.wrapping_add(self.register_x);
+                format!("${:02X},X @ {:02X} = {:02X}", byte, address, self.peek(address as u16))
+            }
+            AddressingMode::ZeroPageY => {
+                let address = byte.wrapping_add(self.register_y);
+                format!("${:02X},Y @ {:02X} = {:02X}", byte, address, self.peek(address as u16))
+            }
+            AddressingMode::Relative => {
+                let target = pc.wrapping_add(2).wrapping_add(byte as i8 as u16);
+                format!("${:04X}", target)
+            }
+            AddressingMode::Absolute => {
+                if matches!(instruction.mnemonic, "JMP" | "JSR") {
+                    format!("${:04X}", word)
+                } else {
+                    format!("${:04X} = {:02X}", word, self.peek(word))
+                }
+            }
+            AddressingMode::AbsoluteX => {
+                let address = word.wrapping_add(self.register_x as u16);
+                format!("${:04X},X @ {:04X} = {:02X}", word, address, self.peek(address))
+            }
+            AddressingMode::AbsoluteY => {
+                let address = word.wrapping_add(self.register_y as u16);
+                format!("${:04X},Y @ {:04X} = {:02X}", word, address, self.peek(address))
+            }
+            AddressingMode::Indirect => {
+                // same page wrap as jmp, only the 65C02 reads the high byte from the next page
+                let high = if word & 0x00FF == 0x00FF && !self.variant.is_cmos() {
+                    word & 0xFF00
+                } else {
+                    word.wrapping_add(1)
+                };
+                let address = u16::from_le_bytes([self.peek(word), self.peek(high)]);
+                format!("(${:04X}) = {:04X}", word, address)
+            }
+            AddressingMode::IndirectX => {
+                let ptr = byte.wrapping_add(self.register_x);
+                let address = self.peek_zero_page_u16(ptr);
+                format!("(${:02X},X) @ {:02X} = {:04X} = {:02X}", byte, ptr, address, self.peek(address))
+            }
+            AddressingMode::IndirectY => {
+                let base = self.peek_zero_page_u16(byte);
+                let address = base.wrapping_add(self.register_y as u16);
+                format!("(${:02X}),Y = {:04X} @ {:04X} = {:02X}", byte, base, address, self.peek(address))
+            }
+            AddressingMode::ZeroPageIndirect => {
+                let address = self.peek_zero_page_u16(byte);
+                format!("(${:02X}) = {:04X} = {:02X}", byte, address, self.peek(address))
+            }
+            AddressingMode::AbsoluteIndirectX => {
+                let ptr = word.wrapping_add(self.register_x as u16);
+                let address = u16::from_le_bytes([self.peek(ptr), self.peek(ptr.wrapping_add(1))]);
+                format!("(${:04X},X) @ {:04X} = {:04X}", word, ptr, address)
+            }
+            AddressingMode::ZeroPageRelative => {
+                let target = pc.wrapping_add(3).wrapping_add(bytes.get(2).copied().unwrap_or(0) as i8 as u16);
+                format!("${:02X} = {:02X},${:04X}", byte, self.peek(byte as u16), target)
+            }
+        }
+    }
+
+    // Memory is peeked, tracing does not disturb I/O registers
+    fn peek(&self, address: u16) -> u8 {
+        self.bus.peek(address)
+    }
+
+    fn peek_zero_page_u16(&self, ptr: u8) -> u16 {
+        u16::from_le_bytes([self.peek(ptr as u16), self.peek(ptr.wrapping_add(1) as u16)])
+    }
+}
+
+// nestest.log spells ISC as ISB
+fn nestest_mnemonic(instruction: &OperationCode) -> &'static str {
+    match instruction.mnemonic {
+        "ISC" => "ISB",
+        mnemonic => mnemonic,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::bus::mock_bus::MockBus;
+    use crate::emulator::cpu::CpuVariant;
+
+    fn prepare_nestest_cpu() -> CPU<MockBus> {
+        let mut bus = MockBus::new();
+        bus.load_program(&[0x4C, 0xF5, 0xC5], 0xC000); // JMP $C5F5
+        bus.load_program(&[0xA2, 0x00, 0x86, 0x00, 0x20, 0x2D, 0xC7], 0xC5F5); // LDX #$00, STX $00, JSR $C72D
+        bus.load_program(&[0xEA], 0xC72D); // NOP
+        bus.memory[0xFFFC] = 0x00;
+        bus.memory[0xFFFD] = 0xC0;
+
+        let mut cpu = CPU::new(bus);
+        cpu.power_on();
+        cpu
+    }
+
+    #[test]
+    fn test_nestest_log_format() {
+        let mut cpu = prepare_nestest_cpu();
+        let mut tracer = Tracer::new(Vec::new());
+
+        for _ in 0..5 {
+            tracer.step(&mut cpu).unwrap();
+        }
+
+        let log = String::from_utf8(tracer.into_inner()).unwrap();
+        let expected = "\
+C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+C5F5  A2 00     LDX #$00                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 30 CYC:10
+C5F7  86 00     STX $00 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 36 CYC:12
+C5F9  20 2D C7  JSR $C72D                       A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 45 CYC:15
+C72D  EA        NOP                             A:00 X:00 Y:00 P:26 SP:FB PPU:  0, 63 CYC:21
+";
+        assert_eq!(log, expected);
+    }
+
+    #[test]
+    fn test_trace_does_not_access_the_bus() {
+        let mut bus = MockBus::new();
+        bus.load_program(&[0xB1, 0x10], 0x0400); // LDA ($10),Y
+        bus.record_accesses = true;
+
+        let mut cpu = CPU::new(bus);
+        cpu.program_counter = 0x0400;
+        cpu.trace();
+
+        assert!(cpu.bus().accesses.is_empty());
+    }
+
+    fn disassembly(program: &[u8], setup: impl FnOnce(&mut CPU<MockBus>)) -> String {
+        let mut bus = MockBus::new();
+        bus.load_program(program, 0x0400);
+        let mut cpu = CPU::new(bus);
+        cpu.program_counter = 0x0400;
+        setup(&mut cpu);
+
+        cpu.trace()[6..48].trim_end().to_string()
+    }
+
+    #[test]
+    fn test_trace_addressing_modes() {
+        assert_eq!(disassembly(&[0x4A], |_| {}), "4A        LSR A");
+        assert_eq!(disassembly(&[0xB5, 0x33], |cpu| cpu.register_x = 0x02), "B5 33     LDA $33,X @ 35 = 00");
+        assert_eq!(disassembly(&[0xBD, 0x00, 0x03], |cpu| cpu.register_x = 0x89), "BD 00 03  LDA $0300,X @ 0389 = 00");
+        assert_eq!(disassembly(&[0xD0, 0xFC], |_| {}), "D0 FC     BNE $03FE");
+        assert_eq!(
+            disassembly(&[0x6C, 0x00, 0x02], |cpu| {
+                cpu.mem_write(0x0200, 0x7E);
+                cpu.mem_write(0x0201, 0xDB);
+            }),
+            "6C 00 02  JMP ($0200) = DB7E"
+        );
+        assert_eq!(
+            disassembly(&[0x6C, 0xFF, 0x02], |cpu| {
+                cpu.mem_write(0x02FF, 0x00);
+                cpu.mem_write(0x0200, 0xA9);
+                cpu.mem_write(0x0300, 0x04);
+            }),
+            "6C FF 02  JMP ($02FF) = A900"
+        );
+        assert_eq!(
+            disassembly(&[0x6C, 0xFF, 0x02], |cpu| {
+                cpu.variant = CpuVariant::Cmos65C02;
+                cpu.mem_write(0x02FF, 0x00);
+                cpu.mem_write(0x0200, 0xA9);
+                cpu.mem_write(0x0300, 0x04);
+            }),
+            "6C FF 02  JMP ($02FF) = 0400"
+        );
+        assert_eq!(
+            disassembly(&[0xA1, 0x80], |cpu| {
+                cpu.mem_write(0x0080, 0x00);
+                cpu.mem_write(0x0081, 0x02);
+                cpu.mem_write(0x0200, 0x5A);
+            }),
+            "A1 80     LDA ($80,X) @ 80 = 0200 = 5A"
+        );
+        assert_eq!(
+            disassembly(&[0xB1, 0x89], |cpu| {
+                cpu.mem_write(0x0089, 0x00);
+                cpu.mem_write(0x008A, 0x03);
+                cpu.register_y = 0x01;
+            }),
+            "B1 89     LDA ($89),Y = 0300 @ 0301 = 00"
+        );
+    }
+
+    #[test]
+    fn test_trace_unofficial_operation_codes() {
+        assert_eq!(disassembly(&[0x04, 0xA9], |_| {}), "04 A9    *NOP $A9 = 00");
+        assert_eq!(disassembly(&[0xE7, 0x10], |_| {}), "E7 10    *ISB $10 = 00");
+        assert_eq!(disassembly(&[0xEB, 0x40], |_| {}), "EB 40    *SBC #$40");
+    }
+}
@@ -0,0 +1,255 @@
+use crate::emulator::bus::cpu_bus::CpuBus;
+use crate::emulator::cpu::addressing::{page_crossed, AddressingMode};
+use crate::emulator::cpu::{AddressingModeOperations, CPU};
+use crate::emulator::cpu::flags::{CpuFlags, FlagOperations};
+
+// https://www.nesdev.org/wiki/CPU_unofficial_opcodes
+// https://www.nesdev.org/6502_cpu.txt
+
+// Value OR-ed into A by the unstable ANE and LXA, it differs between chips
+const UNSTABLE_MAGIC: u8 = 0xEE;
+
+pub trait UnofficialInstructions {
+    fn alr(&mut self, mode: &AddressingMode);
+    fn anc(&mut self, mode: &AddressingMode);
+    fn ane(&mut self, mode: &AddressingMode);
+    fn arr(&mut self, mode: &AddressingMode);
+    fn dcp(&mut self, mode: &AddressingMode);
+    fn isc(&mut self, mode: &AddressingMode);
+    fn jam(&mut self);
+    fn las(&mut self, mode: &AddressingMode);
+    fn lax(&mut self, mode: &AddressingMode);
+    fn lxa(&mut self, mode: &AddressingMode);
+    fn nop_read(&mut self, mode: &AddressingMode);
+    fn rla(&mut self, mode: &AddressingMode);
+    fn rra(&mut self, mode: &AddressingMode);
+    fn sax(&mut self, mode: &AddressingMode);
+    fn sbx(&mut self, mode: &AddressingMode);
+    fn sha(&mut self, mode: &AddressingMode);
+    fn shx(&mut self, mode: &AddressingMode);
+    fn shy(&mut self, mode: &AddressingMode);
+    fn slo(&mut self, mode: &AddressingMode);
+    fn sre(&mut self, mode: &AddressingMode);
+    fn tas(&mut self, mode: &AddressingMode);
+}
+
+impl<B: CpuBus> UnofficialInstructions for CPU<B> {
+    fn alr(&mut self, mode: &AddressingMode) {
+        let value = self.register_a & self.get_operand(mode);
+
+        self.set_flag(CpuFlags::CARRY, value & 0x01 != 0);
+        self.register_a = value >> 1;
+
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    fn anc(&mut self, mode: &AddressingMode) {
+        self.register_a &= self.get_operand(mode);
+
+        self.update_zero_and_negative_flags(self.register_a);
+        self.set_flag(CpuFlags::CARRY, self.register_a & 0x80 != 0);
+    }
+
+    fn ane(&mut self, mode: &AddressingMode) {
+        let value = self.get_operand(mode);
+
+        self.register_a = (self.register_a | UNSTABLE_MAGIC) & self.register_x & value;
+
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    fn arr(&mut self, mode: &AddressingMode) {
+        let value = self.register_a & self.get_operand(mode);
+        let carry = (self.contains_flag(CpuFlags::CARRY) as u8) << 7;
+
+        self.register_a = (value >> 1) | carry;
+
+        if self.is_decimal_mode() {
+            self.arr_decimal(value, carry);
+            return;
+        }
+
+        // C is bit 6 of the result, V is bit 6 xor bit 5
+        self.update_zero_and_negative_flags(self.register_a);
+        self.set_flag(CpuFlags::CARRY, self.register_a & 0x40 != 0);
+        self.set_flag(CpuFlags::OVERFLOW, ((self.register_a >> 6) ^ (self.register_a >> 5)) & 0x01 != 0);
+    }
+
+    fn dcp(&mut self, mode: &AddressingMode) {
+        let address = self.get_address(mode);
+        let value = self.mem_read(address);
+        let result = value.wrapping_sub(1);
+
+        self.mem_write_modified(address, value, result);
+        self.compare(self.register_a, result);
+    }
+
+    fn isc(&mut self, mode: &AddressingMode) {
+        let address = self.get_address(mode);
+        let value = self.mem_read(address);
+        let result = value.wrapping_add(1);
+
+        self.mem_write_modified(address, value, result);
+        self.subtract_with_carry(result);
+    }
+
+    fn jam(&mut self) {
+        // the CPU stops fetching, program counter stays on the operation code
+        self.program_counter = self.program_counter.wrapping_sub(1);
+        self.jammed = true;
+    }
+
+    fn las(&mut self, mode: &AddressingMode) {
+        let value = self.get_operand(mode) & self.stack_pointer;
+
+        self.register_a = value;
+        self.register_x = value;
+        self.stack_pointer = value;
+
+        self.update_zero_and_negative_flags(value);
+    }
+
+    fn lax(&mut self, mode: &AddressingMode) {
+        let value = self.get_operand(mode);
+
+        self.register_a = value;
+        self.register_x = value;
+
+        self.update_zero_and_negative_flags(value);
+    }
+
+    fn lxa(&mut self, mode: &AddressingMode) {
+        let value = (self.register_a | UNSTABLE_MAGIC) & self.get_operand(mode);
+
+        self.register_a = value;
+        self.register_x = value;
+
+        self.update_zero_and_negative_flags(value);
+    }
+
+    fn nop_read(&mut self, mode: &AddressingMode) {
+        // operand is read and thrown away
+        self.get_operand(mode);
+    }
+
+    fn rla(&mut self, mode: &AddressingMode) {
+        let address = self.get_address(mode);
+        let value = self.mem_read(address);
+        let carry = self.contains_flag(CpuFlags::CARRY) as u8;
+
+        let result = (value << 1) | carry;
+        self.mem_write_modified(address, value, result);
+        self.set_flag(CpuFlags::CARRY, value & 0x80 != 0);
+
+        self.register_a &= result;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    fn rra(&mut self, mode: &AddressingMode) {
+        let address = self.get_address(mode);
+        let value = self.mem_read(address);
+        let carry = (self.contains_flag(CpuFlags::CARRY) as u8) << 7;
+
+        let result = (value >> 1) | carry;
+        self.mem_write_modified(address, value, result);
+        self.set_flag(CpuFlags::CARRY, value & 0x01 != 0);
+
+        self.add_with_carry(result);
+    }
+
+    fn sax(&mut self, mode: &AddressingMode) {
+        let address = self.get_address(mode);
+        self.mem_write(address, self.register_a & self.register_x);
+    }
+
+    fn sbx(&mut self, mode: &AddressingMode) {
+        let value = self.get_operand(mode);
+        let register = self.register_a & self.register_x;
+
+        self.compare(register, value);
+        self.register_x = register.wrapping_sub(value);
+    }
+
+    fn sha(&mut self, mode: &AddressingMode) {
+        let value = self.register_a & self.register_x;
+        self.unstable_store(mode, value);
+    }
+
+    fn shx(&mut self, mode: &AddressingMode) {
+        self.unstable_store(mode, self.register_x);
+    }
+
+    fn shy(&mut self, mode: &AddressingMode) {
+        self.unstable_store(mode, self.register_y);
+    }
+
+    fn slo(&mut self, mode: &AddressingMode) {
+        let address = self.get_address(mode);
+        let value = self.mem_read(address);
+
+        let result = value << 1;
+        self.mem_write_modified(address, value, result);
+        self.set_flag(CpuFlags::CARRY, value & 0x80 != 0);
+
+        self.register_a |= result;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    fn sre(&mut self, mode: &AddressingMode) {
+        let address = self.get_address(mode);
+        let value = self.mem_read(address);
+
+        let result = value >> 1;
+        self.mem_write_modified(address, value, result);
+        self.set_flag(CpuFlags::CARRY, value & 0x01 != 0);
+
+        self.register_a ^= result;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    fn tas(&mut self, mode: &AddressingMode) {
+        self.stack_pointer = self.register_a & self.register_x;
+        self.unstable_store(mode, self.stack_pointer);
+    }
+}
+
+// helper
+impl<B: CpuBus> CPU<B> {
+    // NMOS 6502 ARR in decimal mode: N and Z from the rotated value, V from bit 6 changing,
+    // then each digit of the rotated value is adjusted like in ADC, C is set by the high digit
+    fn arr_decimal(&mut self, value: u8, carry: u8) {
+        self.set_flag(CpuFlags::NEGATIVE, carry != 0);
+        self.set_flag(CpuFlags::ZERO, self.register_a == 0);
+        self.set_flag(CpuFlags::OVERFLOW, (value ^ self.register_a) & 0x40 != 0);
+
+        if (value & 0x0F) + (value & 0x01) > 0x05 {
+            self.register_a = (self.register_a & 0xF0) | (self.register_a.wrapping_add(0x06) & 0x0F);
+        }
+
+        let high_adjust = (value as u16 & 0xF0) + (value as u16 & 0x10) > 0x50;
+        if high_adjust {
+            self.register_a = self.register_a.wrapping_add(0x60);
+        }
+        self.set_flag(CpuFlags::CARRY, high_adjust);
+    }
+
+    // SHA, SHX, SHY and TAS store value & (high byte of the base address + 1),
+    // when indexing crosses a page that value also replaces the high byte of the address
+    fn unstable_store(&mut self, mode: &AddressingMode, value: u8) {
+        let (base, index) = match mode {
+            AddressingMode::AbsoluteX => (self.get_absolute_address(), self.register_x),
+            AddressingMode::AbsoluteY => (self.get_absolute_address(), self.register_y),
+            AddressingMode::IndirectY => (self.get_indirect_y_base(), self.register_y),
+            _ => panic!("Unsupported addressing mode for unstable store"),
+        };
+
+        let mut address = self.indexed_address(base, index, true);
+        let data = value & ((base >> 8) as u8).wrapping_add(1);
+
+        if page_crossed(base, address) {
+            address = ((data as u16) << 8) | (address & 0x00FF);
+        }
+
+        self.mem_write(address, data);
+    }
+}
@@ -0,0 +1,36 @@
+use crate::emulator::cpu::{OperationCode, CMOS_OPERATION_CODES, CPU_OPERATION_CODES};
+
+// 6502 family members the CPU can behave as
+// https://www.nesdev.org/wiki/CPU
+// http://www.6502.org/tutorials/65c02opcodes.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CpuVariant {
+    // NES CPU, an NMOS 6502 with the decimal mode disconnected. SED and CLD still change the flag.
+    #[default]
+    Ricoh2A03,
+    // NMOS 6502 with decimal mode, N, V and Z come from the binary result
+    Nmos6502,
+    // CMOS 65C02 (WDC): new instructions, undocumented codes are NOPs, JMP ($xxFF) reads the right byte,
+    // decimal mode takes an extra cycle and sets N and Z from the result, interrupts clear D
+    Cmos65C02,
+}
+
+impl CpuVariant {
+    // The 2A03 decodes every operation code exactly like the NMOS 6502, the same length, addressing mode
+    // and cycles. Its decimal mode is cut off in the adder, which has_decimal_mode covers, so both share
+    // one table.
+    pub fn operation_codes(&self) -> &'static [OperationCode; 256] {
+        match self {
+            CpuVariant::Ricoh2A03 | CpuVariant::Nmos6502 => &CPU_OPERATION_CODES,
+            CpuVariant::Cmos65C02 => &CMOS_OPERATION_CODES,
+        }
+    }
+
+    pub fn has_decimal_mode(&self) -> bool {
+        !matches!(self, CpuVariant::Ricoh2A03)
+    }
+
+    pub fn is_cmos(&self) -> bool {
+        matches!(self, CpuVariant::Cmos65C02)
+    }
+}
@@ -2,5 +2,6 @@ pub mod bus;
 pub mod cpu;
 pub mod ppu;
 pub mod apu;
+pub mod controller;
 pub mod ram;
 pub mod rom;
\ No newline at end of file
@@ -0,0 +1,120 @@
+use crate::emulator::bus::ppu_bus::PpuBus;
+use crate::emulator::ppu::PPU;
+use crate::emulator::ppu::registers::{ControlRegister, MaskRegister};
+
+// https://www.nesdev.org/wiki/PPU_rendering#Preface
+#[derive(Default)]
+pub(super) struct Background {
+    // latches filled by the memory fetches of the current tile
+    next_tile_id: u8,
+    next_tile_attribute: u8,
+    next_tile_lsb: u8,
+    next_tile_msb: u8,
+
+    // 16-bit shifters, high byte is the tile being drawn, low byte is the next one
+    pattern_lo: u16,
+    pattern_hi: u16,
+    attribute_lo: u16,
+    attribute_hi: u16,
+}
+
+impl Background {
+    fn load_shifters(&mut self) {
+        self.pattern_lo = (self.pattern_lo & 0xFF00) | self.next_tile_lsb as u16;
+        self.pattern_hi = (self.pattern_hi & 0xFF00) | self.next_tile_msb as u16;
+
+        let attribute_lo = if self.next_tile_attribute & 0b01 != 0 { 0xFF } else { 0x00 };
+        let attribute_hi = if self.next_tile_attribute & 0b10 != 0 { 0xFF } else { 0x00 };
+        self.attribute_lo = (self.attribute_lo & 0xFF00) | attribute_lo;
+        self.attribute_hi = (self.attribute_hi & 0xFF00) | attribute_hi;
+    }
+
+    fn shift(&mut self) {
+        self.pattern_lo <<= 1;
+        self.pattern_hi <<= 1;
+        self.attribute_lo <<= 1;
+        self.attribute_hi <<= 1;
+    }
+
+    // (palette, pixel) for the current dot
+    pub(super) fn pixel(&self, fine_x: u8) -> (u8, u8) {
+        let bit = 0x8000 >> fine_x;
+
+        let pixel = ((self.pattern_hi & bit != 0) as u8) << 1 | (self.pattern_lo & bit != 0) as u8;
+        let palette = ((self.attribute_hi & bit != 0) as u8) << 1 | (self.attribute_lo & bit != 0) as u8;
+
+        (palette, pixel)
+    }
+}
+
+impl PPU {
+    // Tile fetches and scroll updates for visible and pre-render scanlines
+    // https://www.nesdev.org/wiki/File:Ntsc_timing.png
+    pub(super) fn background_tick(&mut self, bus: &mut dyn PpuBus) {
+        let dot = self.cycles;
+
+        if (2..=257).contains(&dot) || (321..=337).contains(&dot) {
+            self.background.shift();
+
+            match (dot - 1) % 8 {
+                0 => {
+                    self.background.load_shifters();
+                    self.background.next_tile_id = self.mem_read(bus, 0x2000 | (self.vram_addr.0 & 0x0FFF));
+                }
+                2 => {
+                    let v = self.vram_addr.0;
+                    let address = 0x23C0 | (v & 0x0C00) | ((v >> 4) & 0x38) | ((v >> 2) & 0x07);
+                    let mut attribute = self.mem_read(bus, address);
+                    if self.vram_addr.coarse_y() & 0x02 != 0 {
+                        attribute >>= 4;
+                    }
+                    if self.vram_addr.coarse_x() & 0x02 != 0 {
+                        attribute >>= 2;
+                    }
+                    self.background.next_tile_attribute = attribute & 0x03;
+                }
+                4 => {
+                    let address = self.background_pattern_address();
+                    self.background.next_tile_lsb = self.mem_read(bus, address);
+                }
+                6 => {
+                    let address = self.background_pattern_address();
+                    self.background.next_tile_msb = self.mem_read(bus, address + 8);
+                }
+                7 => self.vram_addr.increment_coarse_x(),
+                _ => {}
+            }
+        }
+
+        match dot {
+            256 => self.vram_addr.increment_y(),
+            257 => {
+                self.background.load_shifters();
+                self.vram_addr.copy_horizontal(self.temp_vram_addr);
+            }
+            // unused nametable fetches
+            338 | 340 => {
+                self.background.next_tile_id = self.mem_read(bus, 0x2000 | (self.vram_addr.0 & 0x0FFF));
+            }
+            280..=304 if self.scanline == 261 => self.vram_addr.copy_vertical(self.temp_vram_addr),
+            _ => {}
+        }
+    }
+
+    // (palette, pixel) of the background at screen column x, 0 is transparent
+    pub(super) fn background_pixel(&self, x: usize) -> (u8, u8) {
+        if !self.mask.contains(MaskRegister::SHOW_BACKGROUND) {
+            return (0, 0);
+        }
+        if x < 8 && !self.mask.contains(MaskRegister::SHOW_BACKGROUND_LEFT) {
+            return (0, 0);
+        }
+
+        self.background.pixel(self.fine_x)
+    }
+
+    fn background_pattern_address(&self) -> u16 {
+        let table = if self.ctrl.contains(ControlRegister::BACKGROUND_PATTERN_ADDR) { 0x1000 } else { 0x0000 };
+        table + (self.background.next_tile_id as u16) * 16 + self.vram_addr.fine_y()
+    }
+}
@@ -1,61 +1,291 @@
+pub mod registers;
+mod background;
+mod sprites;
+
+use crate::emulator::bus::ppu_bus::PpuBus;
+use crate::emulator::ppu::background::Background;
+use crate::emulator::ppu::sprites::Sprites;
+use crate::emulator::ppu::registers::{ControlRegister, LoopyRegister, MaskRegister, StatusRegister};
+
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+
+// https://www.nesdev.org/wiki/Open_bus_behavior#PPU_open_bus
+// A bit of the I/O latch that is not refreshed fades to 0 after roughly 600ms
+const IO_LATCH_DECAY_FRAMES: u8 = 36;
+
 // Picture Processing Unit
 pub struct PPU {
-    vram: [u8; 2048],
     oam: [u8; 256],
     palette: [u8; 32],
+
+    ctrl: ControlRegister,
+    mask: MaskRegister,
+    status: StatusRegister,
+    oam_addr: u8,
+
+    // https://www.nesdev.org/wiki/PPU_scrolling#PPU_internal_registers
+    vram_addr: LoopyRegister,      // v
+    temp_vram_addr: LoopyRegister, // t
+    fine_x: u8,                    // x
+    write_toggle: bool,            // w
+    read_buffer: u8,
+
+    // Value left on the PPU data bus by the last register access, frames until each bit decays
+    io_latch: u8,
+    io_latch_decay: [u8; 8],
+
+    background: Background,
+    sprites: Sprites,
+    // palette-indexed (0-63) pixels, row by row
+    frame_buffer: Vec<u8>,
+
     scanline: i16,
     pub cycles: u16,
+    odd_frame: bool,
     frame_complete: bool,
+
+    // NMI output of the PPU is (vblank && PPUCTRL.7), the CPU reacts to its rising edge
+    nmi_output: bool,
     nmi_flag: bool,
+    // $2002 read one dot before vblank starts, the flag is not set for this frame
+    suppress_vblank: bool,
+}
+
+impl Default for PPU {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PPU {
     pub fn new() -> Self {
         PPU {
-            vram: [0; 2048],
             oam: [0; 256],
             palette: [0; 32],
+            ctrl: ControlRegister::empty(),
+            mask: MaskRegister::empty(),
+            status: StatusRegister::empty(),
+            oam_addr: 0,
+            vram_addr: LoopyRegister::default(),
+            temp_vram_addr: LoopyRegister::default(),
+            fine_x: 0,
+            write_toggle: false,
+            read_buffer: 0,
+            io_latch: 0,
+            io_latch_decay: [0; 8],
+            background: Background::default(),
+            sprites: Sprites::default(),
+            frame_buffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
             scanline: 0,
             cycles: 0,
+            odd_frame: false,
             frame_complete: false,
+            nmi_output: false,
             nmi_flag: false,
+            suppress_vblank: false,
         }
     }
 
-    pub fn read(&self, address: u16) -> u8 {
-        match address & 0x2007 {
-            0x2000..=0x2FFF => self.vram[((address - 0x2000) & 0x0FFF) as usize],
-            0x3000..=0x3EFF => self.vram[((address - 0x3000) & 0x0FFF) as usize], // Mirror of 0x2000-0x2EFF
-            0x3F00..=0x3FFF => self.palette[((address - 0x3F00) & 0x1F) as usize],
-            _ => panic!("Disallowed address read PPU: {:04X}", address),
+    // https://www.nesdev.org/wiki/PPU_power_up_state
+    // OAM, palette and nametables are left untouched
+    pub fn reset(&mut self) {
+        self.ctrl = ControlRegister::empty();
+        self.mask = MaskRegister::empty();
+        self.status.remove(StatusRegister::VBLANK_STARTED);
+        self.temp_vram_addr = LoopyRegister::default();
+        self.fine_x = 0;
+        self.write_toggle = false;
+        self.read_buffer = 0;
+        self.scanline = 0;
+        self.cycles = 0;
+        self.odd_frame = false;
+        self.frame_complete = false;
+        self.nmi_output = false;
+        self.nmi_flag = false;
+        self.suppress_vblank = false;
+    }
+
+    // CPU-facing registers $2000-$2007
+    pub fn read(&mut self, bus: &mut dyn PpuBus, address: u16) -> u8 {
+        match 0x2000 | (address & 0x0007) {
+            // PPUSTATUS
+            0x2002 => {
+                // https://www.nesdev.org/wiki/PPU_frame_timing#VBL_Flag_Timing
+                if self.scanline == 241 {
+                    match self.cycles {
+                        1 => self.suppress_vblank = true,
+                        2 | 3 => self.nmi_flag = false,
+                        _ => {}
+                    }
+                }
+
+                let data = self.status.bits();
+                self.status.remove(StatusRegister::VBLANK_STARTED);
+                self.write_toggle = false;
+                self.update_nmi_output();
+                self.refresh_io_latch(data, 0xE0)
+            }
+            // OAMDATA
+            0x2004 => {
+                let data = self.oam[self.oam_addr as usize];
+                self.refresh_io_latch(data, 0xFF)
+            }
+            // PPUDATA
+            0x2007 => {
+                let addr = self.vram_addr.address();
+                let data = if addr >= 0x3F00 {
+                    // palette reads are not buffered, the buffer gets the nametable byte "underneath"
+                    self.read_buffer = self.mem_read(bus, addr - 0x1000);
+                    let data = self.mem_read(bus, addr);
+                    self.refresh_io_latch(data, 0x3F)
+                } else {
+                    let buffered = self.read_buffer;
+                    self.read_buffer = self.mem_read(bus, addr);
+                    self.refresh_io_latch(buffered, 0xFF)
+                };
+                self.increment_vram_addr();
+                data
+            }
+            // write-only registers return the latch
+            _ => self.io_latch,
+        }
+    }
+
+    // What a read of the register would return, without clearing flags or moving the VRAM address
+    pub fn peek(&self, address: u16) -> u8 {
+        match 0x2000 | (address & 0x0007) {
+            // PPUSTATUS, bits 0-4 are not driven
+            0x2002 => (self.status.bits() & 0xE0) | (self.io_latch & 0x1F),
+            // OAMDATA
+            0x2004 => self.oam[self.oam_addr as usize],
+            // PPUDATA, the palette is inside the PPU, everything else comes from the read buffer
+            0x2007 => {
+                let addr = self.vram_addr.address();
+                if addr >= 0x3F00 {
+                    (self.palette[Self::palette_index(addr)] & 0x3F) | (self.io_latch & 0xC0)
+                } else {
+                    self.read_buffer
+                }
+            }
+            // write-only registers
+            _ => self.io_latch,
         }
     }
 
-    pub fn write(&mut self, address: u16, data: u8) {
-        match address & 0x2007 {
-            0x2000..=0x2FFF => self.vram[((address - 0x2000) & 0x0FFF) as usize] = data,
-            0x3000..=0x3EFF => self.vram[((address - 0x3000) & 0x0FFF) as usize] = data,
-            0x3F00..=0x3FFF => self.palette[((address - 0x3F00) & 0x1F) as usize] = data,
-            _ => panic!("Disallowed address write PPU: {:04X}", address),
+    pub fn write(&mut self, bus: &mut dyn PpuBus, address: u16, data: u8) {
+        // every write, $2002 included, fills the latch
+        self.refresh_io_latch(data, 0xFF);
+
+        match 0x2000 | (address & 0x0007) {
+            // PPUCTRL
+            0x2000 => {
+                self.ctrl = ControlRegister::from_bits_retain(data);
+                self.temp_vram_addr.set_nametable(data as u16);
+                // enabling NMI while in vblank triggers it immediately
+                self.update_nmi_output();
+            }
+            // PPUMASK
+            0x2001 => self.mask = MaskRegister::from_bits_retain(data),
+            // PPUSTATUS is read-only
+            0x2002 => {}
+            // OAMADDR
+            0x2003 => self.oam_addr = data,
+            // OAMDATA
+            0x2004 => {
+                self.oam[self.oam_addr as usize] = data;
+                self.oam_addr = self.oam_addr.wrapping_add(1);
+            }
+            // PPUSCROLL
+            0x2005 => {
+                if !self.write_toggle {
+                    self.temp_vram_addr.set_coarse_x((data >> 3) as u16);
+                    self.fine_x = data & 0x07;
+                } else {
+                    self.temp_vram_addr.set_coarse_y((data >> 3) as u16);
+                    self.temp_vram_addr.set_fine_y(data as u16);
+                }
+                self.write_toggle = !self.write_toggle;
+            }
+            // PPUADDR
+            0x2006 => {
+                if !self.write_toggle {
+                    // bit 14 of t is cleared by the first write
+                    self.temp_vram_addr.0 = (self.temp_vram_addr.0 & 0x00FF) | (((data & 0x3F) as u16) << 8);
+                } else {
+                    self.temp_vram_addr.0 = (self.temp_vram_addr.0 & 0xFF00) | data as u16;
+                    self.vram_addr = self.temp_vram_addr;
+                }
+                self.write_toggle = !self.write_toggle;
+            }
+            // PPUDATA
+            0x2007 => {
+                self.mem_write(bus, self.vram_addr.address(), data);
+                self.increment_vram_addr();
+            }
+            _ => unreachable!(),
         }
     }
 
-    pub fn tick(&mut self) {
+    // One PPU dot. Scanlines 0-239 are visible, 240 is idle, 241-260 are vblank and 261 is pre-render
+    pub fn tick(&mut self, bus: &mut dyn PpuBus) {
+        let rendering_enabled = self.mask.is_rendering_enabled();
+
+        if self.cycles == 1 {
+            if self.scanline == 241 {
+                if !self.suppress_vblank {
+                    self.status.insert(StatusRegister::VBLANK_STARTED);
+                }
+                self.suppress_vblank = false;
+                self.update_nmi_output();
+            } else if self.scanline == 261 {
+                self.status.remove(
+                    StatusRegister::VBLANK_STARTED | StatusRegister::SPRITE_ZERO_HIT | StatusRegister::SPRITE_OVERFLOW
+                );
+                self.update_nmi_output();
+            }
+        }
+
+        if self.scanline < 240 || self.scanline == 261 {
+            if rendering_enabled {
+                self.background_tick(bus);
+                self.sprite_tick(bus);
+            }
+
+            if self.scanline < 240 && (1..=256).contains(&self.cycles) {
+                self.render_pixel(bus);
+            }
+        }
+
         self.cycles += 1;
 
+        // odd frames skip the last dot of the pre-render scanline while rendering
+        if self.scanline == 261 && self.cycles == 340 && self.odd_frame && rendering_enabled {
+            self.cycles = 341;
+        }
+
         if self.cycles >= 341 {
             self.cycles = 0;
             self.scanline += 1;
 
-            if self.scanline == 241 {
-                self.nmi_flag = true;
-            } else if self.scanline >= 261 {
+            if self.scanline > 261 {
                 self.scanline = 0;
+                self.odd_frame = !self.odd_frame;
                 self.frame_complete = true;
+                self.decay_io_latch();
             }
         }
     }
 
+    pub fn frame_buffer(&self) -> &[u8] {
+        &self.frame_buffer
+    }
+
+    // (scanline, dot) of the next tick
+    pub fn position(&self) -> (u16, u16) {
+        (self.scanline as u16, self.cycles)
+    }
+
     pub fn is_frame_complete(&mut self) -> bool {
         if self.frame_complete {
             self.frame_complete = false;
@@ -73,4 +303,647 @@ impl PPU {
             false
         }
     }
-}
\ No newline at end of file
+
+    // Bits in `mask` are driven by the register, the rest of the value comes from the latch
+    fn refresh_io_latch(&mut self, data: u8, mask: u8) -> u8 {
+        self.io_latch = (self.io_latch & !mask) | (data & mask);
+        for bit in 0..8 {
+            if mask & (1 << bit) != 0 {
+                self.io_latch_decay[bit] = IO_LATCH_DECAY_FRAMES;
+            }
+        }
+        self.io_latch
+    }
+
+    fn decay_io_latch(&mut self) {
+        for bit in 0..8 {
+            if self.io_latch_decay[bit] > 0 {
+                self.io_latch_decay[bit] -= 1;
+                if self.io_latch_decay[bit] == 0 {
+                    self.io_latch &= !(1 << bit);
+                }
+            }
+        }
+    }
+
+    fn update_nmi_output(&mut self) {
+        let nmi_output = self.status.contains(StatusRegister::VBLANK_STARTED)
+            && self.ctrl.contains(ControlRegister::GENERATE_NMI);
+
+        if nmi_output && !self.nmi_output {
+            self.nmi_flag = true;
+        }
+        self.nmi_output = nmi_output;
+    }
+
+    fn render_pixel(&mut self, bus: &mut dyn PpuBus) {
+        let x = (self.cycles - 1) as usize;
+        let y = self.scanline as usize;
+
+        let (background_palette, background_pixel) = self.background_pixel(x);
+        let sprite = self.sprite_pixel(x);
+
+        // https://www.nesdev.org/wiki/PPU_rendering#Preface (priority multiplexer)
+        let palette_address = match sprite {
+            Some(sprite) if background_pixel != 0 => {
+                if sprite.sprite_zero && x != 255 {
+                    self.status.insert(StatusRegister::SPRITE_ZERO_HIT);
+                }
+                if sprite.behind_background {
+                    0x3F00 | ((background_palette as u16) << 2) | background_pixel as u16
+                } else {
+                    0x3F10 | ((sprite.palette as u16) << 2) | sprite.pixel as u16
+                }
+            }
+            Some(sprite) => 0x3F10 | ((sprite.palette as u16) << 2) | sprite.pixel as u16,
+            None if background_pixel != 0 => {
+                0x3F00 | ((background_palette as u16) << 2) | background_pixel as u16
+            }
+            None => 0x3F00, // universal background color
+        };
+
+        let mut color = self.mem_read(bus, palette_address) & 0x3F;
+        if self.mask.contains(MaskRegister::GREYSCALE) {
+            color &= 0x30;
+        }
+
+        self.frame_buffer[y * SCREEN_WIDTH + x] = color;
+    }
+
+    fn increment_vram_addr(&mut self) {
+        let increment = self.ctrl.vram_addr_increment();
+        self.vram_addr.0 = self.vram_addr.0.wrapping_add(increment) & 0x7FFF;
+    }
+
+    // PPU address space $0000-$3FFF
+    fn mem_read(&self, bus: &mut dyn PpuBus, address: u16) -> u8 {
+        match address & 0x3FFF {
+            0x0000..=0x3EFF => bus.read(address & 0x3FFF),
+            _ => self.palette[Self::palette_index(address)],
+        }
+    }
+
+    fn mem_write(&mut self, bus: &mut dyn PpuBus, address: u16, data: u8) {
+        match address & 0x3FFF {
+            0x0000..=0x3EFF => bus.write(address & 0x3FFF, data),
+            _ => self.palette[Self::palette_index(address)] = data,
+        }
+    }
+
+    // $3F10/$3F14/$3F18/$3F1C mirror $3F00/$3F04/$3F08/$3F0C
+    fn palette_index(address: u16) -> usize {
+        let index = address & 0x1F;
+        match index {
+            0x10 | 0x14 | 0x18 | 0x1C => (index - 0x10) as usize,
+            _ => index as usize,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::bus::mock_ppu_bus::MockPpuBus;
+
+    fn set_vram_addr(ppu: &mut PPU, bus: &mut MockPpuBus, address: u16) {
+        ppu.write(bus, 0x2006, (address >> 8) as u8);
+        ppu.write(bus, 0x2006, address as u8);
+    }
+
+    #[test]
+    fn test_ppudata_write_and_buffered_read() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+
+        set_vram_addr(&mut ppu, &mut bus, 0x2305);
+        ppu.write(&mut bus, 0x2007, 0x66);
+        ppu.write(&mut bus, 0x2007, 0x77);
+
+        set_vram_addr(&mut ppu, &mut bus, 0x2305);
+        ppu.read(&mut bus, 0x2007); // dummy read fills the buffer
+        assert_eq!(ppu.read(&mut bus, 0x2007), 0x66);
+        assert_eq!(ppu.read(&mut bus, 0x2007), 0x77);
+    }
+
+    #[test]
+    fn test_ppudata_increment_32() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        ppu.write(&mut bus, 0x2000, 0b0000_0100);
+
+        set_vram_addr(&mut ppu, &mut bus, 0x21FF);
+        ppu.write(&mut bus, 0x2007, 0x66);
+        ppu.write(&mut bus, 0x2007, 0x77);
+
+        assert_eq!(ppu.vram_addr.address(), 0x223F);
+        assert_eq!(bus.memory[0x21FF], 0x66);
+        assert_eq!(bus.memory[0x221F], 0x77);
+    }
+
+    #[test]
+    fn test_palette_read_is_not_buffered() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+
+        set_vram_addr(&mut ppu, &mut bus, 0x3F10);
+        ppu.write(&mut bus, 0x2007, 0x2A);
+
+        set_vram_addr(&mut ppu, &mut bus, 0x3F00);
+        assert_eq!(ppu.read(&mut bus, 0x2007), 0x2A);
+    }
+
+    #[test]
+    fn test_status_read_clears_vblank_and_write_toggle() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        ppu.status.insert(StatusRegister::VBLANK_STARTED);
+
+        ppu.write(&mut bus, 0x2006, 0x21);
+        let status = ppu.read(&mut bus, 0x2002);
+
+        assert_eq!(status & 0x80, 0x80);
+        assert!(!ppu.status.contains(StatusRegister::VBLANK_STARTED));
+        assert!(!ppu.write_toggle);
+
+        // toggle was reset, so this is treated as the high byte again
+        set_vram_addr(&mut ppu, &mut bus, 0x2400);
+        assert_eq!(ppu.vram_addr.address(), 0x2400);
+    }
+
+    #[test]
+    fn test_peek_has_no_side_effects() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        bus.memory[0x2400] = 0x55;
+        ppu.status.insert(StatusRegister::VBLANK_STARTED);
+
+        set_vram_addr(&mut ppu, &mut bus, 0x2400);
+        ppu.read(&mut bus, 0x2007); // buffer = $55
+        ppu.write(&mut bus, 0x2006, 0x3F); // toggle set
+
+        assert_eq!(ppu.peek(0x2002) & 0x80, 0x80);
+        assert_eq!(ppu.peek(0x2007), 0x55);
+        assert_eq!(ppu.peek(0x2000), 0x3F);
+
+        assert!(ppu.status.contains(StatusRegister::VBLANK_STARTED));
+        assert!(ppu.write_toggle);
+        assert_eq!(ppu.vram_addr.address(), 0x2401);
+        assert_eq!(ppu.read(&mut bus, 0x2002) & 0x80, 0x80);
+    }
+
+    #[test]
+    fn test_scroll_and_ctrl_update_temp_address() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+
+        ppu.write(&mut bus, 0x2000, 0b0000_0011);
+        ppu.write(&mut bus, 0x2005, 0b0111_1101); // coarse X = 15, fine X = 5
+        ppu.write(&mut bus, 0x2005, 0b0101_1110); // coarse Y = 11, fine Y = 6
+
+        assert_eq!(ppu.temp_vram_addr.nametable(), 0b11);
+        assert_eq!(ppu.temp_vram_addr.coarse_x(), 15);
+        assert_eq!(ppu.fine_x, 5);
+        assert_eq!(ppu.temp_vram_addr.coarse_y(), 11);
+        assert_eq!(ppu.temp_vram_addr.fine_y(), 6);
+        assert!(!ppu.write_toggle);
+    }
+
+    #[test]
+    fn test_ppuaddr_second_write_copies_t_to_v() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+
+        ppu.write(&mut bus, 0x2006, 0xFF); // bits 14-15 are dropped
+        assert_eq!(ppu.vram_addr.0, 0);
+
+        ppu.write(&mut bus, 0x2006, 0x10);
+        assert_eq!(ppu.vram_addr.0, 0x3F10);
+    }
+
+    // tile 1: left half color 1, right half color 3; palette 0 = $0F, $01, $02, $03
+    fn prepare_background(ppu: &mut PPU, bus: &mut MockPpuBus) {
+        for row in 0..8 {
+            bus.memory[16 + row] = 0b1111_0000;
+            bus.memory[16 + 8 + row] = 0b0000_1111;
+        }
+        for i in 0..32 * 30 {
+            bus.memory[0x2000 + i] = 1;
+        }
+        set_vram_addr(ppu, bus, 0x3F00);
+        for color in [0x0F, 0x01, 0x02, 0x03] {
+            ppu.write(bus, 0x2007, color);
+        }
+    }
+
+    fn run_frame(ppu: &mut PPU, bus: &mut MockPpuBus) {
+        while !ppu.is_frame_complete() {
+            ppu.tick(bus);
+        }
+    }
+
+    fn pixel(ppu: &PPU, x: usize, y: usize) -> u8 {
+        ppu.frame_buffer()[y * SCREEN_WIDTH + x]
+    }
+
+    #[test]
+    fn test_background_rendering() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        prepare_background(&mut ppu, &mut bus);
+        ppu.write(&mut bus, 0x2001, 0b0000_1010); // background + left column
+        set_vram_addr(&mut ppu, &mut bus, 0x0000);
+
+        // the first frame starts without the pre-render scanline prefetch
+        run_frame(&mut ppu, &mut bus);
+        run_frame(&mut ppu, &mut bus);
+
+        assert_eq!(ppu.frame_buffer().len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+        assert_eq!(pixel(&ppu, 0, 0), 0x01);
+        assert_eq!(pixel(&ppu, 3, 0), 0x01);
+        assert_eq!(pixel(&ppu, 4, 0), 0x02);
+        assert_eq!(pixel(&ppu, 7, 100), 0x02);
+        assert_eq!(pixel(&ppu, 8, 239), 0x01);
+        assert_eq!(pixel(&ppu, 255, 239), 0x02);
+    }
+
+    #[test]
+    fn test_background_fine_x_scroll() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        prepare_background(&mut ppu, &mut bus);
+        ppu.write(&mut bus, 0x2001, 0b0000_1010);
+        ppu.write(&mut bus, 0x2005, 2);
+        ppu.write(&mut bus, 0x2005, 0);
+        ppu.write(&mut bus, 0x2000, 0);
+
+        run_frame(&mut ppu, &mut bus);
+        run_frame(&mut ppu, &mut bus);
+
+        assert_eq!(pixel(&ppu, 0, 10), 0x01);
+        assert_eq!(pixel(&ppu, 1, 10), 0x01);
+        assert_eq!(pixel(&ppu, 2, 10), 0x02);
+        assert_eq!(pixel(&ppu, 6, 10), 0x01);
+    }
+
+    #[test]
+    fn test_background_left_clipping_and_greyscale() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        prepare_background(&mut ppu, &mut bus);
+        ppu.write(&mut bus, 0x2001, 0b0000_1001); // background, left column hidden, greyscale
+        set_vram_addr(&mut ppu, &mut bus, 0x0000);
+
+        // the first frame starts without the pre-render scanline prefetch
+        run_frame(&mut ppu, &mut bus);
+        run_frame(&mut ppu, &mut bus);
+
+        assert_eq!(pixel(&ppu, 0, 0), 0x0F & 0x30);
+        assert_eq!(pixel(&ppu, 7, 0), 0x0F & 0x30);
+        assert_eq!(pixel(&ppu, 8, 0), 0x01 & 0x30);
+    }
+
+    #[test]
+    fn test_rendering_disabled_shows_backdrop() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        prepare_background(&mut ppu, &mut bus);
+
+        run_frame(&mut ppu, &mut bus);
+
+        assert!(ppu.frame_buffer().iter().all(|&color| color == 0x0F));
+    }
+
+    // tile 2: solid color 3; sprite palette 0 = $0F, $11, $12, $13
+    fn prepare_sprites(ppu: &mut PPU, bus: &mut MockPpuBus) {
+        for row in 0..8 {
+            bus.memory[32 + row] = 0xFF;
+            bus.memory[32 + 8 + row] = 0xFF;
+        }
+        set_vram_addr(ppu, bus, 0x3F10);
+        for color in [0x0F, 0x11, 0x12, 0x13] {
+            ppu.write(bus, 0x2007, color);
+        }
+    }
+
+    fn set_sprite(ppu: &mut PPU, index: usize, y: u8, tile: u8, attribute: u8, x: u8) {
+        ppu.oam[index * 4..index * 4 + 4].copy_from_slice(&[y, tile, attribute, x]);
+    }
+
+    #[test]
+    fn test_sprite_rendering() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        prepare_sprites(&mut ppu, &mut bus);
+        set_sprite(&mut ppu, 0, 49, 2, 0, 100);
+        ppu.write(&mut bus, 0x2001, 0b0001_0110); // sprites + left columns
+
+        run_frame(&mut ppu, &mut bus);
+
+        assert_eq!(pixel(&ppu, 99, 50), 0x0F);
+        assert_eq!(pixel(&ppu, 100, 49), 0x0F);
+        assert_eq!(pixel(&ppu, 100, 50), 0x13);
+        assert_eq!(pixel(&ppu, 107, 57), 0x13);
+        assert_eq!(pixel(&ppu, 108, 57), 0x0F);
+        assert_eq!(pixel(&ppu, 107, 58), 0x0F);
+    }
+
+    #[test]
+    fn test_sprite_horizontal_flip() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        prepare_sprites(&mut ppu, &mut bus);
+        // tile 3: only the leftmost column, color 1
+        for row in 0..8 {
+            bus.memory[48 + row] = 0b1000_0000;
+        }
+        set_sprite(&mut ppu, 0, 9, 3, 0, 16);
+        set_sprite(&mut ppu, 1, 9, 3, 0b0100_0000, 32);
+        ppu.write(&mut bus, 0x2001, 0b0001_0110);
+
+        run_frame(&mut ppu, &mut bus);
+
+        assert_eq!(pixel(&ppu, 16, 12), 0x11);
+        assert_eq!(pixel(&ppu, 23, 12), 0x0F);
+        assert_eq!(pixel(&ppu, 32, 12), 0x0F);
+        assert_eq!(pixel(&ppu, 39, 12), 0x11);
+    }
+
+    #[test]
+    fn test_sprite_8x16() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        prepare_sprites(&mut ppu, &mut bus);
+        // 8x16 sprite using tiles 2 (top) and 3 (bottom, color 1)
+        for row in 0..8 {
+            bus.memory[48 + row] = 0xFF;
+        }
+        set_sprite(&mut ppu, 0, 19, 2, 0, 40);
+        ppu.write(&mut bus, 0x2000, 0b0010_0000);
+        ppu.write(&mut bus, 0x2001, 0b0001_0110);
+
+        run_frame(&mut ppu, &mut bus);
+
+        assert_eq!(pixel(&ppu, 40, 20), 0x13);
+        assert_eq!(pixel(&ppu, 40, 27), 0x13);
+        assert_eq!(pixel(&ppu, 40, 28), 0x11);
+        assert_eq!(pixel(&ppu, 40, 35), 0x11);
+        assert_eq!(pixel(&ppu, 40, 36), 0x0F);
+    }
+
+    #[test]
+    fn test_sprite_behind_background() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        prepare_background(&mut ppu, &mut bus);
+        prepare_sprites(&mut ppu, &mut bus);
+        set_sprite(&mut ppu, 0, 49, 2, 0b0010_0000, 100);
+        ppu.write(&mut bus, 0x2001, 0b0001_1110);
+        set_vram_addr(&mut ppu, &mut bus, 0x0000);
+
+        run_frame(&mut ppu, &mut bus);
+        run_frame(&mut ppu, &mut bus);
+
+        assert_eq!(pixel(&ppu, 100, 50), 0x02);
+    }
+
+    #[test]
+    fn test_sprite_zero_hit() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        prepare_background(&mut ppu, &mut bus);
+        prepare_sprites(&mut ppu, &mut bus);
+        set_sprite(&mut ppu, 0, 49, 2, 0, 100);
+        ppu.write(&mut bus, 0x2001, 0b0001_1110);
+        set_vram_addr(&mut ppu, &mut bus, 0x0000);
+
+        run_frame(&mut ppu, &mut bus);
+        while ppu.scanline != 50 {
+            ppu.tick(&mut bus);
+        }
+        assert!(!ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+
+        while ppu.scanline != 51 {
+            ppu.tick(&mut bus);
+        }
+        assert!(ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+
+        // cleared on the pre-render scanline
+        while ppu.scanline != 261 || ppu.cycles != 2 {
+            ppu.tick(&mut bus);
+        }
+        assert!(!ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+    }
+
+    #[test]
+    fn test_sprite_zero_hit_requires_opaque_background() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        prepare_sprites(&mut ppu, &mut bus);
+        set_sprite(&mut ppu, 0, 49, 2, 0, 100);
+        ppu.write(&mut bus, 0x2001, 0b0001_1110);
+
+        run_frame(&mut ppu, &mut bus);
+        run_frame(&mut ppu, &mut bus);
+
+        assert!(!ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+    }
+
+    #[test]
+    fn test_sprite_overflow() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        prepare_sprites(&mut ppu, &mut bus);
+        for index in 0..9 {
+            set_sprite(&mut ppu, index, 99, 2, 0, index as u8 * 8);
+        }
+        for index in 9..64 {
+            set_sprite(&mut ppu, index, 0xF0, 2, 0, 0);
+        }
+        ppu.write(&mut bus, 0x2001, 0b0001_0110);
+
+        while ppu.scanline != 101 {
+            ppu.tick(&mut bus);
+        }
+
+        assert!(ppu.status.contains(StatusRegister::SPRITE_OVERFLOW));
+        // only the first eight are drawn
+        assert_eq!(pixel(&ppu, 56, 100), 0x13);
+        assert_eq!(pixel(&ppu, 64, 100), 0x0F);
+    }
+
+    fn run_until(ppu: &mut PPU, bus: &mut MockPpuBus, scanline: i16, dot: u16) {
+        while ppu.scanline != scanline || ppu.cycles != dot {
+            ppu.tick(bus);
+        }
+    }
+
+    #[test]
+    fn test_vblank_flag_timing() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+
+        run_until(&mut ppu, &mut bus, 241, 1);
+        assert!(!ppu.status.contains(StatusRegister::VBLANK_STARTED));
+        ppu.tick(&mut bus);
+        assert!(ppu.status.contains(StatusRegister::VBLANK_STARTED));
+
+        run_until(&mut ppu, &mut bus, 261, 1);
+        assert!(ppu.status.contains(StatusRegister::VBLANK_STARTED));
+        ppu.tick(&mut bus);
+        assert!(!ppu.status.contains(StatusRegister::VBLANK_STARTED));
+    }
+
+    #[test]
+    fn test_nmi_disabled() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+
+        run_frame(&mut ppu, &mut bus);
+
+        assert!(!ppu.fetch_nmi());
+    }
+
+    #[test]
+    fn test_nmi_enabled() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        ppu.write(&mut bus, 0x2000, 0b1000_0000);
+
+        run_until(&mut ppu, &mut bus, 241, 1);
+        assert!(!ppu.fetch_nmi());
+        ppu.tick(&mut bus);
+        assert!(ppu.fetch_nmi());
+        assert!(!ppu.fetch_nmi());
+    }
+
+    #[test]
+    fn test_nmi_enabled_during_vblank() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+
+        run_until(&mut ppu, &mut bus, 250, 0);
+        assert!(!ppu.fetch_nmi());
+
+        ppu.write(&mut bus, 0x2000, 0b1000_0000);
+        assert!(ppu.fetch_nmi());
+
+        // toggling it again without leaving vblank generates another NMI
+        ppu.write(&mut bus, 0x2000, 0);
+        ppu.write(&mut bus, 0x2000, 0b1000_0000);
+        assert!(ppu.fetch_nmi());
+
+        // but not once the flag has been read
+        ppu.write(&mut bus, 0x2000, 0);
+        ppu.read(&mut bus, 0x2002);
+        ppu.write(&mut bus, 0x2000, 0b1000_0000);
+        assert!(!ppu.fetch_nmi());
+    }
+
+    #[test]
+    fn test_status_read_before_vblank_suppresses_flag_and_nmi() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        ppu.write(&mut bus, 0x2000, 0b1000_0000);
+
+        run_until(&mut ppu, &mut bus, 241, 1);
+        assert_eq!(ppu.read(&mut bus, 0x2002) & 0x80, 0);
+
+        run_until(&mut ppu, &mut bus, 241, 10);
+        assert!(!ppu.status.contains(StatusRegister::VBLANK_STARTED));
+        assert!(!ppu.fetch_nmi());
+    }
+
+    #[test]
+    fn test_status_read_at_vblank_suppresses_nmi() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        ppu.write(&mut bus, 0x2000, 0b1000_0000);
+
+        run_until(&mut ppu, &mut bus, 241, 2);
+        assert_eq!(ppu.read(&mut bus, 0x2002) & 0x80, 0x80);
+        assert!(!ppu.fetch_nmi());
+    }
+
+    #[test]
+    fn test_reset_clears_registers_and_keeps_memory() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+        ppu.write(&mut bus, 0x2000, 0b1000_0100);
+        ppu.write(&mut bus, 0x2001, 0b0001_1110);
+        ppu.write(&mut bus, 0x2005, 0x12);
+        ppu.write(&mut bus, 0x2003, 0x00);
+        ppu.write(&mut bus, 0x2004, 0x66);
+
+        ppu.reset();
+
+        assert!(ppu.ctrl.is_empty());
+        assert!(ppu.mask.is_empty());
+        assert!(!ppu.write_toggle);
+        assert_eq!(ppu.fine_x, 0);
+        assert_eq!(ppu.oam[0], 0x66);
+    }
+
+    #[test]
+    fn test_oam_data() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+
+        ppu.write(&mut bus, 0x2003, 0x10);
+        ppu.write(&mut bus, 0x2004, 0x66);
+        ppu.write(&mut bus, 0x2004, 0x77);
+
+        ppu.write(&mut bus, 0x2003, 0x10);
+        assert_eq!(ppu.read(&mut bus, 0x2004), 0x66);
+        ppu.write(&mut bus, 0x2003, 0x11);
+        assert_eq!(ppu.read(&mut bus, 0x2004), 0x77);
+    }
+
+    #[test]
+    fn test_write_only_registers_read_the_io_latch() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+
+        ppu.write(&mut bus, 0x2005, 0x5A);
+        assert_eq!(ppu.read(&mut bus, 0x2000), 0x5A);
+        assert_eq!(ppu.read(&mut bus, 0x2006), 0x5A);
+        assert_eq!(ppu.peek(0x2003), 0x5A);
+
+        // PPUSTATUS only drives bits 5-7
+        ppu.status.insert(StatusRegister::VBLANK_STARTED);
+        assert_eq!(ppu.read(&mut bus, 0x2002), 0x9A);
+        assert_eq!(ppu.read(&mut bus, 0x2001), 0x9A);
+    }
+
+    #[test]
+    fn test_palette_read_keeps_latch_high_bits() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+
+        set_vram_addr(&mut ppu, &mut bus, 0x3F00);
+        ppu.write(&mut bus, 0x2007, 0x2A);
+        set_vram_addr(&mut ppu, &mut bus, 0x3F00);
+        ppu.write(&mut bus, 0x2000, 0xC0);
+
+        assert_eq!(ppu.peek(0x2007), 0xEA);
+        assert_eq!(ppu.read(&mut bus, 0x2007), 0xEA);
+    }
+
+    #[test]
+    fn test_io_latch_decay() {
+        let mut ppu = PPU::new();
+        let mut bus = MockPpuBus::new();
+
+        ppu.write(&mut bus, 0x2003, 0xFF);
+        for _ in 0..IO_LATCH_DECAY_FRAMES - 1 {
+            run_frame(&mut ppu, &mut bus);
+        }
+        // the status read refreshes bits 5-7 only
+        ppu.status.insert(StatusRegister::all());
+        ppu.read(&mut bus, 0x2002);
+        assert_eq!(ppu.peek(0x2000), 0xFF);
+
+        run_frame(&mut ppu, &mut bus);
+        assert_eq!(ppu.peek(0x2000), 0xE0);
+    }
+}
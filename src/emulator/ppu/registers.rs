@@ -0,0 +1,183 @@
+/* https://www.nesdev.org/wiki/PPU_registers#PPUCTRL
+7  bit  0
+---- ----
+VPHB SINN
+|||| ||||
+|||| ||++- Base nametable address
+|||| ||    (0 = $2000; 1 = $2400; 2 = $2800; 3 = $2C00)
+|||| |+--- VRAM address increment per CPU read/write of PPUDATA
+|||| |     (0: add 1, going across; 1: add 32, going down)
+|||| +---- Sprite pattern table address for 8x8 sprites
+||||       (0: $0000; 1: $1000; ignored in 8x16 mode)
+|||+------ Background pattern table address (0: $0000; 1: $1000)
+||+------- Sprite size (0: 8x8 pixels; 1: 8x16 pixels)
+|+-------- PPU master/slave select
++--------- Generate an NMI at the start of vertical blanking (0: off; 1: on)
+*/
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub struct ControlRegister: u8 {
+        const NAMETABLE_X = 0b0000_0001;
+        const NAMETABLE_Y = 0b0000_0010;
+        const VRAM_ADDR_INCREMENT = 0b0000_0100;
+        const SPRITE_PATTERN_ADDR = 0b0000_1000;
+        const BACKGROUND_PATTERN_ADDR = 0b0001_0000;
+        const SPRITE_SIZE = 0b0010_0000;
+        const MASTER_SLAVE_SELECT = 0b0100_0000;
+        const GENERATE_NMI = 0b1000_0000;
+    }
+}
+
+impl ControlRegister {
+    pub fn vram_addr_increment(&self) -> u16 {
+        if self.contains(ControlRegister::VRAM_ADDR_INCREMENT) { 32 } else { 1 }
+    }
+}
+
+/* https://www.nesdev.org/wiki/PPU_registers#PPUMASK
+7  bit  0
+---- ----
+BGRs bMmG
+|||| ||||
+|||| |||+- Greyscale (0: normal color, 1: produce a greyscale display)
+|||| ||+-- 1: Show background in leftmost 8 pixels of screen, 0: Hide
+|||| |+--- 1: Show sprites in leftmost 8 pixels of screen, 0: Hide
+|||| +---- 1: Show background
+|||+------ 1: Show sprites
+||+------- Emphasize red (green on PAL/Dendy)
+|+-------- Emphasize green (red on PAL/Dendy)
++--------- Emphasize blue
+*/
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub struct MaskRegister: u8 {
+        const GREYSCALE = 0b0000_0001;
+        const SHOW_BACKGROUND_LEFT = 0b0000_0010;
+        const SHOW_SPRITES_LEFT = 0b0000_0100;
+        const SHOW_BACKGROUND = 0b0000_1000;
+        const SHOW_SPRITES = 0b0001_0000;
+        const EMPHASIZE_RED = 0b0010_0000;
+        const EMPHASIZE_GREEN = 0b0100_0000;
+        const EMPHASIZE_BLUE = 0b1000_0000;
+    }
+}
+
+impl MaskRegister {
+    pub fn is_rendering_enabled(&self) -> bool {
+        self.intersects(MaskRegister::SHOW_BACKGROUND | MaskRegister::SHOW_SPRITES)
+    }
+}
+
+/* https://www.nesdev.org/wiki/PPU_registers#PPUSTATUS
+7  bit  0
+---- ----
+VSO. ....
+|||| ||||
+|||+-++++- (PPU open bus)
+||+------- Sprite overflow
+|+-------- Sprite 0 hit
++--------- Vertical blank has started (0: not in vblank; 1: in vblank)
+*/
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub struct StatusRegister: u8 {
+        const SPRITE_OVERFLOW = 0b0010_0000;
+        const SPRITE_ZERO_HIT = 0b0100_0000;
+        const VBLANK_STARTED = 0b1000_0000;
+    }
+}
+
+/* https://www.nesdev.org/wiki/PPU_scrolling#PPU_internal_registers
+Loopy's v (current VRAM address) and t (temporary VRAM address) layout:
+yyy NN YYYYY XXXXX
+||| || ||||| +++++-- coarse X scroll
+||| || +++++-------- coarse Y scroll
+||| ++-------------- nametable select
++++----------------- fine Y scroll
+*/
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LoopyRegister(pub u16);
+
+impl LoopyRegister {
+    const COARSE_X: u16 = 0x001F;
+    const COARSE_Y: u16 = 0x03E0;
+    const NAMETABLE: u16 = 0x0C00;
+    const FINE_Y: u16 = 0x7000;
+
+    pub fn coarse_x(&self) -> u16 {
+        self.0 & Self::COARSE_X
+    }
+
+    pub fn set_coarse_x(&mut self, value: u16) {
+        self.0 = (self.0 & !Self::COARSE_X) | (value & 0x1F);
+    }
+
+    pub fn coarse_y(&self) -> u16 {
+        (self.0 & Self::COARSE_Y) >> 5
+    }
+
+    pub fn set_coarse_y(&mut self, value: u16) {
+        self.0 = (self.0 & !Self::COARSE_Y) | ((value & 0x1F) << 5);
+    }
+
+    pub fn nametable(&self) -> u16 {
+        (self.0 & Self::NAMETABLE) >> 10
+    }
+
+    pub fn set_nametable(&mut self, value: u16) {
+        self.0 = (self.0 & !Self::NAMETABLE) | ((value & 0x03) << 10);
+    }
+
+    pub fn fine_y(&self) -> u16 {
+        (self.0 & Self::FINE_Y) >> 12
+    }
+
+    pub fn set_fine_y(&mut self, value: u16) {
+        self.0 = (self.0 & !Self::FINE_Y) | ((value & 0x07) << 12);
+    }
+
+    // 14-bit address placed on the PPU bus by $2007 accesses
+    pub fn address(&self) -> u16 {
+        self.0 & 0x3FFF
+    }
+
+    // https://www.nesdev.org/wiki/PPU_scrolling#Coarse_X_increment
+    pub fn increment_coarse_x(&mut self) {
+        if self.coarse_x() == 31 {
+            self.set_coarse_x(0);
+            self.0 ^= 0x0400; // switch horizontal nametable
+        } else {
+            self.0 += 1;
+        }
+    }
+
+    // https://www.nesdev.org/wiki/PPU_scrolling#Y_increment
+    pub fn increment_y(&mut self) {
+        if self.fine_y() < 7 {
+            self.0 += 0x1000;
+            return;
+        }
+
+        self.set_fine_y(0);
+        match self.coarse_y() {
+            29 => {
+                self.set_coarse_y(0);
+                self.0 ^= 0x0800; // switch vertical nametable
+            }
+            31 => self.set_coarse_y(0), // attribute rows, no nametable switch
+            coarse_y => self.set_coarse_y(coarse_y + 1),
+        }
+    }
+
+    // v: ....A.. ...BCDEF <- t: ....A.. ...BCDEF
+    pub fn copy_horizontal(&mut self, source: LoopyRegister) {
+        let mask = Self::COARSE_X | 0x0400;
+        self.0 = (self.0 & !mask) | (source.0 & mask);
+    }
+
+    // v: GHIA.BC DEF..... <- t: GHIA.BC DEF.....
+    pub fn copy_vertical(&mut self, source: LoopyRegister) {
+        let mask = Self::FINE_Y | Self::COARSE_Y | 0x0800;
+        self.0 = (self.0 & !mask) | (source.0 & mask);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::emulator::bus::ppu_bus::PpuBus;
+use crate::emulator::ppu::PPU;
+use crate::emulator::ppu::registers::{ControlRegister, MaskRegister, StatusRegister};
+
+/* https://www.nesdev.org/wiki/PPU_OAM
+Byte 0 - Y position of top of sprite (minus one)
+Byte 1 - Tile index number
+Byte 2 - Attributes
+76543210
+||||||||
+||||||++- Palette (4 to 7) of sprite
+|||+++--- Unimplemented (read 0)
+||+------ Priority (0: in front of background; 1: behind background)
+|+------- Flip sprite horizontally
++-------- Flip sprite vertically
+Byte 3 - X position of left side of sprite
+*/
+const ATTRIBUTE_PALETTE: u8 = 0b0000_0011;
+const ATTRIBUTE_PRIORITY: u8 = 0b0010_0000;
+const ATTRIBUTE_FLIP_HORIZONTAL: u8 = 0b0100_0000;
+const ATTRIBUTE_FLIP_VERTICAL: u8 = 0b1000_0000;
+
+const MAX_SPRITES_PER_SCANLINE: usize = 8;
+
+#[derive(Clone, Copy, Default)]
+struct SpriteSlot {
+    x: u8,
+    attribute: u8,
+    pattern_lo: u8,
+    pattern_hi: u8,
+}
+
+#[derive(Default)]
+pub(super) struct Sprites {
+    secondary_oam: [u8; MAX_SPRITES_PER_SCANLINE * 4],
+    found: usize,
+    sprite_zero_found: bool,
+
+    // sprites fetched for the next scanline
+    slots: [SpriteSlot; MAX_SPRITES_PER_SCANLINE],
+    count: usize,
+    sprite_zero_in_slots: bool,
+}
+
+pub(super) struct SpritePixel {
+    pub(super) palette: u8,
+    pub(super) pixel: u8,
+    pub(super) behind_background: bool,
+    pub(super) sprite_zero: bool,
+}
+
+impl PPU {
+    // Sprite evaluation and pattern fetches for visible and pre-render scanlines
+    // https://www.nesdev.org/wiki/PPU_sprite_evaluation
+    pub(super) fn sprite_tick(&mut self, bus: &mut dyn PpuBus) {
+        match self.cycles {
+            257 => {
+                if self.scanline == 261 {
+                    self.sprites.found = 0;
+                    self.sprites.sprite_zero_found = false;
+                } else {
+                    self.evaluate_sprites();
+                }
+                self.sprites.count = self.sprites.found;
+                self.sprites.sprite_zero_in_slots = self.sprites.sprite_zero_found;
+            }
+            258..=320 => {
+                // OAMADDR is cleared during each of the sprite tile loading ticks
+                self.oam_addr = 0;
+
+                let slot = ((self.cycles - 257) / 8) as usize;
+                match (self.cycles - 257) % 8 {
+                    4 => self.sprites.slots[slot].pattern_lo = self.fetch_sprite_pattern(bus, slot, 0),
+                    6 => self.sprites.slots[slot].pattern_hi = self.fetch_sprite_pattern(bus, slot, 8),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Sprite pixel at screen column x for the current scanline, if an opaque one is there
+    pub(super) fn sprite_pixel(&self, x: usize) -> Option<SpritePixel> {
+        if !self.mask.contains(MaskRegister::SHOW_SPRITES) {
+            return None;
+        }
+        if x < 8 && !self.mask.contains(MaskRegister::SHOW_SPRITES_LEFT) {
+            return None;
+        }
+
+        for (index, slot) in self.sprites.slots[..self.sprites.count].iter().enumerate() {
+            let column = x.wrapping_sub(slot.x as usize);
+            if column >= 8 {
+                continue;
+            }
+
+            let bit = 0x80 >> column;
+            let pixel = ((slot.pattern_hi & bit != 0) as u8) << 1 | (slot.pattern_lo & bit != 0) as u8;
+            if pixel == 0 {
+                continue;
+            }
+
+            return Some(SpritePixel {
+                palette: slot.attribute & ATTRIBUTE_PALETTE,
+                pixel,
+                behind_background: slot.attribute & ATTRIBUTE_PRIORITY != 0,
+                sprite_zero: index == 0 && self.sprites.sprite_zero_in_slots,
+            });
+        }
+
+        None
+    }
+
+    // https://www.nesdev.org/wiki/PPU_sprite_evaluation#Details
+    fn evaluate_sprites(&mut self) {
+        let height = self.sprite_height();
+
+        self.sprites.secondary_oam = [0xFF; MAX_SPRITES_PER_SCANLINE * 4];
+        self.sprites.found = 0;
+        self.sprites.sprite_zero_found = false;
+
+        let mut n = 0;
+        while n < 64 && self.sprites.found < MAX_SPRITES_PER_SCANLINE {
+            let y = self.oam[n * 4];
+            if self.is_sprite_in_range(y, height) {
+                let target = self.sprites.found * 4;
+                self.sprites.secondary_oam[target..target + 4].copy_from_slice(&self.oam[n * 4..n * 4 + 4]);
+                if n == 0 {
+                    self.sprites.sprite_zero_found = true;
+                }
+                self.sprites.found += 1;
+            }
+            n += 1;
+        }
+
+        // The hardware keeps scanning with a diagonally incrementing byte offset,
+        // so the overflow flag has both false positives and false negatives
+        let mut m = 0;
+        while n < 64 {
+            let y = self.oam[n * 4 + m];
+            if self.is_sprite_in_range(y, height) {
+                self.status.insert(StatusRegister::SPRITE_OVERFLOW);
+                break;
+            }
+            n += 1;
+            m = (m + 1) & 0x03;
+        }
+    }
+
+    fn is_sprite_in_range(&self, y: u8, height: i16) -> bool {
+        let row = self.scanline - y as i16;
+        (0..height).contains(&row)
+    }
+
+    fn fetch_sprite_pattern(&mut self, bus: &mut dyn PpuBus, slot: usize, plane: u16) -> u8 {
+        if slot >= self.sprites.count {
+            // empty slots still fetch tile $FF, but the result is transparent
+            let _ = self.mem_read(bus, self.sprite_table() | 0x0FF0 | plane);
+            return 0;
+        }
+
+        let sprite = &self.sprites.secondary_oam[slot * 4..slot * 4 + 4];
+        let (y, tile, attribute, x) = (sprite[0], sprite[1], sprite[2], sprite[3]);
+
+        let height = self.sprite_height();
+        let mut row = (self.scanline - y as i16) as u16;
+        if attribute & ATTRIBUTE_FLIP_VERTICAL != 0 {
+            row = height as u16 - 1 - row;
+        }
+
+        let address = if height == 16 {
+            let table = (tile as u16 & 0x01) << 12;
+            let tile = (tile & 0xFE) as u16 + (row >> 3);
+            table | (tile << 4) | (row & 0x07)
+        } else {
+            self.sprite_table() | ((tile as u16) << 4) | row
+        };
+
+        let mut pattern = self.mem_read(bus, address + plane);
+        if attribute & ATTRIBUTE_FLIP_HORIZONTAL != 0 {
+            pattern = pattern.reverse_bits();
+        }
+
+        self.sprites.slots[slot].x = x;
+        self.sprites.slots[slot].attribute = attribute;
+
+        pattern
+    }
+
+    fn sprite_table(&self) -> u16 {
+        if self.ctrl.contains(ControlRegister::SPRITE_PATTERN_ADDR) { 0x1000 } else { 0x0000 }
+    }
+
+    fn sprite_height(&self) -> i16 {
+        if self.ctrl.contains(ControlRegister::SPRITE_SIZE) { 16 } else { 8 }
+    }
+}
@@ -2,6 +2,12 @@ pub struct RAM {
     memory: [u8; 0x800] // 2KB RAM
 }
 
+impl Default for RAM {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RAM {
     pub fn new() -> Self {
         RAM { memory: [0; 0x800] }
@@ -11,6 +17,11 @@ impl RAM {
         self.memory[(address & 0x07FF) as usize]
     }
 
+    // RAM reads have no side effects
+    pub fn peek(&self, address: u16) -> u8 {
+        self.read(address)
+    }
+
     pub fn write(&mut self, address: u16, data: u8) {
         self.memory[(address & 0x07FF) as usize] = data;
     }
@@ -1,8 +1,25 @@
 
-
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Mirroring {
     Horizontal,
     Vertical,
     FourScreen,
-}
\ No newline at end of file
+    SingleScreenLower,
+    SingleScreenUpper,
+}
+
+impl Mirroring {
+    // Index into nametable memory for a PPU address in $2000-$3EFF
+    // https://www.nesdev.org/wiki/Mirroring#Nametable_Mirroring
+    pub fn vram_index(&self, address: u16) -> usize {
+        let table = (address >> 10) & 0x03;
+        let physical_table = match self {
+            Mirroring::Horizontal => table >> 1,
+            Mirroring::Vertical => table & 0x01,
+            Mirroring::FourScreen => table,
+            Mirroring::SingleScreenLower => 0,
+            Mirroring::SingleScreenUpper => 1,
+        };
+        (physical_table * 0x400 + (address & 0x03FF)) as usize
+    }
+}
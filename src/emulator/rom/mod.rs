@@ -5,6 +5,7 @@ pub mod mirroring;
 pub struct ROM {
     pub prg_rom: Vec<u8>,
     chr_rom: Vec<u8>,
+    chr_ram: bool,
     sram: Vec<u8>,
     expansion: Vec<u8>,
     mapper: u8,
@@ -15,9 +16,14 @@ pub struct ROM {
 
 impl ROM {
     pub fn new(prg_rom: Vec<u8>, chr_rom: Vec<u8>, mapper: u8, mirroring: Mirroring, battery: bool) -> Self {
+        // cartridges without CHR ROM carry 8KB of CHR RAM instead
+        let chr_ram = chr_rom.is_empty();
+        let chr_rom = if chr_ram { vec![0; 8192] } else { chr_rom };
+
         ROM {
             prg_rom,
             chr_rom,
+            chr_ram,
             sram: vec![0; 8192], // 8KB
             expansion: Vec::new(),
             mapper,
@@ -26,7 +32,13 @@ impl ROM {
         }
     }
 
-    pub fn read_prg(&self, addr: u16) -> u8 {
+    // CPU read, mappers that latch on reads update their state here
+    pub fn read_prg(&mut self, addr: u16) -> u8 {
+        self.peek_prg(addr)
+    }
+
+    // Same value as read_prg without touching mapper state, for debuggers
+    pub fn peek_prg(&self, addr: u16) -> u8 {
         let prg_addr = (addr - 0x8000) as usize;
         match self.mapper {
             0 => { // NROM
@@ -41,7 +53,7 @@ impl ROM {
         }
     }
 
-    pub fn write_prg(&mut self, address: u16, data: u8) {
+    pub fn write_prg(&mut self, _address: u16, _data: u8) {
         match self.mapper {
             0 => {}, // NROM - read only
             1 => {   // MMC1
@@ -51,11 +63,45 @@ impl ROM {
         }
     }
 
-    pub fn read_sram(&self, addr: u16) -> u8 {
+    // PPU read, MMC2/MMC4 switch CHR banks when the PPU fetches certain tiles
+    pub fn read_chr(&mut self, addr: u16) -> u8 {
+        self.peek_chr(addr)
+    }
+
+    pub fn peek_chr(&self, addr: u16) -> u8 {
+        match self.mapper {
+            0 => self.chr_rom[addr as usize % self.chr_rom.len()], // NROM
+            // todo other mappers
+            _ => panic!("Unsupported mapper: {}", self.mapper)
+        }
+    }
+
+    pub fn write_chr(&mut self, addr: u16, data: u8) {
+        if !self.chr_ram {
+            return;
+        }
+
+        match self.mapper {
+            0 => { // NROM
+                let len = self.chr_rom.len();
+                self.chr_rom[addr as usize % len] = data;
+            }
+            // todo other mappers
+            _ => panic!("Unsupported mapper: {}", self.mapper)
+        }
+    }
+
+    // Current nametable layout, mappers may switch it at runtime
+    pub fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    // None when nothing is mapped there, the CPU then reads open bus
+    pub fn read_sram(&self, addr: u16) -> Option<u8> {
         if self.battery {
-            self.sram[(addr - 0x6000) as usize]
+            Some(self.sram[(addr - 0x6000) as usize])
         } else {
-            0
+            None
         }
     }
 
@@ -65,11 +111,11 @@ impl ROM {
         }
     }
 
-    pub fn read_expansion(&self, addr: u16) -> u8 {
+    pub fn read_expansion(&self, addr: u16) -> Option<u8> {
         if !self.expansion.is_empty() {
-            self.expansion[(addr - 0x4020) as usize]
+            Some(self.expansion[(addr - 0x4020) as usize])
         } else {
-            0
+            None
         }
     }
 
@@ -140,7 +186,7 @@ mod tests {
         assert_eq!(rom.chr_rom.len(), 8192);
         assert_eq!(rom.mapper, 0);
         assert_eq!(rom.mirroring, Mirroring::Horizontal);
-        assert_eq!(rom.battery, false);
+        assert!(!rom.battery);
     }
 
     #[test]
@@ -156,25 +202,50 @@ mod tests {
     #[test]
     fn test_prg_rom_reading() {
         let test_data = create_test_rom();
-        let rom = ROM::from_nes_file(&test_data).unwrap();
+        let mut rom = ROM::from_nes_file(&test_data).unwrap();
 
         assert_eq!(rom.read_prg(0x8000), test_data[16]);
 
         assert_eq!(rom.read_prg(0xBFFF), test_data[16 + 0x3FFF]);
 
         assert_eq!(rom.read_prg(0xC000), test_data[16 + 0x4000]);
+        assert_eq!(rom.peek_prg(0xC000), test_data[16 + 0x4000]);
     }
 
     #[test]
     fn test_prg_rom_mirroring() {
         let mut test_data = create_test_rom();
         test_data[4] = 1; // 1 * 16KB PRG ROM
-        let rom = ROM::from_nes_file(&test_data).unwrap();
+        let mut rom = ROM::from_nes_file(&test_data).unwrap();
 
         assert_eq!(rom.read_prg(0x8000), rom.read_prg(0xC000));
         assert_eq!(rom.read_prg(0x9FFF), rom.read_prg(0xDFFF));
     }
 
+    #[test]
+    fn test_chr_rom_is_read_only() {
+        let test_data = create_test_rom();
+        let mut rom = ROM::from_nes_file(&test_data).unwrap();
+
+        assert_eq!(rom.read_chr(0x0123), test_data[16 + 32768 + 0x0123]);
+
+        rom.write_chr(0x0123, 0xAB);
+        assert_eq!(rom.read_chr(0x0123), test_data[16 + 32768 + 0x0123]);
+    }
+
+    #[test]
+    fn test_chr_ram() {
+        let mut test_data = create_test_rom();
+        test_data[5] = 0; // no CHR ROM
+        test_data.truncate(16 + 32768);
+        let mut rom = ROM::from_nes_file(&test_data).unwrap();
+
+        assert_eq!(rom.chr_rom.len(), 8192);
+
+        rom.write_chr(0x1FFF, 0xAB);
+        assert_eq!(rom.read_chr(0x1FFF), 0xAB);
+    }
+
     #[test]
     fn test_sram_operations() {
         let test_data = create_test_rom();
@@ -182,10 +253,10 @@ mod tests {
         rom.battery = true;
 
         rom.write_sram(0x6000, 0x42);
-        assert_eq!(rom.read_sram(0x6000), 0x42);
+        assert_eq!(rom.read_sram(0x6000), Some(0x42));
 
         rom.write_sram(0x7FFF, 0xFF);
-        assert_eq!(rom.read_sram(0x7FFF), 0xFF);
+        assert_eq!(rom.read_sram(0x7FFF), Some(0xFF));
     }
 
     #[test]
@@ -197,6 +268,6 @@ mod tests {
         assert!(rom.battery);
 
         rom.write_sram(0x6000, 0x42);
-        assert_eq!(rom.read_sram(0x6000), 0x42);
+        assert_eq!(rom.read_sram(0x6000), Some(0x42));
     }
 }
\ No newline at end of file
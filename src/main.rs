@@ -1,19 +1,71 @@
-use std::fs;
+use std::{env, fs, process};
 use nesrs::emulator::bus::Bus;
-use nesrs::emulator::bus::cpu_bus::CpuBus;
-use nesrs::emulator::cpu::CPU;
+use nesrs::emulator::cpu::disasm;
+use nesrs::emulator::cpu::{TimingMode, CPU};
 use nesrs::emulator::rom::ROM;
 
+const PRG_BANK_SIZE: usize = 16384;
+
 fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    match args.get(1).map(String::as_str) {
+        Some("disasm") => disassemble(&args[2..]),
+        _ => run(),
+    }
+}
+
+fn run() {
     let rom_data = fs::read("./test_rom/test_program.nes").expect("Could not read ROM file");
     let rom = ROM::from_nes_file(&rom_data).expect("Failed to parse NES ROM");
 
     let bus = Bus::new(rom);
-    let mut cpu = CPU::new(Box::new(bus));
+    let mut cpu = CPU::new(bus);
+    cpu.timing_mode = TimingMode::Cycle;
 
-    cpu.program_counter = 0x8000;
+    cpu.power_on();
 
-    cpu.interpret(0xFFFF);
+    for frame in 0..60 {
+        let result = cpu.run_frame();
+        if result.is_halted() {
+            println!("CPU halted in frame {}: {:?} at {:#06X}", frame, result, cpu.program_counter);
+            break;
+        }
+    }
 
     println!("program end");
 }
+
+// nesrs disasm <file.nes> [bank] [origin]
+// Disassembles one 16KB PRG ROM bank, the origin defaults to $8000 for the first bank and $C000 for the others
+fn disassemble(args: &[String]) {
+    let usage = || -> ! {
+        eprintln!("usage: nesrs disasm <file.nes> [bank] [origin]");
+        process::exit(1);
+    };
+
+    let path = args.first().unwrap_or_else(|| usage());
+    let bank = match args.get(1) {
+        Some(bank) => bank.parse::<usize>().unwrap_or_else(|_| usage()),
+        None => 0,
+    };
+    let origin = match args.get(2) {
+        Some(origin) => u16::from_str_radix(origin.trim_start_matches("0x").trim_start_matches('$'), 16)
+            .unwrap_or_else(|_| usage()),
+        None if bank == 0 => 0x8000,
+        None => 0xC000,
+    };
+
+    let rom_data = fs::read(path).expect("Could not read ROM file");
+    let rom = ROM::from_nes_file(&rom_data).expect("Failed to parse NES ROM");
+
+    let start = bank * PRG_BANK_SIZE;
+    if start >= rom.prg_rom.len() {
+        eprintln!("bank {} out of range, the ROM has {} PRG banks", bank, rom.prg_rom.len() / PRG_BANK_SIZE);
+        process::exit(1);
+    }
+    let end = (start + PRG_BANK_SIZE).min(rom.prg_rom.len());
+
+    let instructions = disasm::disassemble(&rom.prg_rom[start..end], origin);
+    print!("{}", disasm::listing(&instructions, &disasm::Labels::new()));
+}
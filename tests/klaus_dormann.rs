@@ -0,0 +1,218 @@
+// Klaus Dormann's 6502 functional and interrupt tests: https://github.com/Klaus2m5/6502_65C02_functional_tests
+// The prebuilt binaries from bin_files are not part of the repository, so the tests are ignored by default.
+// Copy each .bin with the .lst listing next to it into test_rom/klaus or point KLAUS_DORMANN_TESTS at them,
+// then run the tests explicitly:
+//   cargo test --release --test klaus_dormann -- --ignored
+// Every check ends in a trap, a branch or jump to itself. The addresses the runner needs (start, success
+// trap, test number, feedback port) are taken from the listing of the binary being loaded, not hard-coded.
+
+use std::path::PathBuf;
+use std::{env, fs};
+
+use nesrs::emulator::bus::cpu_bus::{CpuBus, IrqSource};
+use nesrs::emulator::bus::mock_bus::MockBus;
+use nesrs::emulator::cpu::{CpuVariant, StepResult, CPU};
+
+const DEFAULT_ROOT: &str = "test_rom/klaus";
+
+// bin_files holds full 64KB images
+const IMAGE_SIZE: usize = 0x10000;
+
+// I_port bits (I_drive = 1, totem pole): a set bit drives the line
+const FEEDBACK_IRQ: u8 = 0b0000_0001;
+const FEEDBACK_NMI: u8 = 0b0000_0010;
+
+// a hung test that never traps
+const MAX_CYCLES: usize = 200_000_000;
+
+// MockBus with the interrupt test's feedback register: IRQ follows its bit, NMI fires on the rising edge
+struct FeedbackBus {
+    bus: MockBus,
+    port: Option<u16>,
+}
+
+impl CpuBus for FeedbackBus {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.bus.read(addr)
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        self.bus.peek(addr)
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        if Some(addr) == self.port {
+            let previous = self.bus.memory[addr as usize];
+
+            if data & FEEDBACK_IRQ != 0 {
+                self.bus.assert_irq(IrqSource::EXTERNAL);
+            } else {
+                self.bus.acknowledge_irq(IrqSource::EXTERNAL);
+            }
+
+            if data & FEEDBACK_NMI != 0 && previous & FEEDBACK_NMI == 0 {
+                self.bus.nmi_interrupt = Some(1);
+            }
+        }
+
+        self.bus.write(addr, data);
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        self.bus.tick(cycles);
+    }
+
+    fn fetch_nmi(&mut self) -> Option<u8> {
+        self.bus.fetch_nmi()
+    }
+
+    fn assert_irq(&mut self, source: IrqSource) {
+        self.bus.assert_irq(source);
+    }
+
+    fn acknowledge_irq(&mut self, source: IrqSource) {
+        self.bus.acknowledge_irq(source);
+    }
+
+    fn is_irq_asserted(&self) -> bool {
+        self.bus.is_irq_asserted()
+    }
+
+    fn is_frame_complete(&mut self) -> bool {
+        self.bus.is_frame_complete()
+    }
+
+    fn ppu_position(&self) -> (u16, u16) {
+        self.bus.ppu_position()
+    }
+}
+
+#[test]
+#[ignore = "needs the Klaus Dormann test binaries, see the top of this file"]
+fn test_functional() {
+    // the binary includes the decimal mode tests, the 2A03 would fail them
+    run("6502_functional_test", CpuVariant::Nmos6502, Some("test_case"), None);
+}
+
+#[test]
+#[ignore = "needs the Klaus Dormann test binaries, see the top of this file"]
+fn test_interrupt() {
+    run("6502_interrupt_test", CpuVariant::Nmos6502, None, Some("I_port"));
+}
+
+#[test]
+fn test_listing_addresses() {
+    let listing = Listing(String::from("\
+0400 =                  code_segment = $400
+0200 =                  test_case   =   $200
+                        success macro
+                                jmp *
+                                endm
+3465 : a9f0                     lda #$f0
+3467 : 8d0002                   sta test_case
+                                success
+                       >        ;db     $db
+3469 : 4c6934          >        jmp *           ;test passed, no errors
+"));
+
+    assert_eq!(listing.symbol("code_segment"), Ok(0x0400));
+    assert_eq!(listing.symbol("test_case"), Ok(0x0200));
+    assert!(listing.symbol("I_port").is_err());
+    assert_eq!(listing.success_trap(), Ok(0x3469));
+}
+
+fn run(name: &str, variant: CpuVariant, test_case: Option<&str>, feedback_port: Option<&str>) {
+    let root = env::var_os("KLAUS_DORMANN_TESTS").map_or_else(|| PathBuf::from(DEFAULT_ROOT), PathBuf::from);
+    let read = |extension: &str| {
+        let path = root.join(name).with_extension(extension);
+        fs::read(&path).unwrap_or_else(|error| panic!("{}: {}, it is needed to run this test", path.display(), error))
+    };
+
+    let image = read("bin");
+    assert_eq!(image.len(), IMAGE_SIZE, "{}.bin is not a 64KB image", name);
+    let listing = Listing(String::from_utf8_lossy(&read("lst")).into_owned());
+    let address = |result: Result<u16, String>| result.unwrap_or_else(|error| panic!("{}.lst: {}", name, error));
+
+    let start = address(listing.symbol("code_segment"));
+    let success = address(listing.success_trap());
+    let test_case = test_case.map(|symbol| address(listing.symbol(symbol)));
+    let port = feedback_port.map(|symbol| address(listing.symbol(symbol)));
+
+    let mut bus = MockBus::new();
+    bus.load_program(&image, 0x0000);
+
+    let mut cpu = CPU::new(FeedbackBus { bus, port });
+    cpu.variant = variant;
+    cpu.program_counter = start;
+
+    let trap = run_until_trap(&mut cpu);
+
+    if trap != success {
+        let test_case = test_case
+            .map(|address| format!(", test ${:02X}", cpu.bus().peek(address)))
+            .unwrap_or_default();
+
+        panic!("{} failed: trapped at ${:04X}{} after {} cycles", name, trap, test_case, cpu.state().cycles);
+    }
+}
+
+// as65 listing of a test, lines start with the address or value as 4 hex digits
+struct Listing(String);
+
+impl Listing {
+    // `0200 =   test_case = $200`
+    fn symbol(&self, name: &str) -> Result<u16, String> {
+        self.0.lines()
+            .find_map(|line| {
+                let (value, rest) = line.split_once('=')?;
+                let rest = rest.trim_start().strip_prefix(name)?;
+                if !rest.trim_start().starts_with('=') {
+                    return None;
+                }
+                u16::from_str_radix(value.trim(), 16).ok()
+            })
+            .ok_or_else(|| format!("{} not found", name))
+    }
+
+    // The `jmp *` assembled for the `success` macro: `3469 : 4c6934   >   jmp *`
+    fn success_trap(&self) -> Result<u16, String> {
+        let mut in_success = false;
+
+        for line in self.0.lines() {
+            let source: Vec<&str> = line.split(|c: char| c.is_whitespace() || c == '>').filter(|word| !word.is_empty()).collect();
+            if source.contains(&"success") && !source.contains(&"macro") {
+                in_success = true;
+                continue;
+            }
+
+            let Some((address, bytes)) = line.split_once(':') else {
+                continue;
+            };
+            let Ok(address) = u16::from_str_radix(address.trim(), 16) else {
+                continue;
+            };
+            let [low, high] = address.to_le_bytes();
+            if in_success && bytes.trim_start().to_lowercase().starts_with(&format!("4c{:02x}{:02x}", low, high)) {
+                return Ok(address);
+            }
+        }
+
+        Err("no jmp * after the success macro".to_string())
+    }
+}
+// Steps until an instruction leaves the program counter where it was, returns that address
+fn run_until_trap(cpu: &mut CPU<FeedbackBus>) -> u16 {
+    loop {
+        let address = cpu.state().program_counter;
+
+        match cpu.step() {
+            StepResult::Executed { .. } if cpu.state().program_counter == address => return address,
+            result if result.is_halted() => panic!("{:?} at ${:04X}", result, address),
+            _ => {}
+        }
+
+        if cpu.state().cycles > MAX_CYCLES {
+            panic!("no trap after {} cycles, program counter at ${:04X}", MAX_CYCLES, address);
+        }
+    }
+}
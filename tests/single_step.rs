@@ -0,0 +1,228 @@
+// Per-instruction test vectors: https://github.com/SingleStepTests/65x02
+// Each case has the registers and memory before and after one instruction and every bus cycle in between.
+// The vectors are not part of the repository, so the tests are ignored by default. Clone them into
+// test_rom/65x02 or point SINGLE_STEP_TESTS at a copy, then run the tests explicitly:
+//   test_rom/65x02/nes6502/v1/00.json ... ff.json
+//   cargo test --test single_step -- --ignored
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use nesrs::emulator::bus::mock_bus::{BusAccessKind, MockBus};
+use nesrs::emulator::cpu::{CpuFlags, CpuState, CpuVariant, CPU};
+use serde_json::Value;
+
+const DEFAULT_ROOT: &str = "test_rom/65x02";
+
+// B and bit 5 of the P register don't affect execution and the CPU keeps its own values for them,
+// their pushed copies are still checked through ram
+const IGNORED_FLAGS: u8 = CpuFlags::BREAK.bits() | CpuFlags::UNUSED.bits();
+
+struct Snapshot {
+    pc: u16,
+    s: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    ram: Vec<(u16, u8)>,
+}
+
+struct Case {
+    name: String,
+    initial: Snapshot,
+    expected: Snapshot,
+    cycles: Vec<(u16, u8, BusAccessKind)>,
+}
+
+// Failed cases of one operation code
+struct Failures {
+    count: usize,
+    total: usize,
+    first: String,
+}
+
+#[test]
+#[ignore = "needs the SingleStepTests vectors, see the top of this file"]
+fn test_nes6502() {
+    run_variant("nes6502", CpuVariant::Ricoh2A03);
+}
+
+#[test]
+#[ignore = "needs the SingleStepTests vectors, see the top of this file"]
+fn test_6502() {
+    run_variant("6502", CpuVariant::Nmos6502);
+}
+
+#[test]
+#[ignore = "needs the SingleStepTests vectors, see the top of this file"]
+fn test_wdc65c02() {
+    run_variant("wdc65c02", CpuVariant::Cmos65C02);
+}
+
+fn run_variant(name: &str, variant: CpuVariant) {
+    let root = env::var_os("SINGLE_STEP_TESTS").map_or_else(|| PathBuf::from(DEFAULT_ROOT), PathBuf::from);
+    let directory = root.join(name).join("v1");
+    if !directory.is_dir() {
+        panic!("{} not found, the {} vectors are needed to run this test", directory.display(), name);
+    }
+
+    let mut cpu = CPU::new(MockBus::new());
+    cpu.variant = variant;
+    cpu.bus_mut().record_accesses = true;
+
+    let mut failures: BTreeMap<u8, Failures> = BTreeMap::new();
+    let mut total = 0;
+
+    for operation_code in 0..=0xFF_u8 {
+        let path = directory.join(format!("{:02x}.json", operation_code));
+        if !path.exists() {
+            continue;
+        }
+
+        let cases = load_cases(&path).unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+        for case in &cases {
+            if let Err(mismatch) = run_case(&mut cpu, case) {
+                let entry = failures.entry(operation_code).or_insert(Failures { count: 0, total: cases.len(), first: mismatch });
+                entry.count += 1;
+            }
+        }
+        total += cases.len();
+    }
+
+    if !failures.is_empty() {
+        let report: Vec<String> = failures.iter()
+            .map(|(code, failures)| {
+                format!("${:02X}: {}/{} failed, first: {}", code, failures.count, failures.total, failures.first)
+            })
+            .collect();
+
+        panic!("{} of {} operation codes failed ({} cases run)\n{}", failures.len(), name, total, report.join("\n"));
+    }
+}
+
+// Runs one instruction, Err lists every field that differs
+fn run_case(cpu: &mut CPU<MockBus>, case: &Case) -> Result<(), String> {
+    let initial = &case.initial;
+    for &(address, value) in &initial.ram {
+        cpu.bus_mut().memory[address as usize] = value;
+    }
+    cpu.bus_mut().accesses.clear();
+
+    cpu.set_state(CpuState {
+        register_a: initial.a,
+        register_x: initial.x,
+        register_y: initial.y,
+        stack_pointer: initial.s,
+        program_counter: initial.pc,
+        flags: CpuFlags::from_bits_retain(initial.p),
+        cycles: 0,
+        nmi_pending: false,
+        pending_interrupt: None,
+        jammed: false,
+        waiting: false,
+    });
+
+    cpu.step();
+
+    let state = cpu.state();
+    let expected = &case.expected;
+    let mut mismatches = Vec::new();
+
+    let mut compare = |field: &str, expected: u16, actual: u16| {
+        if expected != actual {
+            mismatches.push(format!("{} expected ${:02X} got ${:02X}", field, expected, actual));
+        }
+    };
+    compare("pc", expected.pc, state.program_counter);
+    compare("s", expected.s as u16, state.stack_pointer as u16);
+    compare("a", expected.a as u16, state.register_a as u16);
+    compare("x", expected.x as u16, state.register_x as u16);
+    compare("y", expected.y as u16, state.register_y as u16);
+    compare("p", (expected.p & !IGNORED_FLAGS) as u16, (state.flags.bits() & !IGNORED_FLAGS) as u16);
+
+    for &(address, value) in &expected.ram {
+        compare(&format!("ram[${:04X}]", address), value as u16, cpu.bus().memory[address as usize] as u16);
+    }
+
+    let accesses: Vec<(u16, u8, BusAccessKind)> = cpu.bus().accesses.iter()
+        .map(|access| (access.address, access.data, access.kind))
+        .collect();
+    if accesses != case.cycles {
+        mismatches.push(format!("cycles expected {:02X?} got {:02X?}", case.cycles, accesses));
+    }
+
+    // leave the memory zeroed for the next case
+    let touched: Vec<u16> = initial.ram.iter().chain(&expected.ram).map(|&(address, _)| address)
+        .chain(accesses.iter().map(|&(address, _, _)| address))
+        .collect();
+    for address in touched {
+        cpu.bus_mut().memory[address as usize] = 0;
+    }
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("\"{}\" {}", case.name, mismatches.join(", ")))
+    }
+}
+
+fn load_cases(path: &Path) -> Result<Vec<Case>, String> {
+    let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let json: Value = serde_json::from_str(&text).map_err(|error| error.to_string())?;
+
+    json.as_array()
+        .ok_or("expected an array of cases")?
+        .iter()
+        .map(parse_case)
+        .collect()
+}
+
+fn parse_case(json: &Value) -> Result<Case, String> {
+    let name = json["name"].as_str().ok_or("case without a name")?.to_string();
+
+    let cycles = json["cycles"].as_array()
+        .ok_or_else(|| format!("{}: no cycles", name))?
+        .iter()
+        .map(|cycle| {
+            let kind = match cycle[2].as_str() {
+                Some("read") => BusAccessKind::Read,
+                Some("write") => BusAccessKind::Write,
+                _ => return Err(format!("{}: bad cycle {}", name, cycle)),
+            };
+            Ok((number(&cycle[0])?, number(&cycle[1])?, kind))
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(Case {
+        initial: parse_snapshot(&json["initial"]).map_err(|error| format!("{}: {}", name, error))?,
+        expected: parse_snapshot(&json["final"]).map_err(|error| format!("{}: {}", name, error))?,
+        cycles,
+        name,
+    })
+}
+
+fn parse_snapshot(json: &Value) -> Result<Snapshot, String> {
+    let ram = json["ram"].as_array()
+        .ok_or("no ram")?
+        .iter()
+        .map(|entry| Ok((number(&entry[0])?, number(&entry[1])?)))
+        .collect::<Result<_, String>>()?;
+
+    Ok(Snapshot {
+        pc: number(&json["pc"])?,
+        s: number(&json["s"])?,
+        a: number(&json["a"])?,
+        x: number(&json["x"])?,
+        y: number(&json["y"])?,
+        p: number(&json["p"])?,
+        ram,
+    })
+}
+
+fn number<T: TryFrom<u64>>(json: &Value) -> Result<T, String> {
+    json.as_u64()
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or_else(|| format!("bad number {}", json))
+}